@@ -4,7 +4,7 @@ use std::{
     path::PathBuf,
 };
 
-use recently_used_xbel::update_recently_used;
+use recently_used_xbel::{update_recently_used, UpdateOptions};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("creating file");
@@ -21,7 +21,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         &path,
         "org.cosmic.test-script".to_string(),
         String::from("test-script"),
-        None,
+        &UpdateOptions::default(),
     );
 
     println!("res: {:?}", res);
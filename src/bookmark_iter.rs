@@ -0,0 +1,71 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::custom_reader::XbelReader;
+use crate::{Bookmark, Error};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// Lazily reads the bookmarks of a recently-used.xbel document.
+///
+/// Bookmarks are deserialized one at a time as the iterator advances, so callers interested
+/// in only the first few entries don't pay for reading the entire file. An invalid bookmark
+/// yields an error without ending the iteration; a malformed document ends it.
+///
+/// ```no_run
+/// use recently_used_xbel::BookmarkIter;
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let path = recently_used_xbel::dir().unwrap();
+///
+///     for bookmark in BookmarkIter::open(path)?.take(10) {
+///         println!("{}", bookmark?.href);
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub struct BookmarkIter<R> {
+    reader: XbelReader<R>,
+    failed: bool,
+}
+
+impl<R: BufRead> BookmarkIter<R> {
+    /// Reads the header of the document, leaving the bookmarks to be read by the iterator.
+    pub fn new(reader: R) -> Result<Self, Error> {
+        let mut reader = XbelReader::new(reader)?;
+        reader.read_root().map_err(Error::Deserialization)?;
+        Ok(Self {
+            reader,
+            failed: false,
+        })
+    }
+}
+
+impl BookmarkIter<BufReader<File>> {
+    /// Opens the recently-used.xbel file at the given location.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let file = File::open(path).map_err(Error::Read)?;
+        Self::new(BufReader::new(file))
+    }
+}
+
+impl<R: BufRead> Iterator for BookmarkIter<R> {
+    type Item = Result<Bookmark, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        match self.reader.next_bookmark() {
+            Ok(Some(read)) => Some(read.result.map_err(Error::Deserialization)),
+            Ok(None) => None,
+            Err(why) => {
+                self.failed = true;
+                Some(Err(Error::Deserialization(why)))
+            }
+        }
+    }
+}
@@ -0,0 +1,301 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{
+    Application, Applications, Bookmark, Info, Metadata, MimeType, Prolog, RecentlyUsed, Unknown,
+    BOOKMARK_NAMESPACE, MIME_NAMESPACE, XBEL_VERSION,
+};
+use serde::de::{self, value::MapAccessDeserializer, Deserializer, MapAccess, Visitor};
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::fmt;
+use std::marker::PhantomData;
+
+/// A borrowed view of [`RecentlyUsed`], referencing the buffer it was parsed from.
+///
+/// Values are only copied when they contain escaped characters, which avoids allocating for
+/// every attribute when a large file is parsed repeatedly.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename = "xbel")]
+pub struct RecentlyUsedRef<'a> {
+    /// The version of XBEL the document conforms to.
+    #[serde(rename = "@version", borrow, default = "xbel_version")]
+    pub version: Cow<'a, str>,
+    #[serde(rename = "@xmlns:bookmark", borrow, default = "bookmark_namespace")]
+    pub xmlns_bookmark: Cow<'a, str>,
+    #[serde(rename = "@xmlns:mime", borrow, default = "mime_namespace")]
+    pub xmlns_mime: Cow<'a, str>,
+
+    /// The title of the collection.
+    #[serde(default, borrow)]
+    pub title: Option<Cow<'a, str>>,
+    /// A description of the collection.
+    #[serde(default, borrow)]
+    pub desc: Option<Cow<'a, str>>,
+    /// Metadata about the collection as a whole.
+    #[serde(default, borrow)]
+    pub info: Option<InfoRef<'a>>,
+
+    /// Files that have been recently used.
+    #[serde(
+        rename = "bookmark",
+        default,
+        borrow,
+        deserialize_with = "without_text"
+    )]
+    pub bookmarks: Vec<BookmarkRef<'a>>,
+}
+
+fn xbel_version<'a>() -> Cow<'a, str> {
+    Cow::Borrowed(XBEL_VERSION)
+}
+
+fn bookmark_namespace<'a>() -> Cow<'a, str> {
+    Cow::Borrowed(BOOKMARK_NAMESPACE)
+}
+
+fn mime_namespace<'a>() -> Cow<'a, str> {
+    Cow::Borrowed(MIME_NAMESPACE)
+}
+
+/// Deserializes a sequence of elements, skipping any text or CDATA sections between them.
+///
+/// The deserializer otherwise hands text following an element to the sequence as an item of
+/// its own, which fails to deserialize as an element.
+fn without_text<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    let items = Vec::<ElementOrText<T>>::deserialize(deserializer)?;
+    Ok(items
+        .into_iter()
+        .filter_map(|item| match item {
+            ElementOrText::Element(element) => Some(element),
+            ElementOrText::Text => None,
+        })
+        .collect())
+}
+
+enum ElementOrText<T> {
+    Element(T),
+    Text,
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for ElementOrText<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ElementOrTextVisitor<T>(PhantomData<T>);
+
+        impl<'de, T: Deserialize<'de>> Visitor<'de> for ElementOrTextVisitor<T> {
+            type Value = ElementOrText<T>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("an element or text")
+            }
+
+            fn visit_str<E: de::Error>(self, _: &str) -> Result<Self::Value, E> {
+                Ok(ElementOrText::Text)
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+                T::deserialize(MapAccessDeserializer::new(map)).map(ElementOrText::Element)
+            }
+        }
+
+        deserializer.deserialize_any(ElementOrTextVisitor(PhantomData))
+    }
+}
+
+/// Deserializes the count of an application as leniently as
+/// [`Application::count`] describes.
+fn count<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    let count = Cow::<str>::deserialize(deserializer)?;
+    Ok(crate::custom_reader::parse_count(&count))
+}
+
+/// A borrowed view of [`Bookmark`].
+#[derive(Debug, Clone, Deserialize)]
+pub struct BookmarkRef<'a> {
+    /// The location of the file.
+    #[serde(rename = "@href", borrow)]
+    pub href: Cow<'a, str>,
+    /// When the file was added to the list.
+    #[serde(rename = "@added", borrow)]
+    pub added: Cow<'a, str>,
+    /// When the file was last modified, if known.
+    #[serde(rename = "@modified", default, borrow)]
+    pub modified: Option<Cow<'a, str>>,
+    /// When the file was last visited, if known.
+    #[serde(rename = "@visited", default, borrow)]
+    pub visited: Option<Cow<'a, str>>,
+    /// The title of the bookmark.
+    #[serde(default, borrow)]
+    pub title: Option<Cow<'a, str>>,
+    /// A description of the bookmark.
+    #[serde(default, borrow)]
+    pub desc: Option<Cow<'a, str>>,
+    /// Additional metadata and applications related to the bookmark.
+    #[serde(rename = "info", borrow)]
+    pub info: Option<InfoRef<'a>>,
+}
+
+/// A borrowed view of [`Info`].
+#[derive(Debug, Clone, Deserialize)]
+pub struct InfoRef<'a> {
+    /// Metadata about the bookmark, one block per owner.
+    #[serde(
+        rename = "metadata",
+        default,
+        borrow,
+        deserialize_with = "without_text"
+    )]
+    pub metadata: Vec<MetadataRef<'a>>,
+}
+
+/// A borrowed view of [`Metadata`].
+#[derive(Debug, Clone, Deserialize)]
+pub struct MetadataRef<'a> {
+    /// The owner of the metadata.
+    #[serde(rename = "@owner", borrow)]
+    pub owner: Cow<'a, str>,
+
+    /// The MIME type information.
+    #[serde(rename = "mime-type", borrow)]
+    pub mime_type: Option<MimeTypeRef<'a>>,
+
+    /// The applications that have accessed the file.
+    #[serde(rename = "applications", default, borrow)]
+    pub applications: ApplicationsRef<'a>,
+}
+
+/// A borrowed view of [`MimeType`].
+#[derive(Debug, Clone, Deserialize)]
+pub struct MimeTypeRef<'a> {
+    /// The type of the file (e.g., "text/markdown").
+    #[serde(rename = "@type", borrow)]
+    pub mime_type: Cow<'a, str>,
+}
+
+/// A borrowed view of [`Applications`].
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ApplicationsRef<'a> {
+    /// The list of applications.
+    #[serde(
+        rename = "application",
+        default,
+        borrow,
+        deserialize_with = "without_text"
+    )]
+    pub applications: Vec<ApplicationRef<'a>>,
+}
+
+/// A borrowed view of [`Application`].
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApplicationRef<'a> {
+    /// The name of the application.
+    #[serde(rename = "@name", borrow)]
+    pub name: Cow<'a, str>,
+
+    /// The command used to execute the application.
+    #[serde(rename = "@exec", borrow)]
+    pub exec: Cow<'a, str>,
+
+    /// When the application last modified the bookmark.
+    #[serde(rename = "@modified", borrow)]
+    pub modified: Cow<'a, str>,
+
+    /// The number of times the application has accessed the bookmark.
+    #[serde(rename = "@count", deserialize_with = "count")]
+    pub count: u32,
+}
+
+impl RecentlyUsedRef<'_> {
+    /// Copies all borrowed values into an owned [`RecentlyUsed`].
+    pub fn into_owned(self) -> RecentlyUsed {
+        RecentlyUsed {
+            version: self.version.into_owned(),
+            xmlns_bookmark: self.xmlns_bookmark.into_owned(),
+            xmlns_mime: self.xmlns_mime.into_owned(),
+            title: self.title.map(Cow::into_owned),
+            desc: self.desc.map(Cow::into_owned),
+            info: self.info.map(InfoRef::into_owned),
+            bookmarks: self
+                .bookmarks
+                .into_iter()
+                .map(BookmarkRef::into_owned)
+                .collect(),
+            prolog: Prolog::default(),
+            unknown: Unknown::default(),
+        }
+    }
+}
+
+impl BookmarkRef<'_> {
+    /// Copies all borrowed values into an owned [`Bookmark`].
+    pub fn into_owned(self) -> Bookmark {
+        Bookmark {
+            href: self.href.into_owned(),
+            added: self.added.into_owned(),
+            modified: self.modified.map(Cow::into_owned),
+            visited: self.visited.map(Cow::into_owned),
+            title: self.title.map(Cow::into_owned),
+            desc: self.desc.map(Cow::into_owned),
+            info: self.info.map(InfoRef::into_owned),
+            #[cfg(feature = "spans")]
+            span: None,
+            unknown: Unknown::default(),
+        }
+    }
+}
+
+impl InfoRef<'_> {
+    /// Copies all borrowed values into an owned [`Info`].
+    pub fn into_owned(self) -> Info {
+        Info {
+            metadata: self
+                .metadata
+                .into_iter()
+                .map(MetadataRef::into_owned)
+                .collect(),
+            unknown: Unknown::default(),
+        }
+    }
+}
+
+impl MetadataRef<'_> {
+    /// Copies all borrowed values into an owned [`Metadata`].
+    pub fn into_owned(self) -> Metadata {
+        Metadata {
+            owner: self.owner.into_owned().into(),
+            mime_type: self.mime_type.map(|mime| MimeType {
+                mime_type: mime.mime_type.into_owned(),
+            }),
+            applications: Applications {
+                applications: self
+                    .applications
+                    .applications
+                    .into_iter()
+                    .map(ApplicationRef::into_owned)
+                    .collect(),
+            },
+            groups: Vec::new(),
+            icon: None,
+            private: false,
+            extensions: Vec::new(),
+            unknown: Unknown::default(),
+        }
+    }
+}
+
+impl ApplicationRef<'_> {
+    /// Copies all borrowed values into an owned [`Application`].
+    pub fn into_owned(self) -> Application {
+        Application {
+            name: self.name.into_owned(),
+            exec: self.exec.into_owned(),
+            modified: self.modified.into_owned(),
+            count: self.count,
+            unknown: Unknown::default(),
+        }
+    }
+}
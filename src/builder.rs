@@ -0,0 +1,277 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{
+    Application, Applications, Bookmark, Error, Info, Metadata, MetadataOwner, MimeType, Unknown,
+};
+use chrono::{DateTime, Utc};
+use std::ffi::OsStr;
+use std::path::Path;
+use url::Url;
+
+/// The MIME type GTK records for files whose type is unknown.
+pub(crate) const DEFAULT_MIME_TYPE: &str = "application/octet-stream";
+
+impl Bookmark {
+    /// Starts building a bookmark for a file, given either its absolute path or a URL.
+    ///
+    /// ```
+    /// use recently_used_xbel::Bookmark;
+    ///
+    /// let bookmark = Bookmark::builder("/home/user/notes.md")
+    ///     .app("org.gnome.TextEditor", "gnome-text-editor %u")
+    ///     .build()?;
+    ///
+    /// assert_eq!(bookmark.href, "file:///home/user/notes.md");
+    /// # Ok::<(), recently_used_xbel::Error>(())
+    /// ```
+    pub fn builder(location: impl AsRef<OsStr>) -> BookmarkBuilder {
+        BookmarkBuilder::new(location.as_ref())
+    }
+
+    /// Starts building a bookmark for a URL of any scheme, such as `sftp://`, `smb://`, or
+    /// `trash://`, without looking anything up about the file it refers to.
+    pub fn builder_from_url(url: &Url) -> BookmarkBuilder {
+        BookmarkBuilder::with_href(Some(url.as_str().to_owned()))
+    }
+}
+
+impl Application {
+    /// Starts building an application entry, given its name and the command it was opened
+    /// with, such as `gnome-text-editor %u`.
+    pub fn builder(name: impl Into<String>, exec: impl Into<String>) -> ApplicationBuilder {
+        ApplicationBuilder {
+            name: name.into(),
+            exec: exec.into(),
+            modified: None,
+            count: 1,
+        }
+    }
+}
+
+/// Builds a [`Bookmark`] which is valid according to the specification.
+///
+/// Timestamps which aren't set default to the time the bookmark is built, and the MIME type
+/// is guessed from the file's extension if it isn't set.
+#[derive(Debug, Clone)]
+pub struct BookmarkBuilder {
+    href: Option<String>,
+    host: Option<String>,
+    added: Option<DateTime<Utc>>,
+    modified: Option<DateTime<Utc>>,
+    visited: Option<DateTime<Utc>>,
+    title: Option<String>,
+    desc: Option<String>,
+    owner: MetadataOwner,
+    mime_type: Option<String>,
+    applications: Vec<ApplicationBuilder>,
+    groups: Vec<String>,
+    private: bool,
+}
+
+impl BookmarkBuilder {
+    fn new(location: &OsStr) -> Self {
+        Self::with_href(crate::location_to_href(location))
+    }
+
+    fn with_href(href: Option<String>) -> Self {
+        Self {
+            href,
+            host: None,
+            added: None,
+            modified: None,
+            visited: None,
+            title: None,
+            desc: None,
+            owner: MetadataOwner::Freedesktop,
+            mime_type: None,
+            applications: Vec::new(),
+            groups: Vec::new(),
+            private: false,
+        }
+    }
+
+    /// Sets the host the file is on, such as the server of an NFS mount, for a bookmark built
+    /// from a path or a `file://` URL.
+    pub fn host(mut self, host: impl Into<String>) -> Self {
+        self.host = Some(host.into());
+        self
+    }
+
+    /// Sets when the file was added to the list.
+    pub fn added(mut self, time: impl Into<DateTime<Utc>>) -> Self {
+        self.added = Some(time.into());
+        self
+    }
+
+    /// Sets when the file was last modified.
+    pub fn modified(mut self, time: impl Into<DateTime<Utc>>) -> Self {
+        self.modified = Some(time.into());
+        self
+    }
+
+    /// Sets when the file was last visited.
+    pub fn visited(mut self, time: impl Into<DateTime<Utc>>) -> Self {
+        self.visited = Some(time.into());
+        self
+    }
+
+    /// Sets the title of the bookmark.
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Sets the description of the bookmark.
+    pub fn desc(mut self, desc: impl Into<String>) -> Self {
+        self.desc = Some(desc.into());
+        self
+    }
+
+    /// Sets the owner of the bookmark's metadata, which is freedesktop.org by default.
+    pub fn owner(mut self, owner: impl Into<MetadataOwner>) -> Self {
+        self.owner = owner.into();
+        self
+    }
+
+    /// Sets the MIME type of the file, such as `text/markdown`.
+    pub fn mime(mut self, mime_type: impl Into<String>) -> Self {
+        self.mime_type = Some(mime_type.into());
+        self
+    }
+
+    /// Adds an application which opened the file once, when the bookmark was last modified.
+    pub fn app(self, name: impl Into<String>, exec: impl Into<String>) -> Self {
+        self.application(Application::builder(name, exec))
+    }
+
+    /// Adds an application entry, such as one opening the file several times.
+    pub fn application(mut self, application: ApplicationBuilder) -> Self {
+        self.applications.push(application);
+        self
+    }
+
+    /// Adds the bookmark to a group.
+    pub fn group(mut self, group: impl Into<String>) -> Self {
+        self.groups.push(group.into());
+        self
+    }
+
+    /// Sets whether the bookmark should only be shown by the applications which registered
+    /// it.
+    pub fn private(mut self, private: bool) -> Self {
+        self.private = private;
+        self
+    }
+
+    /// Builds the bookmark, failing with [`Error::Path`] if it was given a relative path,
+    /// [`Error::Url`] if its host is invalid, [`Error::Mime`] if its MIME type is
+    /// malformed, or [`Error::NoApplications`] if no application was added, as the
+    /// specification requires one. The MIME type is normalized as by [`MimeType::new`].
+    pub fn build(self) -> Result<Bookmark, Error> {
+        let mut href = self.href.ok_or(Error::Path)?;
+        if self.applications.is_empty() {
+            return Err(Error::NoApplications);
+        }
+        if let Some(host) = &self.host {
+            href = with_host(&href, host)?;
+        }
+        let now = Utc::now();
+        let added = self.added.unwrap_or(now);
+        let modified = self.modified.unwrap_or(now);
+
+        let mime_type = match self.mime_type {
+            Some(mime_type) => MimeType::new(&mime_type)?,
+            None => MimeType {
+                mime_type: guess_mime(&href).unwrap_or_else(|| DEFAULT_MIME_TYPE.to_owned()),
+            },
+        };
+
+        let applications = self
+            .applications
+            .into_iter()
+            .map(|app| app.build_at(modified))
+            .collect();
+
+        Ok(Bookmark {
+            href,
+            added: crate::time::format(added),
+            modified: Some(crate::time::format(modified)),
+            visited: Some(crate::time::format(self.visited.unwrap_or(now))),
+            title: self.title,
+            desc: self.desc,
+            info: Some(Info {
+                metadata: vec![Metadata {
+                    owner: self.owner,
+                    mime_type: Some(mime_type),
+                    applications: Applications { applications },
+                    groups: self.groups,
+                    icon: None,
+                    private: self.private,
+                    extensions: Vec::new(),
+                    unknown: Unknown::default(),
+                }],
+                unknown: Unknown::default(),
+            }),
+            #[cfg(feature = "spans")]
+            span: None,
+            unknown: Unknown::default(),
+        })
+    }
+}
+
+/// Replaces the host of a `file://` URL.
+fn with_host(href: &str, host: &str) -> Result<String, Error> {
+    let mut url = Url::parse(href).map_err(Error::Url)?;
+    if url.scheme() != "file" {
+        return Err(Error::Path);
+    }
+    url.set_host(Some(host)).map_err(Error::Url)?;
+    Ok(url.into())
+}
+
+/// Guesses the MIME type of the file a URL points to from its extension.
+fn guess_mime(href: &str) -> Option<String> {
+    let url = Url::parse(href).ok()?;
+    crate::mime_from_path(Path::new(url.path()))
+}
+
+/// Builds an [`Application`] entry of a bookmark.
+#[derive(Debug, Clone)]
+pub struct ApplicationBuilder {
+    name: String,
+    exec: String,
+    modified: Option<DateTime<Utc>>,
+    count: u32,
+}
+
+impl ApplicationBuilder {
+    /// Sets when the application last modified the bookmark. Defaults to when the bookmark
+    /// was last modified if it is added to a [`BookmarkBuilder`], or else to when the entry
+    /// is built.
+    pub fn modified(mut self, time: impl Into<DateTime<Utc>>) -> Self {
+        self.modified = Some(time.into());
+        self
+    }
+
+    /// Sets the number of times the application has opened the file, which is 1 by default.
+    pub fn count(mut self, count: u32) -> Self {
+        self.count = count;
+        self
+    }
+
+    /// Builds the application entry.
+    pub fn build(self) -> Application {
+        self.build_at(Utc::now())
+    }
+
+    fn build_at(self, modified: DateTime<Utc>) -> Application {
+        Application {
+            name: self.name,
+            exec: self.exec,
+            modified: crate::time::format(self.modified.unwrap_or(modified)),
+            count: self.count,
+            unknown: Unknown::default(),
+        }
+    }
+}
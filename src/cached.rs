@@ -0,0 +1,89 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{Error, RecentlyUsed};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// The modification time and size of a file, or `None` if it doesn't exist.
+type Stamp = Option<(SystemTime, u64)>;
+
+/// A recently-used.xbel file which is only parsed again once it has changed on disk.
+///
+/// Intended for panels and launchers which poll the list of recent files frequently. Each
+/// call to [`get`](Self::get) compares the file's modification time and size with those of
+/// the last parse, and returns the previous result if neither has changed. A missing or
+/// empty file is treated as an empty list, as with [`parse_path_or_default`](crate::parse_path_or_default).
+///
+/// ```no_run
+/// use recently_used_xbel::CachedRecents;
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let mut recents = CachedRecents::new()?;
+///
+///     loop {
+///         println!("{} recent files", recents.get()?.bookmarks.len());
+///         std::thread::sleep(std::time::Duration::from_secs(1));
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct CachedRecents {
+    path: PathBuf,
+    cached: Option<(Stamp, Arc<RecentlyUsed>)>,
+}
+
+impl CachedRecents {
+    /// Caches the recently-used.xbel file in its default location.
+    pub fn new() -> Result<Self, Error> {
+        crate::dir().map(Self::with_path).ok_or(Error::DoesNotExist)
+    }
+
+    /// Caches a recently-used.xbel file at the given location.
+    pub fn with_path(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            cached: None,
+        }
+    }
+
+    /// The location of the cached file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the contents of the file, parsing it only if it changed since the last call.
+    ///
+    /// Changes which keep both the size and the modification time of the file, such as two
+    /// writes within the resolution of the file system's timestamps, are not detected. Use
+    /// [`invalidate`](Self::invalidate) to force the next call to parse the file.
+    pub fn get(&mut self) -> Result<Arc<RecentlyUsed>, Error> {
+        let stamp = match fs::metadata(&self.path) {
+            Ok(metadata) => Some((
+                metadata.modified().map_err(Error::Metadata)?,
+                metadata.len(),
+            )),
+            Err(why) if why.kind() == io::ErrorKind::NotFound => None,
+            Err(why) => return Err(Error::Read(why)),
+        };
+
+        if let Some((cached_stamp, recently_used)) = &self.cached {
+            if *cached_stamp == stamp {
+                return Ok(Arc::clone(recently_used));
+            }
+        }
+
+        let recently_used = Arc::new(crate::parse_path_or_default(&self.path)?);
+        self.cached = Some((stamp, Arc::clone(&recently_used)));
+        Ok(recently_used)
+    }
+
+    /// Discards the cached contents, so that the next call to [`get`](Self::get) parses the
+    /// file again.
+    pub fn invalidate(&mut self) {
+        self.cached = None;
+    }
+}
@@ -0,0 +1,662 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::encoding::{self, Input};
+#[cfg(feature = "spans")]
+use crate::line_counter::{count_newlines, LineCounter};
+use crate::xml::{BytesStart, Event, NsReader, ResolveResult};
+use crate::DeError;
+#[cfg(feature = "spans")]
+use crate::Span;
+use crate::{
+    Application, Applications, Bookmark, Error, Icon, Info, Metadata, MetadataOwner, MimeType,
+    ParseOptions, ParseWarning, Prolog, RawElement, RecentlyUsed, Unknown, BOOKMARK_NAMESPACE,
+    MIME_NAMESPACE, XBEL_VERSION,
+};
+use std::io::BufRead;
+
+/// How deeply elements may be nested within a bookmark or other element read as a whole.
+/// GTK never nests more than a handful of levels.
+pub(crate) const MAX_DEPTH: usize = 256;
+
+/// An element read from the document, kept unconverted so that errors in its content can be
+/// reported without losing track of where the element ends.
+pub(crate) struct Element {
+    pub start: BytesStart<'static>,
+    /// The namespace URI that the element's prefix resolved to.
+    pub namespace: Option<Vec<u8>>,
+    pub children: Vec<Element>,
+    /// The escaped text content of the element.
+    pub text: Vec<u8>,
+}
+
+impl Element {
+    fn new(start: BytesStart<'static>, resolved: ResolveResult) -> Self {
+        let namespace = match resolved {
+            ResolveResult::Bound(ns) => Some(ns.into_inner().to_vec()),
+            ResolveResult::Unbound => None,
+            // Tolerate files which use the conventional prefixes without declaring them.
+            ResolveResult::Unknown(prefix) => match prefix.as_slice() {
+                b"bookmark" => Some(BOOKMARK_NAMESPACE.as_bytes().to_vec()),
+                b"mime" => Some(MIME_NAMESPACE.as_bytes().to_vec()),
+                _ => None,
+            },
+        };
+
+        Self {
+            start,
+            namespace,
+            children: Vec::new(),
+            text: Vec::new(),
+        }
+    }
+
+    pub fn local_name(&self) -> &[u8] {
+        self.start.local_name().into_inner()
+    }
+
+    /// Whether this element has the given name, where a namespace of `None` refers to the
+    /// unprefixed elements of XBEL itself.
+    pub fn is(&self, namespace: Option<&str>, name: &str) -> bool {
+        let namespace_matches = match namespace {
+            Some(ns) => self.namespace.as_deref() == Some(ns.as_bytes()),
+            None => self.start.name().prefix().is_none(),
+        };
+
+        namespace_matches && self.local_name() == name.as_bytes()
+    }
+
+    /// Whether this element is in a namespace other than those of XBEL and freedesktop.org.
+    pub fn is_extension(&self) -> bool {
+        self.start.name().prefix().is_some()
+            && !matches!(
+                self.namespace.as_deref(),
+                Some(ns) if ns == BOOKMARK_NAMESPACE.as_bytes() || ns == MIME_NAMESPACE.as_bytes()
+            )
+    }
+
+    pub fn child(&self, namespace: Option<&str>, name: &str) -> Option<&Element> {
+        self.children.iter().find(|el| el.is(namespace, name))
+    }
+
+    pub fn attribute(&self, name: &str) -> Result<Option<String>, DeError> {
+        for attr in self.start.attributes() {
+            let attr = attr?;
+            if attr.key.as_ref() == name.as_bytes() {
+                return Ok(Some(attr.unescape_value()?.into_owned()));
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn required_attribute(&self, name: &str) -> Result<String, DeError> {
+        self.attribute(name)?
+            .ok_or_else(|| DeError::Custom(format!("missing field `@{}`", name)))
+    }
+
+    pub fn text(&self) -> Result<String, DeError> {
+        let text = std::str::from_utf8(&self.text)?;
+        Ok(crate::xml::unescape(text)?.into_owned())
+    }
+
+    /// The attributes of the element whose names are not in `known`.
+    fn attributes_except(&self, known: &[&str]) -> Result<Vec<(String, String)>, DeError> {
+        let mut attributes = Vec::new();
+        for attr in self.start.attributes() {
+            let attr = attr?;
+            let key = std::str::from_utf8(attr.key.as_ref())?;
+            if !known.contains(&key) {
+                attributes.push((key.to_owned(), attr.unescape_value()?.into_owned()));
+            }
+        }
+        Ok(attributes)
+    }
+
+    /// Collects the attributes not listed in `known_attributes`, and the children that
+    /// `is_known` does not accept.
+    pub fn unknown(
+        &self,
+        known_attributes: &[&str],
+        is_known: impl Fn(&Element) -> bool,
+    ) -> Result<Unknown, DeError> {
+        Ok(Unknown {
+            attributes: self.attributes_except(known_attributes)?,
+            elements: self
+                .children
+                .iter()
+                .filter(|el| !is_known(el))
+                .map(Element::to_raw)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    pub fn to_raw(&self) -> Result<RawElement, DeError> {
+        Ok(RawElement {
+            name: std::str::from_utf8(self.start.name().as_ref())?.to_owned(),
+            attributes: self.attributes_except(&[])?,
+            children: self
+                .children
+                .iter()
+                .map(Element::to_raw)
+                .collect::<Result<_, _>>()?,
+            text: self.text()?,
+        })
+    }
+}
+
+pub(crate) const ROOT_ATTRIBUTES: &[&str] = &["version", "xmlns:bookmark", "xmlns:mime"];
+
+/// A bookmark paired with where it was found in the document.
+pub(crate) struct ReadBookmark {
+    /// The byte offset of the start of the bookmark.
+    pub offset: u64,
+    pub result: Result<Bookmark, DeError>,
+}
+
+#[cfg(feature = "spans")]
+type Source<R> = LineCounter<Input<R>>;
+#[cfg(not(feature = "spans"))]
+type Source<R> = Input<R>;
+
+/// Reads an xbel document one bookmark at a time.
+pub(crate) struct XbelReader<R> {
+    reader: NsReader<Source<R>>,
+    buf: Vec<u8>,
+    finished: bool,
+    prolog: Prolog,
+    /// Children of the root element other than bookmarks.
+    root_children: Vec<Element>,
+    /// The version attribute of the root element.
+    pub version: Option<String>,
+    /// The byte offset of the start of the root element.
+    pub root_offset: u64,
+}
+
+impl<R: BufRead> XbelReader<R> {
+    pub fn new(reader: R) -> Result<Self, Error> {
+        encoding::detect(reader).map(Self::from_input)
+    }
+
+    /// Reads a document as UTF-8, whatever its declared encoding.
+    pub fn from_utf8(reader: R) -> Self {
+        Self::from_input(Input::Utf8(reader))
+    }
+
+    fn from_input(input: Input<R>) -> Self {
+        #[cfg(feature = "spans")]
+        let input = LineCounter {
+            inner: input,
+            newlines: 0,
+        };
+
+        let mut reader = NsReader::from_reader(input);
+        reader.config_mut().trim_text(true);
+        Self {
+            reader,
+            buf: Vec::new(),
+            finished: false,
+            prolog: Prolog::default(),
+            root_children: Vec::new(),
+            version: None,
+            root_offset: 0,
+        }
+    }
+
+    /// The byte offset up to which the input has been consumed.
+    pub fn position(&self) -> u64 {
+        self.reader.buffer_position()
+    }
+
+    /// The byte offset at which the last error of the document's markup was found.
+    pub fn error_position(&self) -> u64 {
+        self.reader.error_position()
+    }
+
+    /// Reads up to the start of the root element, returning a document without bookmarks.
+    pub fn read_root(&mut self) -> Result<RecentlyUsed, DeError> {
+        let root = self.read_root_element()?;
+
+        Ok(RecentlyUsed {
+            version: self
+                .version
+                .clone()
+                .unwrap_or_else(|| XBEL_VERSION.to_owned()),
+            xmlns_bookmark: root
+                .attribute("xmlns:bookmark")?
+                .unwrap_or_else(|| BOOKMARK_NAMESPACE.to_owned()),
+            xmlns_mime: root
+                .attribute("xmlns:mime")?
+                .unwrap_or_else(|| MIME_NAMESPACE.to_owned()),
+            title: None,
+            desc: None,
+            info: None,
+            bookmarks: Vec::new(),
+            prolog: std::mem::take(&mut self.prolog),
+            unknown: Unknown {
+                attributes: root.attributes_except(ROOT_ATTRIBUTES)?,
+                elements: Vec::new(),
+            },
+        })
+    }
+
+    /// Fills in the title, description, info, and unknown elements of the document from the
+    /// children of the root element other than bookmarks which were read so far.
+    pub fn finish_root(&mut self, recently_used: &mut RecentlyUsed) -> Result<(), DeError> {
+        let children = std::mem::take(&mut self.root_children);
+        let mut unknown = Vec::new();
+
+        for child in &children {
+            if child.is(None, "title") {
+                recently_used.title = Some(child.text()?);
+            } else if child.is(None, "desc") {
+                recently_used.desc = Some(child.text()?);
+            } else if child.is(None, "info") {
+                recently_used.info = Some(info(child)?);
+            } else if let Ok(raw) = child.to_raw() {
+                // Content that cannot be represented is skipped, as with unknown elements
+                // elsewhere in the document.
+                unknown.push(raw);
+            }
+        }
+
+        recently_used.unknown.elements = unknown;
+        Ok(())
+    }
+
+    /// Reads the entire document into a tree of elements.
+    pub fn read_document(&mut self) -> Result<(Prolog, Element), DeError> {
+        let root = self.read_root_element()?;
+        let empty = std::mem::replace(&mut self.finished, true);
+        let root = self.read_element(root, empty)?;
+        Ok((std::mem::take(&mut self.prolog), root))
+    }
+
+    fn read_root_element(&mut self) -> Result<Element, DeError> {
+        loop {
+            self.buf.clear();
+            let (resolved, event) = self.reader.read_resolved_event_into(&mut self.buf)?;
+            let (root, empty) = match event {
+                Event::Decl(e) => {
+                    let content = self.reader.decoder().decode(&e)?;
+                    self.prolog.declaration = Some(format!("<?{}?>", content));
+                    continue;
+                }
+                Event::DocType(e) => {
+                    let content = self.reader.decoder().decode(&e)?;
+                    self.prolog.doctype = Some(format!("<!DOCTYPE {}>", content));
+                    continue;
+                }
+                Event::Start(e) => (Element::new(e.into_owned(), resolved), false),
+                Event::Empty(e) => {
+                    self.finished = true;
+                    (Element::new(e.into_owned(), resolved), true)
+                }
+                Event::Eof => return Err(DeError::UnexpectedEof),
+                _ => continue,
+            };
+
+            let markup = if empty { 3 } else { 2 };
+            self.root_offset = self.position() - root.start.len() as u64 - markup;
+            self.version = root.attribute("version")?;
+            return Ok(root);
+        }
+    }
+
+    /// Reads the next bookmark of the document.
+    ///
+    /// The outer error is returned when the document itself is malformed, and no further
+    /// bookmarks can be read. The inner error is returned for a bookmark whose content is
+    /// invalid, in which case reading may continue with the next bookmark.
+    pub fn next_bookmark(&mut self) -> Result<Option<ReadBookmark>, DeError> {
+        self.next_bookmark_where(|_| true)
+    }
+
+    /// Reads the next bookmark whose start tag is accepted by `filter`, skipping over the
+    /// content of any bookmark that is rejected without converting it.
+    pub fn next_bookmark_where(
+        &mut self,
+        mut filter: impl FnMut(&Element) -> bool,
+    ) -> Result<Option<ReadBookmark>, DeError> {
+        while !self.finished {
+            self.buf.clear();
+            let (resolved, event) = self.reader.read_resolved_event_into(&mut self.buf)?;
+            let (element, empty) = match event {
+                Event::Start(e) => (Element::new(e.into_owned(), resolved), false),
+                Event::Empty(e) => (Element::new(e.into_owned(), resolved), true),
+                Event::End(_) => {
+                    self.finished = true;
+                    break;
+                }
+                Event::Eof => return Err(DeError::UnexpectedEof),
+                _ => continue,
+            };
+
+            // `<` and `>`, plus the `/` of an empty element.
+            let markup = if empty { 3 } else { 2 };
+            let offset = self.position() - element.start.len() as u64 - markup;
+
+            if !element.is(None, "bookmark") {
+                let element = self.read_element(element, empty)?;
+                self.root_children.push(element);
+                continue;
+            }
+
+            if !filter(&element) {
+                if !empty {
+                    self.buf.clear();
+                    self.reader
+                        .read_to_end_into(element.start.name(), &mut self.buf)?;
+                }
+                continue;
+            }
+
+            #[cfg(feature = "spans")]
+            let line = 1 + self.reader.get_ref().newlines - count_newlines(&element.start);
+
+            let element = self.read_element(element, empty)?;
+
+            #[allow(unused_mut)]
+            let mut result = bookmark(&element);
+            #[cfg(feature = "spans")]
+            if let Ok(bookmark) = result.as_mut() {
+                let end = self.position();
+                bookmark.span = Some(Span { offset, end, line });
+            }
+
+            return Ok(Some(ReadBookmark { offset, result }));
+        }
+
+        Ok(None)
+    }
+
+    /// Reads the content of an element up to its end tag.
+    ///
+    /// Elements are read with an explicit stack rather than recursively. Elements nested
+    /// deeper than [`MAX_DEPTH`] are skipped to the end of `element` and reported as an
+    /// error, so that hostile documents can't exhaust the stack of code which walks the tree.
+    fn read_element(&mut self, element: Element, empty: bool) -> Result<Element, DeError> {
+        if empty {
+            return Ok(element);
+        }
+
+        let mut stack = vec![element];
+        loop {
+            self.buf.clear();
+            let (resolved, event) = self.reader.read_resolved_event_into(&mut self.buf)?;
+            let Some(element) = stack.last_mut() else {
+                unreachable!("the stack holds at least the element being read");
+            };
+
+            match event {
+                Event::Start(e) => {
+                    if stack.len() >= MAX_DEPTH {
+                        self.skip_nested(stack.len())?;
+                        return Err(DeError::Custom(format!(
+                            "elements are nested more than {MAX_DEPTH} levels deep"
+                        )));
+                    }
+                    stack.push(Element::new(e.into_owned(), resolved));
+                }
+                Event::Empty(e) => {
+                    let child = Element::new(e.into_owned(), resolved);
+                    element.children.push(child);
+                }
+                Event::Text(e) => element.text.extend_from_slice(&e),
+                // Kept escaped like other text, so that its markup characters stay literal.
+                Event::CData(e) => {
+                    let text = std::str::from_utf8(&e)?;
+                    element
+                        .text
+                        .extend_from_slice(crate::xml::escape(text).as_bytes());
+                }
+                Event::End(_) => {
+                    let Some(element) = stack.pop() else {
+                        unreachable!("the stack holds at least the element being read");
+                    };
+                    match stack.last_mut() {
+                        Some(parent) => parent.children.push(element),
+                        None => return Ok(element),
+                    }
+                }
+                Event::Eof => return Err(DeError::UnexpectedEof),
+                _ => (),
+            }
+        }
+    }
+
+    /// Skips past the end tags of the `depth` elements which are open, the last of which was
+    /// just started.
+    fn skip_nested(&mut self, mut depth: usize) -> Result<(), DeError> {
+        depth += 1;
+        while depth > 0 {
+            self.buf.clear();
+            match self.reader.read_event_into(&mut self.buf)? {
+                Event::Start(_) => depth += 1,
+                Event::End(_) => depth -= 1,
+                Event::Eof => return Err(DeError::UnexpectedEof),
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads a complete document.
+///
+/// When `lenient` is set, invalid bookmarks are skipped and reported as warnings, and a
+/// malformed document yields the bookmarks that could be read before the error.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+pub(crate) fn read<R: BufRead>(
+    reader: R,
+    lenient: bool,
+    options: &ParseOptions,
+) -> Result<(RecentlyUsed, Vec<ParseWarning>), Error> {
+    let mut reader = XbelReader::new(reader)?;
+    let mut recently_used = reader.read_root().map_err(Error::Deserialization)?;
+    let mut warnings = Vec::new();
+
+    if options.deny_unknown_attributes {
+        deny_unknown_attributes(&recently_used.unknown).map_err(Error::Deserialization)?;
+    }
+
+    if lenient && recently_used.version != XBEL_VERSION {
+        warnings.push(ParseWarning {
+            offset: reader.root_offset,
+            error: DeError::Custom(format!(
+                "unsupported xbel version `{}`",
+                recently_used.version
+            )),
+        });
+    }
+
+    loop {
+        match reader.next_bookmark() {
+            Ok(Some(mut read)) => {
+                if options.deny_unknown_attributes {
+                    read.result = read.result.and_then(|bookmark| {
+                        bookmark_attributes_known(&bookmark)?;
+                        Ok(bookmark)
+                    });
+                }
+
+                match read.result {
+                    Ok(bookmark) => recently_used.bookmarks.push(bookmark),
+                    Err(error) if lenient => warnings.push(ParseWarning {
+                        offset: read.offset,
+                        error,
+                    }),
+                    Err(error) => return Err(Error::Deserialization(error)),
+                }
+            }
+            Ok(None) => break,
+            Err(error) if lenient => {
+                let offset = reader.error_position();
+                warnings.push(ParseWarning { offset, error });
+                break;
+            }
+            Err(error) => return Err(Error::Deserialization(error)),
+        }
+    }
+
+    match reader.finish_root(&mut recently_used) {
+        Ok(()) => (),
+        Err(error) if lenient => warnings.push(ParseWarning {
+            offset: reader.root_offset,
+            error,
+        }),
+        Err(error) => return Err(Error::Deserialization(error)),
+    }
+
+    crate::trace::skipped(&warnings);
+    Ok((recently_used, warnings))
+}
+
+/// Rejects the first attribute which is not part of the format, ignoring namespace
+/// declarations.
+fn deny_unknown_attributes(unknown: &Unknown) -> Result<(), DeError> {
+    let attribute = unknown
+        .attributes
+        .iter()
+        .find(|(name, _)| name != "xmlns" && !name.starts_with("xmlns:"));
+
+    match attribute {
+        Some((name, _)) => Err(DeError::Custom(format!("unknown field `@{}`", name))),
+        None => Ok(()),
+    }
+}
+
+fn bookmark_attributes_known(bookmark: &Bookmark) -> Result<(), DeError> {
+    deny_unknown_attributes(&bookmark.unknown)?;
+
+    if let Some(info) = &bookmark.info {
+        deny_unknown_attributes(&info.unknown)?;
+
+        for metadata in &info.metadata {
+            deny_unknown_attributes(&metadata.unknown)?;
+
+            for application in &metadata.applications.applications {
+                deny_unknown_attributes(&application.unknown)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn bookmark(element: &Element) -> Result<Bookmark, DeError> {
+    Ok(Bookmark {
+        href: element.required_attribute("href")?,
+        added: element.required_attribute("added")?,
+        modified: element.attribute("modified")?,
+        visited: element.attribute("visited")?,
+        title: element
+            .child(None, "title")
+            .map(Element::text)
+            .transpose()?,
+        desc: element.child(None, "desc").map(Element::text).transpose()?,
+        info: element.child(None, "info").map(info).transpose()?,
+        #[cfg(feature = "spans")]
+        span: None,
+        unknown: element.unknown(&["href", "added", "modified", "visited"], |el| {
+            el.is(None, "title") || el.is(None, "desc") || el.is(None, "info")
+        })?,
+    })
+}
+
+pub(crate) fn info(element: &Element) -> Result<Info, DeError> {
+    let metadata = element
+        .children
+        .iter()
+        .filter(|el| el.is(None, "metadata"))
+        .map(metadata)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if metadata.is_empty() {
+        return Err(DeError::Custom("missing field `metadata`".to_owned()));
+    }
+
+    Ok(Info {
+        metadata,
+        unknown: element.unknown(&[], |el| el.is(None, "metadata"))?,
+    })
+}
+
+fn metadata(element: &Element) -> Result<Metadata, DeError> {
+    let owner = MetadataOwner::from(element.required_attribute("owner")?);
+    let applications = element.child(Some(BOOKMARK_NAMESPACE), "applications");
+
+    // Only freedesktop.org's own block is required to list applications.
+    if applications.is_none() && owner == MetadataOwner::Freedesktop {
+        return Err(DeError::Custom("missing field `applications`".to_owned()));
+    }
+
+    Ok(Metadata {
+        owner,
+        mime_type: element
+            .child(Some(MIME_NAMESPACE), "mime-type")
+            .map(|el| {
+                el.required_attribute("type")
+                    .map(|mime_type| MimeType { mime_type })
+            })
+            .transpose()?,
+        applications: Applications {
+            applications: applications
+                .iter()
+                .flat_map(|el| &el.children)
+                .filter(|el| el.is(Some(BOOKMARK_NAMESPACE), "application"))
+                .map(application)
+                .collect::<Result<_, _>>()?,
+        },
+        groups: element
+            .child(Some(BOOKMARK_NAMESPACE), "groups")
+            .iter()
+            .flat_map(|el| &el.children)
+            .filter(|el| el.is(Some(BOOKMARK_NAMESPACE), "group"))
+            .map(Element::text)
+            .collect::<Result<_, _>>()?,
+        icon: element
+            .child(Some(BOOKMARK_NAMESPACE), "icon")
+            .map(|el| {
+                Ok::<_, DeError>(Icon {
+                    href: el.required_attribute("href")?,
+                    mime_type: el.attribute("type")?,
+                })
+            })
+            .transpose()?,
+        private: element.child(Some(BOOKMARK_NAMESPACE), "private").is_some(),
+        extensions: element
+            .children
+            .iter()
+            .filter(|el| el.is_extension())
+            .map(Element::to_raw)
+            .collect::<Result<_, _>>()?,
+        unknown: element.unknown(&["owner"], |el| {
+            el.is_extension()
+                || el.is(Some(MIME_NAMESPACE), "mime-type")
+                || el.is(Some(BOOKMARK_NAMESPACE), "applications")
+                || el.is(Some(BOOKMARK_NAMESPACE), "groups")
+                || el.is(Some(BOOKMARK_NAMESPACE), "icon")
+                || el.is(Some(BOOKMARK_NAMESPACE), "private")
+        })?,
+    })
+}
+
+/// Reads the count of an application, saturating counts which don't fit and reading those
+/// which aren't numbers, as some applications write, as a single use.
+pub(crate) fn parse_count(count: &str) -> u32 {
+    let digits = count.trim();
+    if digits.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+        return 1;
+    }
+    digits.parse().unwrap_or(u32::MAX)
+}
+
+fn application(element: &Element) -> Result<Application, DeError> {
+    Ok(Application {
+        name: element.required_attribute("name")?,
+        exec: element.required_attribute("exec")?,
+        modified: element.required_attribute("modified")?,
+        count: parse_count(&element.required_attribute("count")?),
+        unknown: element.unknown(&["name", "exec", "modified", "count"], |_| false)?,
+    })
+}
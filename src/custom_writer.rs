@@ -1,91 +1,433 @@
 // Copyright 2024 System76 <info@system76.com>
 // SPDX-License-Identifier: MPL-2.0
 
-use crate::RecentlyUsed;
-use quick_xml::writer::Writer;
-use quick_xml::Error;
-use std::io::Cursor;
+use crate::xml::{Attribute, BytesText, Error, Event, QName, Writer};
+use crate::{
+    Application, Bookmark, Info, Metadata, MetadataOwner, NewlineStyle, RawElement, RecentlyUsed,
+    Unknown, WriteOptions,
+};
+use std::borrow::Cow;
+use std::io::{self, Write};
 
-pub fn custom_write(recently_used: RecentlyUsed) -> Result<String, crate::Error> {
-    let mut writer = Writer::new(Cursor::new(Vec::new()));
+/// Creates an attribute with its value escaped.
+///
+/// In addition to the markup characters, tabs and line breaks are written as character
+/// references, since XML parsers would otherwise normalize them to spaces.
+pub(crate) fn attr<'a>(key: &'a str, value: &'a str) -> Attribute<'a> {
+    let mut escaped = crate::xml::escape(value);
+    if escaped.contains(['\t', '\n', '\r']) {
+        escaped = Cow::Owned(
+            escaped
+                .replace('\t', "&#9;")
+                .replace('\n', "&#10;")
+                .replace('\r', "&#13;"),
+        );
+    }
 
-    let _ = writer
-        .create_element("xbel")
+    Attribute {
+        key: QName(key.as_bytes()),
+        value: match escaped {
+            Cow::Borrowed(value) => Cow::Borrowed(value.as_bytes()),
+            Cow::Owned(value) => Cow::Owned(value.into_bytes()),
+        },
+    }
+}
+
+pub(crate) fn unknown_attributes(unknown: &Unknown) -> impl Iterator<Item = Attribute<'_>> {
+    unknown
+        .attributes
+        .iter()
+        .map(|(key, value)| attr(key, value))
+}
+
+impl WriteOptions {
+    /// The prefix elements in the desktop-bookmarks namespace are written with.
+    fn bookmark_prefix(&self) -> &str {
+        self.bookmark_namespace
+            .as_ref()
+            .map_or("bookmark", |namespace| &namespace.prefix)
+    }
+
+    /// The prefix elements in the shared-mime-info namespace are written with.
+    fn mime_prefix(&self) -> &str {
+        self.mime_namespace
+            .as_ref()
+            .map_or("mime", |namespace| &namespace.prefix)
+    }
+}
+
+/// The name of an element in the namespace with the given prefix.
+fn qualified(prefix: &str, local: &str) -> String {
+    format!("{prefix}:{local}")
+}
+
+/// The name of an element which is not modeled, with the usual prefixes of the namespaces
+/// replaced by those it is written with.
+fn raw_name<'a>(name: &'a str, options: &WriteOptions) -> Cow<'a, str> {
+    match name.split_once(':') {
+        Some(("bookmark", local)) => Cow::Owned(qualified(options.bookmark_prefix(), local)),
+        Some(("mime", local)) => Cow::Owned(qualified(options.mime_prefix(), local)),
+        _ => Cow::Borrowed(name),
+    }
+}
+
+pub(crate) fn write_unknown_elements<W: Write>(
+    writer: &mut Writer<W>,
+    unknown: &Unknown,
+    options: &WriteOptions,
+) -> Result<(), Error> {
+    for element in &unknown.elements {
+        write_raw(writer, element, options)?;
+    }
+    Ok(())
+}
+
+fn write_raw<W: Write>(
+    writer: &mut Writer<W>,
+    element: &RawElement,
+    options: &WriteOptions,
+) -> Result<(), Error> {
+    let name = raw_name(&element.name, options);
+    let start = writer.create_element(name.as_ref()).with_attributes(
+        element
+            .attributes
+            .iter()
+            .map(|(key, value)| attr(key, value)),
+    );
+
+    if element.children.is_empty() && element.text.is_empty() {
+        start.write_empty()?;
+        return Ok(());
+    }
+
+    start.write_inner_content::<_, Error>(|writer| {
+        if !element.text.is_empty() {
+            writer.write_event(Event::Text(BytesText::new(&element.text)))?;
+        }
+        for child in &element.children {
+            write_raw(writer, child, options)?;
+        }
+        Ok(())
+    })?;
+    Ok(())
+}
+
+/// Writes an element holding only text, such as a `<title>`, as an empty-element tag if the
+/// text is empty.
+pub(crate) fn write_text_element<W: Write>(
+    writer: &mut Writer<W>,
+    name: &str,
+    text: &str,
+) -> Result<(), Error> {
+    let element = writer.create_element(name);
+    if text.is_empty() {
+        element.write_empty()?;
+    } else {
+        element.write_text_content(BytesText::new(text))?;
+    }
+    Ok(())
+}
+
+fn write_bookmark<W: Write>(
+    writer: &mut Writer<W>,
+    bookmark: &Bookmark,
+    options: &WriteOptions,
+) -> Result<(), Error> {
+    writer
+        .create_element("bookmark")
         .with_attributes(
-            vec![
-                ("version", "1.0"),
-                (
-                    "xmlns:bookmark",
-                    "http://www.freedesktop.org/standards/desktop-bookmarks",
-                ),
-                (
-                    "xmlns:mime",
-                    "http://www.freedesktop.org/standards/shared-mime-info",
-                ),
-            ]
-            .into_iter(),
+            [attr("href", &bookmark.href), attr("added", &bookmark.added)]
+                .into_iter()
+                .chain(
+                    bookmark
+                        .modified
+                        .as_deref()
+                        .map(|time| attr("modified", time)),
+                )
+                .chain(
+                    bookmark
+                        .visited
+                        .as_deref()
+                        .map(|time| attr("visited", time)),
+                )
+                .chain(unknown_attributes(&bookmark.unknown)),
         )
         .write_inner_content::<_, Error>(|writer| {
-            for b in recently_used.bookmarks {
-                let _ = writer
-                    .create_element("bookmark")
-                    .with_attributes([
-                        ("href", b.href.as_str()),
-                        ("added", b.added.as_str()),
-                        ("modified", b.modified.as_str()),
-                        ("visited", b.visited.as_str()),
-                    ])
+            if let Some(title) = &bookmark.title {
+                write_text_element(writer, "title", title)?;
+            }
+            if let Some(desc) = &bookmark.desc {
+                write_text_element(writer, "desc", desc)?;
+            }
+            if let Some(info) = &bookmark.info {
+                write_info(writer, info, options)?;
+            }
+            write_unknown_elements(writer, &bookmark.unknown, options)
+        })?;
+    Ok(())
+}
+
+pub(crate) fn write_info<W: Write>(
+    writer: &mut Writer<W>,
+    info: &Info,
+    options: &WriteOptions,
+) -> Result<(), Error> {
+    writer
+        .create_element("info")
+        .with_attributes(unknown_attributes(&info.unknown))
+        .write_inner_content::<_, Error>(|writer| {
+            for metadata in &info.metadata {
+                write_metadata(writer, metadata, options)?;
+            }
+            write_unknown_elements(writer, &info.unknown, options)
+        })?;
+    Ok(())
+}
+
+fn write_metadata<W: Write>(
+    writer: &mut Writer<W>,
+    metadata: &Metadata,
+    options: &WriteOptions,
+) -> Result<(), Error> {
+    writer
+        .create_element("metadata")
+        .with_attributes(
+            [attr("owner", metadata.owner.as_str())]
+                .into_iter()
+                .chain(unknown_attributes(&metadata.unknown)),
+        )
+        .write_inner_content::<_, Error>(|writer| {
+            if let Some(mime) = &metadata.mime_type {
+                writer
+                    .create_element(qualified(options.mime_prefix(), "mime-type"))
+                    .with_attributes([attr("type", &mime.mime_type)])
+                    .write_empty()?;
+            }
+
+            let applications = &metadata.applications.applications;
+            if !applications.is_empty() || metadata.owner == MetadataOwner::Freedesktop {
+                writer
+                    .create_element(qualified(options.bookmark_prefix(), "applications"))
+                    .write_inner_content::<_, Error>(|writer| {
+                        for app in applications {
+                            write_application(writer, app, options)?;
+                        }
+                        Ok(())
+                    })?;
+            }
+
+            if !metadata.groups.is_empty() {
+                writer
+                    .create_element(qualified(options.bookmark_prefix(), "groups"))
                     .write_inner_content::<_, Error>(|writer| {
-                        if let Some(info) = b.info {
-                            let _ = writer
-                                .create_element("info")
-                                .write_inner_content::<_, Error>(|writer| {
-                                    let _ = writer
-                                        .create_element("metadata")
-                                        .with_attributes([("owner", info.metadata.owner.as_str())])
-                                        .write_inner_content::<_, Error>(|writer| {
-                                            if let Some(mime) = info.metadata.mime_type {
-                                                let _ = writer
-                                                    .create_element("mime:mime-type")
-                                                    .with_attributes([(
-                                                        "type",
-                                                        mime.mime_type.as_str(),
-                                                    )])
-                                                    .write_empty();
-                                            }
-                                            let _ = writer
-                                                .create_element("bookmark:applications")
-                                                .write_inner_content::<_, Error>(|writer| {
-                                                for app in info.metadata.applications.applications {
-                                                    let _ = writer
-                                                        .create_element("bookmark:application")
-                                                        .with_attributes([
-                                                            ("name", app.name.as_str()),
-                                                            ("exec", app.exec.as_str()),
-                                                            ("modified", app.modified.as_str()),
-                                                            (
-                                                                "count",
-                                                                app.count.to_string().as_str(),
-                                                            ),
-                                                        ])
-                                                        .write_empty();
-                                                }
-                                                Ok(())
-                                            });
-                                            Ok(())
-                                        });
-                                    Ok(())
-                                });
+                        let name = qualified(options.bookmark_prefix(), "group");
+                        for group in &metadata.groups {
+                            write_text_element(writer, &name, group)?;
                         }
                         Ok(())
-                    });
+                    })?;
+            }
+
+            if let Some(icon) = &metadata.icon {
+                writer
+                    .create_element(qualified(options.bookmark_prefix(), "icon"))
+                    .with_attributes(
+                        [attr("href", &icon.href)]
+                            .into_iter()
+                            .chain(icon.mime_type.as_deref().map(|mime| attr("type", mime))),
+                    )
+                    .write_empty()?;
+            }
+
+            if metadata.private {
+                writer
+                    .create_element(qualified(options.bookmark_prefix(), "private"))
+                    .write_empty()?;
+            }
+
+            for element in &metadata.extensions {
+                write_raw(writer, element, options)?;
             }
-            Ok(())
-        });
+            write_unknown_elements(writer, &metadata.unknown, options)
+        })?;
+    Ok(())
+}
+
+fn write_application<W: Write>(
+    writer: &mut Writer<W>,
+    app: &Application,
+    options: &WriteOptions,
+) -> Result<(), Error> {
+    let count = app.count.to_string();
+    writer
+        .create_element(qualified(options.bookmark_prefix(), "application"))
+        .with_attributes(
+            [
+                attr("name", &app.name),
+                attr("exec", &app.exec),
+                attr("modified", &app.modified),
+                attr("count", &count),
+            ]
+            .into_iter()
+            .chain(unknown_attributes(&app.unknown)),
+        )
+        .write_empty()?;
+    Ok(())
+}
+
+/// The XML declaration written when the document didn't have one.
+pub(crate) const DEFAULT_DECLARATION: &str = r#"<?xml version="1.0" encoding="UTF-8"?>"#;
+
+/// Serializes a document to a string.
+///
+/// The output is deterministic: bookmarks, metadata blocks, and applications are written in
+/// the order they are listed, the attributes of each element in a fixed order followed by
+/// any unknown ones as they were parsed, so that writing the same document twice gives
+/// byte-identical output.
+pub fn custom_write(recently_used: &RecentlyUsed) -> Result<String, crate::Error> {
+    custom_write_with(recently_used, &WriteOptions::default())
+}
+
+/// Serializes a document to a string with the given options.
+pub fn custom_write_with(
+    recently_used: &RecentlyUsed,
+    options: &WriteOptions,
+) -> Result<String, crate::Error> {
+    let mut bytes = Vec::new();
+    custom_write_to_with(recently_used, &mut bytes, options)?;
+    String::from_utf8(bytes).map_err(|_| crate::Error::Serialization(None))
+}
 
-    let bytes = writer.into_inner().into_inner();
-    match String::from_utf8(bytes) {
-        Ok(string) => Ok(string),
-        Err(_e) => Err(crate::Error::Serialization(None)),
+/// Serializes a document directly into a writer, such as a file or socket, without building
+/// it up in memory first.
+///
+/// The writer is not buffered; wrap it in a [`BufWriter`](std::io::BufWriter) if it benefits
+/// from buffering.
+pub fn custom_write_to<W: Write>(
+    recently_used: &RecentlyUsed,
+    writer: W,
+) -> Result<(), crate::Error> {
+    custom_write_to_with(recently_used, writer, &WriteOptions::default())
+}
+
+/// Serializes a document directly into a writer with the given options.
+pub fn custom_write_to_with<W: Write>(
+    recently_used: &RecentlyUsed,
+    writer: W,
+    options: &WriteOptions,
+) -> Result<(), crate::Error> {
+    let result = match options.newline_style {
+        NewlineStyle::Lf => write_formatted(writer, recently_used, options),
+        NewlineStyle::CrLf => write_formatted(CrLf(writer), recently_used, options),
+    };
+
+    result.map_err(write_error)
+}
+
+/// Converts an error from the XML writer, distinguishing failures of the underlying writer.
+pub(crate) fn write_error(why: Error) -> crate::Error {
+    match why {
+        Error::Io(why) => crate::Error::Write(io::Error::new(why.kind(), why)),
+        why => crate::Error::Serialization(Some(why.into())),
     }
 }
+
+fn write_formatted<W: Write>(
+    writer: W,
+    recently_used: &RecentlyUsed,
+    options: &WriteOptions,
+) -> Result<(), Error> {
+    let mut writer = match options.indent {
+        Some(indent) => Writer::new_with_indent(writer, b' ', indent),
+        None => Writer::new(writer),
+    };
+
+    write_document(&mut writer, recently_used, options)?;
+    if options.trailing_newline {
+        writer.get_mut().write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+/// Translates line feeds into carriage return and line feed pairs.
+struct CrLf<W>(W);
+
+impl<W: Write> Write for CrLf<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match buf.iter().position(|&byte| byte == b'\n') {
+            Some(0) => self.0.write_all(b"\r\n").map(|()| 1),
+            Some(end) => self.0.write(&buf[..end]),
+            None => self.0.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+fn write_document<W: Write>(
+    writer: &mut Writer<W>,
+    recently_used: &RecentlyUsed,
+    options: &WriteOptions,
+) -> Result<(), Error> {
+    let prolog = &recently_used.prolog;
+    let declaration = options
+        .declaration
+        .then(|| prolog.declaration.as_deref().unwrap_or(DEFAULT_DECLARATION));
+
+    for markup in [declaration, prolog.doctype.as_deref()]
+        .into_iter()
+        .flatten()
+    {
+        let inner = writer.get_mut();
+        inner.write_all(markup.as_bytes())?;
+        inner.write_all(b"\n")?;
+    }
+
+    let bookmark_xmlns = format!("xmlns:{}", options.bookmark_prefix());
+    let mime_xmlns = format!("xmlns:{}", options.mime_prefix());
+    let bookmark_uri = options
+        .bookmark_namespace
+        .as_ref()
+        .map_or(&recently_used.xmlns_bookmark, |namespace| &namespace.uri);
+    let mime_uri = options
+        .mime_namespace
+        .as_ref()
+        .map_or(&recently_used.xmlns_mime, |namespace| &namespace.uri);
+    writer
+        .create_element("xbel")
+        .with_attributes(
+            [
+                attr("version", &recently_used.version),
+                attr(&bookmark_xmlns, bookmark_uri),
+                attr(&mime_xmlns, mime_uri),
+            ]
+            .into_iter()
+            // Leaving out declarations of the same prefixes which the document was parsed with.
+            .chain(
+                recently_used
+                    .unknown
+                    .attributes
+                    .iter()
+                    .filter(|(key, _)| *key != bookmark_xmlns && *key != mime_xmlns)
+                    .map(|(key, value)| attr(key, value)),
+            ),
+        )
+        .write_inner_content::<_, Error>(|writer| {
+            if let Some(title) = &recently_used.title {
+                write_text_element(writer, "title", title)?;
+            }
+            if let Some(desc) = &recently_used.desc {
+                write_text_element(writer, "desc", desc)?;
+            }
+            if let Some(info) = &recently_used.info {
+                write_info(writer, info, options)?;
+            }
+            for bookmark in &recently_used.bookmarks {
+                write_bookmark(writer, bookmark, options)?;
+            }
+            write_unknown_elements(writer, &recently_used.unknown, options)
+        })?;
+    Ok(())
+}
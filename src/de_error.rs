@@ -0,0 +1,59 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+/// Why a part of a document could not be read.
+///
+/// The same type is used whichever features are enabled, and new variants may be added.
+#[derive(Debug, Clone, thiserror::Error)]
+#[non_exhaustive]
+pub enum DeError {
+    /// The document is not a valid XBEL file.
+    #[error("{0}")]
+    Custom(String),
+    /// The document is not well-formed XML.
+    #[error(transparent)]
+    InvalidXml(XmlError),
+    /// The document ended before its root element was closed.
+    #[error("Unexpected `Event::Eof`")]
+    UnexpectedEof,
+    /// A borrowed view of the document could not be deserialized by serde, for reasons other
+    /// than the above.
+    #[cfg(feature = "serde")]
+    #[error(transparent)]
+    Serde(quick_xml::DeError),
+}
+
+/// Why a document is not well-formed XML, as reported by the XML parser it was read with,
+/// which depends on whether the `quick-xml` feature is enabled.
+#[derive(Debug, Clone, thiserror::Error)]
+#[error(transparent)]
+pub struct XmlError(crate::xml::Error);
+
+macro_rules! from_xml_error {
+    ($($error:ty),*) => {
+        $(impl From<$error> for DeError {
+            fn from(error: $error) -> Self {
+                Self::InvalidXml(XmlError(error.into()))
+            }
+        })*
+    };
+}
+
+from_xml_error!(
+    crate::xml::Error,
+    std::str::Utf8Error,
+    crate::xml::EscapeError,
+    crate::xml::AttrError
+);
+
+#[cfg(feature = "serde")]
+impl From<quick_xml::DeError> for DeError {
+    fn from(error: quick_xml::DeError) -> Self {
+        match error {
+            quick_xml::DeError::Custom(message) => Self::Custom(message),
+            quick_xml::DeError::InvalidXml(error) => error.into(),
+            quick_xml::DeError::UnexpectedEof => Self::UnexpectedEof,
+            error => Self::Serde(error),
+        }
+    }
+}
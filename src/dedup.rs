@@ -0,0 +1,111 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{Bookmark, Metadata, RecentlyUsed};
+use chrono::{DateTime, Utc};
+use std::collections::hash_map::{Entry, HashMap};
+
+impl RecentlyUsed {
+    /// Merges bookmarks which point to the same file under differently spelled hrefs, such as
+    /// `file:///home/user/My%20Notes.txt` and `file:///home/user/My Notes.txt`, returning how
+    /// many bookmarks were merged away.
+    ///
+    /// Each file keeps its first bookmark, in its position, with the applications and groups
+    /// of all of them, the uses counted by each application added together. It keeps the
+    /// earliest time it was added, and the latest times it was modified and visited.
+    pub fn dedup(&mut self) -> usize {
+        let len = self.bookmarks.len();
+        let mut positions = HashMap::with_capacity(len);
+        let mut kept: Vec<Bookmark> = Vec::with_capacity(len);
+
+        for bookmark in self.bookmarks.drain(..) {
+            match positions.entry(bookmark.normalized_href()) {
+                Entry::Occupied(entry) => absorb(&mut kept[*entry.get()], bookmark),
+                Entry::Vacant(entry) => {
+                    entry.insert(kept.len());
+                    kept.push(bookmark);
+                }
+            }
+        }
+
+        self.bookmarks = kept;
+        len - self.bookmarks.len()
+    }
+}
+
+/// Merges a duplicate bookmark into the one which is kept.
+fn absorb(kept: &mut Bookmark, duplicate: Bookmark) {
+    let added_earlier = earlier(duplicate.added_time(), kept.added_time());
+    let modified_later = later(duplicate.modified_time(), kept.modified_time());
+    let visited_later = later(duplicate.visited_time(), kept.visited_time());
+    if added_earlier {
+        kept.added = duplicate.added;
+    }
+    if modified_later {
+        kept.modified = duplicate.modified;
+    }
+    if visited_later {
+        kept.visited = duplicate.visited;
+    }
+    kept.title = kept.title.take().or(duplicate.title);
+    kept.desc = kept.desc.take().or(duplicate.desc);
+
+    let Some(info) = duplicate.info else {
+        return;
+    };
+    let Some(kept_info) = &mut kept.info else {
+        kept.info = Some(info);
+        return;
+    };
+
+    for metadata in info.metadata {
+        let existing = kept_info
+            .metadata
+            .iter_mut()
+            .find(|existing| existing.owner == metadata.owner);
+        match existing {
+            Some(existing) => absorb_metadata(existing, metadata),
+            None => kept_info.metadata.push(metadata),
+        }
+    }
+}
+
+fn absorb_metadata(kept: &mut Metadata, duplicate: Metadata) {
+    kept.mime_type = kept.mime_type.take().or(duplicate.mime_type);
+    kept.icon = kept.icon.take().or(duplicate.icon);
+    kept.private |= duplicate.private;
+    for group in duplicate.groups {
+        kept.add_group(group);
+    }
+    if kept.extensions.is_empty() {
+        kept.extensions = duplicate.extensions;
+    }
+
+    for app in duplicate.applications.applications {
+        let existing = kept
+            .applications
+            .applications
+            .iter_mut()
+            .find(|existing| existing.name == app.name);
+        let Some(existing) = existing else {
+            kept.applications.applications.push(app);
+            continue;
+        };
+
+        existing.count = existing.count.saturating_add(app.count);
+        if later(app.modified_time(), existing.modified_time()) {
+            existing.modified = app.modified;
+            existing.exec = app.exec;
+        }
+    }
+}
+
+/// Whether a valid timestamp is earlier than another, or replaces an invalid one.
+fn earlier(time: Option<DateTime<Utc>>, than: Option<DateTime<Utc>>) -> bool {
+    time.is_some() && (than.is_none() || time < than)
+}
+
+/// Whether a valid timestamp is later than another, or replaces an invalid one.
+fn later(time: Option<DateTime<Utc>>, than: Option<DateTime<Utc>>) -> bool {
+    time > than
+}
@@ -0,0 +1,95 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{Bookmark, RecentlyUsed};
+use chrono::{DateTime, Utc};
+use percent_encoding::percent_decode_str;
+use std::fmt;
+
+/// Shows the display name of the file, when it was last used, and by which application, such as
+/// `notes.md, visited 3 hours ago by org.gnome.TextEditor`.
+impl fmt::Display for Bookmark {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.display_name())?;
+
+        let times = [
+            ("visited", self.visited_time()),
+            ("modified", self.modified_time()),
+            ("added", self.added_time()),
+        ];
+        if let Some((verb, time)) = times
+            .into_iter()
+            .find_map(|(verb, time)| Some((verb, time?)))
+        {
+            write!(f, ", {verb} {}", relative(time, Utc::now()))?;
+        }
+
+        if let Some(app) = self.last_application() {
+            write!(f, " by {}", app.name)?;
+        }
+        Ok(())
+    }
+}
+
+/// Shows each bookmark on a line of its own.
+impl fmt::Display for RecentlyUsed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for bookmark in &self.bookmarks {
+            writeln!(f, "{bookmark}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Bookmark {
+    /// The name to show for the bookmark in menus: its title if it has one, or else the
+    /// decoded name of the file it points to, such as `My Notes.md`.
+    ///
+    /// Trailing slashes of directories are ignored, and percent-encoded bytes which aren't
+    /// valid UTF-8 are replaced. Falls back to the whole href if it has no name.
+    pub fn display_name(&self) -> String {
+        if let Some(title) = self
+            .title
+            .as_deref()
+            .filter(|title| !title.trim().is_empty())
+        {
+            return title.to_owned();
+        }
+
+        let segment = self
+            .href
+            .trim_end_matches('/')
+            .rsplit_once('/')
+            .map(|(_, segment)| segment)
+            .filter(|segment| !segment.is_empty());
+
+        match segment {
+            Some(segment) => percent_decode_str(segment).decode_utf8_lossy().into_owned(),
+            None => self.href.clone(),
+        }
+    }
+}
+
+/// Describes how long ago a time was, such as `3 hours ago`.
+fn relative(time: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let elapsed = now.signed_duration_since(time);
+    if elapsed.num_seconds() < 0 {
+        return "in the future".to_owned();
+    }
+
+    let (count, unit) = match elapsed.num_minutes() {
+        0 => return "just now".to_owned(),
+        minutes @ 1..=59 => (minutes, "minute"),
+        _ => match elapsed.num_hours() {
+            hours @ 1..=23 => (hours, "hour"),
+            _ => match elapsed.num_days() {
+                days @ 1..=29 => (days, "day"),
+                days @ 30..=364 => (days / 30, "month"),
+                days => (days / 365, "year"),
+            },
+        },
+    };
+
+    let plural = if count == 1 { "" } else { "s" };
+    format!("{count} {unit}{plural} ago")
+}
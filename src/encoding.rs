@@ -0,0 +1,152 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::Error;
+use std::io::{self, BufRead, Cursor, Read};
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+
+/// A document which is either read as-is, or was transcoded to UTF-8 upfront.
+pub(crate) enum Input<R> {
+    Utf8(R),
+    Transcoded(Cursor<Vec<u8>>),
+}
+
+impl<R: BufRead> Read for Input<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Input::Utf8(reader) => reader.read(buf),
+            Input::Transcoded(reader) => reader.read(buf),
+        }
+    }
+}
+
+impl<R: BufRead> BufRead for Input<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        match self {
+            Input::Utf8(reader) => reader.fill_buf(),
+            Input::Transcoded(reader) => reader.fill_buf(),
+        }
+    }
+
+    fn consume(&mut self, amt: usize) {
+        match self {
+            Input::Utf8(reader) => reader.consume(amt),
+            Input::Transcoded(reader) => reader.consume(amt),
+        }
+    }
+}
+
+enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+}
+
+/// Detects the encoding of a document from its byte order mark or the `encoding`
+/// pseudo-attribute of its XML declaration, transcoding it to UTF-8 if necessary.
+///
+/// Documents in UTF-8 are passed through without being buffered.
+pub(crate) fn detect<R: BufRead>(mut reader: R) -> Result<Input<R>, Error> {
+    let head = reader.fill_buf().map_err(Error::Read)?;
+    let encoding = sniff(head)?;
+
+    if let Encoding::Utf8 = encoding {
+        return Ok(Input::Utf8(reader));
+    }
+
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes).map_err(Error::Read)?;
+
+    let text = match encoding {
+        Encoding::Utf16Le => decode_utf16(&bytes, u16::from_le_bytes)?,
+        Encoding::Utf16Be => decode_utf16(&bytes, u16::from_be_bytes)?,
+        _ => bytes.iter().map(|&byte| char::from(byte)).collect(),
+    };
+
+    Ok(Input::Transcoded(Cursor::new(
+        relabel_as_utf8(text).into_bytes(),
+    )))
+}
+
+/// Whether a document starting with `head` is encoded in UTF-8, and so can be read from any
+/// offset without being transcoded.
+pub(crate) fn is_utf8(head: &[u8]) -> Result<bool, Error> {
+    Ok(matches!(sniff(head)?, Encoding::Utf8))
+}
+
+fn sniff(head: &[u8]) -> Result<Encoding, Error> {
+    // The reader skips a UTF-8 byte order mark itself, keeping byte offsets intact.
+    let encoding = if head.starts_with(UTF8_BOM) {
+        Encoding::Utf8
+    } else if head.starts_with(&[0xFF, 0xFE]) || head.starts_with(b"<\0?\0") {
+        Encoding::Utf16Le
+    } else if head.starts_with(&[0xFE, 0xFF]) || head.starts_with(b"\0<\0?") {
+        Encoding::Utf16Be
+    } else {
+        match declared_encoding(head) {
+            None => Encoding::Utf8,
+            Some(label) => match label.to_ascii_lowercase().as_str() {
+                "utf-8" | "utf8" | "us-ascii" | "ascii" => Encoding::Utf8,
+                "iso-8859-1" | "iso8859-1" | "iso_8859-1" | "latin1" | "l1" => Encoding::Latin1,
+                _ => return Err(Error::UnsupportedEncoding(label)),
+            },
+        }
+    };
+
+    Ok(encoding)
+}
+
+fn decode_utf16(bytes: &[u8], from_bytes: fn([u8; 2]) -> u16) -> Result<String, Error> {
+    let units = bytes
+        .chunks_exact(2)
+        .map(|pair| from_bytes([pair[0], pair[1]]));
+
+    char::decode_utf16(units)
+        .collect::<Result<String, _>>()
+        .map(|text| text.trim_start_matches('\u{feff}').to_owned())
+        .map_err(|why| Error::Read(io::Error::new(io::ErrorKind::InvalidData, why)))
+}
+
+/// Finds the value of the `encoding` pseudo-attribute in the XML declaration at the start of
+/// the document, and the range it spans.
+fn find_encoding(head: &[u8]) -> Option<(usize, usize)> {
+    if !head.starts_with(b"<?xml") {
+        return None;
+    }
+
+    let end = head.windows(2).position(|pair| pair == b"?>")?;
+    let declaration = &head[..end];
+    let start = declaration
+        .windows(b"encoding".len())
+        .position(|window| window == b"encoding")?
+        + b"encoding".len();
+
+    let mut rest = declaration[start..].iter().enumerate();
+    let (_, &quote) = rest.find(|(_, byte)| !byte.is_ascii_whitespace() && **byte != b'=')?;
+    if quote != b'"' && quote != b'\'' {
+        return None;
+    }
+
+    let (value_start, _) = rest.next()?;
+    let value_end = declaration[start + value_start..]
+        .iter()
+        .position(|&byte| byte == quote)?;
+
+    Some((start + value_start, start + value_start + value_end))
+}
+
+fn declared_encoding(head: &[u8]) -> Option<String> {
+    let (start, end) = find_encoding(head)?;
+    Some(String::from_utf8_lossy(&head[start..end]).into_owned())
+}
+
+/// Rewrites the declared encoding of a transcoded document, so that it is not decoded twice
+/// nor written back out with a stale label.
+fn relabel_as_utf8(mut text: String) -> String {
+    if let Some((start, end)) = find_encoding(text.as_bytes()) {
+        text.replace_range(start..end, "UTF-8");
+    }
+    text
+}
@@ -0,0 +1,122 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use std::ffi::OsStr;
+use url::Url;
+
+/// Files which are never recorded by [`update_recently_used`](crate::update_recently_used),
+/// such as caches, temporary files, or anything opened in a private session.
+///
+/// Local files are matched by glob patterns on their paths, in which `*` matches anything
+/// within a path component, `**` matches across components, `?` matches one character, and
+/// a leading `~` stands for the home directory. Any location is matched by URI prefixes.
+///
+/// ```
+/// use recently_used_xbel::ExcludeRules;
+///
+/// let rules = ExcludeRules::new()
+///     .glob("/tmp/**")
+///     .glob("**/*.part")
+///     .uri_prefix("trash://");
+///
+/// assert!(rules.is_excluded("/tmp/build/output.log"));
+/// assert!(rules.is_excluded("/home/user/Downloads/video.mkv.part"));
+/// assert!(rules.is_excluded("trash:///report.odt"));
+/// assert!(!rules.is_excluded("/home/user/report.odt"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExcludeRules {
+    globs: Vec<String>,
+    prefixes: Vec<String>,
+}
+
+impl ExcludeRules {
+    /// Rules which exclude nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Excludes local files whose paths match a glob pattern, such as `~/.cache/**`.
+    pub fn glob(mut self, pattern: impl Into<String>) -> Self {
+        self.globs.push(pattern.into());
+        self
+    }
+
+    /// Excludes locations whose URIs start with a prefix, such as `trash://` or
+    /// `file:///media/`.
+    pub fn uri_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefixes.push(prefix.into());
+        self
+    }
+
+    /// Whether the rules exclude nothing.
+    pub fn is_empty(&self) -> bool {
+        self.globs.is_empty() && self.prefixes.is_empty()
+    }
+
+    /// Whether a file, given as a URL or an absolute path, is excluded.
+    pub fn is_excluded(&self, location: impl AsRef<OsStr>) -> bool {
+        !self.is_empty()
+            && crate::location_to_href(location.as_ref())
+                .is_some_and(|href| self.excludes_href(&href))
+    }
+
+    fn excludes_href(&self, href: &str) -> bool {
+        if self.prefixes.iter().any(|prefix| href.starts_with(prefix)) {
+            return true;
+        }
+
+        let path = Url::parse(href)
+            .ok()
+            .filter(|url| url.scheme() == "file")
+            .and_then(|url| url.to_file_path().ok());
+        let Some(path) = path else {
+            return false;
+        };
+
+        let path = path.as_os_str().as_encoded_bytes();
+        self.globs
+            .iter()
+            .any(|glob| matches(&expand_home(glob), path))
+    }
+}
+
+/// Replaces a leading `~` in a pattern with the home directory.
+fn expand_home(glob: &str) -> Vec<u8> {
+    let home = dirs::home_dir();
+    match (glob.strip_prefix('~'), home) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => {
+            let mut expanded = home.into_os_string().into_encoded_bytes();
+            expanded.extend_from_slice(rest.as_bytes());
+            expanded
+        }
+        _ => glob.as_bytes().to_vec(),
+    }
+}
+
+/// Whether a path matches a glob pattern.
+fn matches(pattern: &[u8], path: &[u8]) -> bool {
+    match pattern {
+        [] => path.is_empty(),
+        // `**/` matches any number of whole components, including none.
+        [b'*', b'*', b'/', rest @ ..] => (0..=path.len())
+            .filter(|&i| i == 0 || path[i - 1] == b'/')
+            .any(|i| matches(rest, &path[i..])),
+        [b'*', b'*', rest @ ..] => (0..=path.len()).any(|i| matches(rest, &path[i..])),
+        [b'*', rest @ ..] => {
+            let component = path.iter().position(|&b| b == b'/').unwrap_or(path.len());
+            (0..=component).any(|i| matches(rest, &path[i..]))
+        }
+        [b'?', rest @ ..] => match path {
+            [first, tail @ ..] if *first != b'/' => {
+                // Skips the continuation bytes of a multibyte character.
+                let continuation = tail.iter().take_while(|&&b| b & 0xc0 == 0x80).count();
+                matches(rest, &tail[continuation..])
+            }
+            _ => false,
+        },
+        [expected, rest @ ..] => path
+            .split_first()
+            .is_some_and(|(first, tail)| first == expected && matches(rest, tail)),
+    }
+}
@@ -0,0 +1,195 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{Application, Error};
+use std::fmt;
+use url::Url;
+
+/// The command line an application opens files with, as stored in its `exec` attribute.
+///
+/// GTK stores the command line shell-quoted as a whole, so that `gedit %u` is written as
+/// `'gedit %u'`. The arguments may contain the field codes `%u` and `%f`, which are replaced
+/// by the URI and the path of the file when the command line is expanded.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Exec {
+    args: Vec<String>,
+}
+
+impl Exec {
+    /// A command line from its arguments, starting with the program.
+    pub fn new<I, S>(args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            args: args.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// The command line GTK records for a program, which opens the file by its URI.
+    pub fn with_uri_placeholder(program: impl Into<String>) -> Self {
+        Self::new([program.into(), "%u".to_owned()])
+    }
+
+    /// A command line which opens the file by its local path.
+    pub fn with_file_placeholder(program: impl Into<String>) -> Self {
+        Self::new([program.into(), "%f".to_owned()])
+    }
+
+    /// Parses an `exec` attribute, removing GTK's quoting and splitting it into arguments.
+    ///
+    /// A command line which GTK quoted as a whole, such as `'gedit %u'`, is unquoted and then
+    /// split. Any other command line, such as `gedit 'my file' %u`, is split once.
+    pub fn parse(exec: &str) -> Result<Self, Error> {
+        let mut args = words(exec, true)?;
+        if let [command_line] = args.as_slice() {
+            if exec.trim_start().starts_with(['\'', '"']) {
+                args = words(command_line, true)?;
+            }
+        }
+
+        Ok(Self { args })
+    }
+
+    /// The arguments of the command line, starting with the program.
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+
+    /// The program the command line runs.
+    pub fn program(&self) -> Option<&str> {
+        self.args.first().map(String::as_str)
+    }
+
+    /// The arguments to open the file at `uri` with, replacing `%u` with the URI, `%f` with
+    /// its local path, and `%%` with `%`.
+    ///
+    /// Fails if an argument contains any other field code, or a `%f` for a file which is not
+    /// local.
+    pub fn expand(&self, uri: &str) -> Result<Vec<String>, Error> {
+        self.args
+            .iter()
+            .map(|arg| {
+                let mut expanded = String::with_capacity(arg.len());
+                let mut chars = arg.chars();
+
+                while let Some(c) = chars.next() {
+                    if c != '%' {
+                        expanded.push(c);
+                        continue;
+                    }
+
+                    match chars.next() {
+                        Some('u') => expanded.push_str(uri),
+                        Some('f') => {
+                            let path = Url::parse(uri)
+                                .ok()
+                                .filter(|url| url.scheme() == "file")
+                                .and_then(|url| url.to_file_path().ok())
+                                .ok_or(Error::Exec("`%f` requires a local file"))?;
+                            expanded.push_str(&path.to_string_lossy());
+                        }
+                        Some('%') => expanded.push('%'),
+                        _ => return Err(Error::Exec("unsupported field code")),
+                    }
+                }
+
+                Ok(expanded)
+            })
+            .collect()
+    }
+}
+
+/// Writes the command line as GTK stores it, such as `'gedit %u'`.
+impl fmt::Display for Exec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let command_line = self
+            .args
+            .iter()
+            .map(|arg| quote_if_needed(arg))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        f.write_str(&quote(&command_line))
+    }
+}
+
+impl From<Exec> for String {
+    fn from(exec: Exec) -> Self {
+        exec.to_string()
+    }
+}
+
+impl Application {
+    /// The command line the application opens files with.
+    pub fn command(&self) -> Result<Exec, Error> {
+        Exec::parse(&self.exec)
+    }
+}
+
+/// Quotes a string for a POSIX shell, as `g_shell_quote` does.
+fn quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+fn quote_if_needed(arg: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "%+,-./:=@_".contains(c);
+    if !arg.is_empty() && arg.chars().all(safe) {
+        arg.to_owned()
+    } else {
+        quote(arg)
+    }
+}
+
+/// Removes shell quoting from `s`, splitting it into words at unquoted whitespace if `split`
+/// is set, following the rules of `g_shell_unquote` and `g_shell_parse_argv`.
+fn words(s: &str, split: bool) -> Result<Vec<String>, Error> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if split && c.is_ascii_whitespace() => words.extend(word.take()),
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err(Error::Exec("unmatched `'`")),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('\n') => (),
+                            Some(c @ ('"' | '\\' | '`' | '$')) => word.push(c),
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err(Error::Exec("unmatched `\"`")),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err(Error::Exec("unmatched `\"`")),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                Some('\n') => (),
+                Some(c) => word.get_or_insert_with(String::new).push(c),
+                None => word.get_or_insert_with(String::new).push('\\'),
+            },
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+
+    words.extend(word);
+    Ok(words)
+}
@@ -0,0 +1,122 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{Bookmark, Error, RecentlyUsed};
+use std::io::Write;
+
+/// Writes the list as CSV, one row per bookmark after a header row.
+///
+/// The columns are `href`, `added`, `modified`, `visited`, `mime_type`, and
+/// `last_application`, which is the name of the application that most recently modified the
+/// bookmark. Absent values are left empty. Fields are quoted as described by RFC 4180.
+pub fn export_csv<W: Write>(recently_used: &RecentlyUsed, mut writer: W) -> Result<(), Error> {
+    write_row(
+        &mut writer,
+        [
+            "href",
+            "added",
+            "modified",
+            "visited",
+            "mime_type",
+            "last_application",
+        ],
+    )?;
+
+    for bookmark in &recently_used.bookmarks {
+        let metadata = bookmark.info.as_ref().and_then(|info| info.freedesktop());
+        let mime_type = metadata.and_then(|metadata| metadata.mime_type.as_ref());
+
+        write_row(
+            &mut writer,
+            [
+                &bookmark.href,
+                &bookmark.added,
+                bookmark.modified.as_deref().unwrap_or_default(),
+                bookmark.visited.as_deref().unwrap_or_default(),
+                mime_type.map_or("", |mime| &mime.mime_type),
+                bookmark.last_application().map_or("", |app| &app.name),
+            ],
+        )?;
+    }
+
+    writer.flush().map_err(Error::Write)
+}
+
+fn write_row<W: Write, const N: usize>(writer: &mut W, fields: [&str; N]) -> Result<(), Error> {
+    let mut row = String::new();
+    for (index, field) in fields.into_iter().enumerate() {
+        if index > 0 {
+            row.push(',');
+        }
+
+        if field.contains([',', '"', '\r', '\n']) {
+            row.push('"');
+            row.push_str(&field.replace('"', "\"\""));
+            row.push('"');
+        } else {
+            row.push_str(field);
+        }
+    }
+    row.push_str("\r\n");
+
+    writer.write_all(row.as_bytes()).map_err(Error::Write)
+}
+
+/// Writes the list in the Netscape bookmark file format, as the `bookmarks.html` files which
+/// web browsers import and export.
+///
+/// Bookmarks are listed in a single folder named after the list's title. Each is named after
+/// the file it points to, and carries its timestamps as `ADD_DATE`, `LAST_MODIFIED`, and
+/// `LAST_VISIT` where they are valid.
+pub fn export_netscape_html<W: Write>(
+    recently_used: &RecentlyUsed,
+    mut writer: W,
+) -> Result<(), Error> {
+    let title = escape_html(recently_used.title.as_deref().unwrap_or("Recently Used"));
+    let mut html = format!(
+        "<!DOCTYPE NETSCAPE-Bookmark-file-1>\n\
+         <META HTTP-EQUIV=\"Content-Type\" CONTENT=\"text/html; charset=UTF-8\">\n\
+         <TITLE>{title}</TITLE>\n\
+         <H1>{title}</H1>\n\
+         <DL><p>\n"
+    );
+
+    for bookmark in &recently_used.bookmarks {
+        html.push_str("    <DT><A HREF=\"");
+        html.push_str(&escape_html(&bookmark.href));
+        html.push('"');
+
+        for (attribute, time) in [
+            ("ADD_DATE", Some(&bookmark.added)),
+            ("LAST_MODIFIED", bookmark.modified.as_ref()),
+            ("LAST_VISIT", bookmark.visited.as_ref()),
+        ] {
+            if let Some(time) = time.and_then(|time| crate::time::parse(time)) {
+                html.push_str(&format!(" {attribute}=\"{}\"", time.timestamp()));
+            }
+        }
+
+        html.push('>');
+        html.push_str(&escape_html(&file_name(bookmark)));
+        html.push_str("</A>\n");
+    }
+
+    html.push_str("</DL><p>\n");
+    writer.write_all(html.as_bytes()).map_err(Error::Write)?;
+    writer.flush().map_err(Error::Write)
+}
+
+/// The name of the file a bookmark points to, or its whole URI if it isn't a local file.
+fn file_name(bookmark: &Bookmark) -> String {
+    bookmark
+        .to_path()
+        .and_then(|path| Some(path.file_name()?.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| bookmark.href.clone())
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,61 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{Bookmark, RecentlyUsed};
+use chrono::{DateTime, Utc};
+use std::cmp::Ordering;
+
+/// Weights for how long ago a bookmark was visited, in days, as Firefox weighs its history.
+const BUCKETS: &[(i64, f64)] = &[(4, 100.0), (14, 70.0), (31, 50.0), (90, 30.0)];
+
+/// The weight of visits older than every bucket, or at an unknown time.
+const OLDEST: f64 = 10.0;
+
+impl Bookmark {
+    /// How likely the file is to be opened again, combining how recently it was visited with
+    /// how many times it has been opened by any application, as Firefox's frecency does.
+    ///
+    /// Visits within the last 4 days weigh the most, falling off until 90 days ago. The
+    /// score is only meaningful when compared to those of other bookmarks at the same `now`.
+    pub fn score(&self, now: DateTime<Utc>) -> f64 {
+        let weight = self.effective_visited().map_or(OLDEST, |visited| {
+            let days = (now - visited).num_days();
+            BUCKETS
+                .iter()
+                .find(|(max, _)| days < *max)
+                .map_or(OLDEST, |(_, weight)| *weight)
+        });
+
+        let count: u64 = self
+            .info
+            .iter()
+            .flat_map(|info| &info.metadata)
+            .flat_map(|metadata| &metadata.applications.applications)
+            .map(|app| u64::from(app.count))
+            .sum();
+
+        weight * count.max(1) as f64
+    }
+}
+
+impl RecentlyUsed {
+    /// The bookmarks ordered by their [`Bookmark::score`] now, highest first. Bookmarks with
+    /// equal scores are ordered by when they were last visited, newest first.
+    pub fn ranked(&self) -> Vec<&Bookmark> {
+        let now = Utc::now();
+        let mut ranked: Vec<_> = self
+            .bookmarks
+            .iter()
+            .map(|bookmark| (bookmark.score(now), bookmark))
+            .collect();
+
+        ranked.sort_by(|(a_score, a), (b_score, b)| {
+            b_score
+                .partial_cmp(a_score)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| b.effective_visited().cmp(&a.effective_visited()))
+        });
+
+        ranked.into_iter().map(|(_, bookmark)| bookmark).collect()
+    }
+}
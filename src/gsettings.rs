@@ -0,0 +1,86 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use std::process::Command;
+use std::time::Duration;
+
+/// The schema of GNOME's privacy settings.
+const SCHEMA: &str = "org.gnome.desktop.privacy";
+
+/// The user's GNOME privacy settings for recent files, which GTK applications follow when
+/// they record files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrivacySettings {
+    /// Whether recently used files are recorded at all, which they aren't if
+    /// `remember-recent-files` is off or `recent-files-max-age` is 0.
+    pub remember_recent_files: bool,
+    /// How long files are kept after they were last visited, from `recent-files-max-age`,
+    /// or `None` if they are kept forever, which a negative number of days means.
+    pub recent_files_max_age: Option<Duration>,
+}
+
+impl Default for PrivacySettings {
+    /// The defaults of the schema: files are recorded and kept forever.
+    fn default() -> Self {
+        Self {
+            remember_recent_files: true,
+            recent_files_max_age: None,
+        }
+    }
+}
+
+impl PrivacySettings {
+    /// Reads the settings with a single run of the `gsettings` tool. Settings which can't be
+    /// read, such as outside of GNOME where the schema isn't installed, keep their defaults.
+    ///
+    /// The update functions read the settings once per process, when they first record a
+    /// file.
+    pub fn read() -> Self {
+        let output = Command::new("gsettings")
+            .args(["list-recursively", SCHEMA])
+            .output()
+            .ok()
+            .filter(|output| output.status.success());
+
+        output
+            .and_then(|output| String::from_utf8(output.stdout).ok())
+            .map_or_else(Self::default, |listing| parse(&listing))
+    }
+}
+
+/// Parses the settings as listed by `gsettings list-recursively`, one per line, such as
+/// `org.gnome.desktop.privacy recent-files-max-age 30`.
+pub(crate) fn parse(listing: &str) -> PrivacySettings {
+    let mut remember = None;
+    let mut max_age_days = None;
+
+    for line in listing.lines() {
+        let mut fields = line.split_whitespace();
+        if fields.next() != Some(SCHEMA) {
+            continue;
+        }
+        // Values may be annotated with their type, as in `int32 30`.
+        match (fields.next(), fields.last()) {
+            (Some("remember-recent-files"), Some(value)) => remember = value.parse::<bool>().ok(),
+            (Some("recent-files-max-age"), Some(value)) => max_age_days = value.parse::<i64>().ok(),
+            _ => (),
+        }
+    }
+
+    let mut settings = PrivacySettings::default();
+    if let Some(remember) = remember {
+        settings.remember_recent_files = remember;
+    }
+    match max_age_days {
+        // GNOME remembers files for no days at all by not recording them.
+        Some(0) => settings.remember_recent_files = false,
+        // A negative number of days means files are kept forever.
+        Some(days) => {
+            settings.recent_files_max_age = u64::try_from(days)
+                .ok()
+                .map(|days| Duration::from_secs(days.saturating_mul(24 * 60 * 60)));
+        }
+        None => (),
+    }
+    settings
+}
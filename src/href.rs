@@ -0,0 +1,129 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{Bookmark, RecentlyUsed};
+use percent_encoding::percent_decode_str;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use url::Url;
+
+/// Normalizes a URI so that different spellings of the same location compare equal.
+///
+/// The URI is parsed, which lowercases its scheme and host and resolves `.` and `..`
+/// segments, then percent-decoded, and a trailing slash is dropped from all but the root.
+/// Escaped slashes and percent signs, and bytes which aren't UTF-8, stay escaped, so that
+/// distinct locations never normalize to the same string.
+pub(crate) fn normalize(href: &str) -> String {
+    let parsed = Url::parse(href).map(String::from);
+    let href = parsed.as_deref().unwrap_or(href);
+
+    let mut normalized = String::with_capacity(href.len());
+    for chunk in decode(href).utf8_chunks() {
+        normalized.push_str(chunk.valid());
+        for byte in chunk.invalid() {
+            normalized.push_str(&format!("%{byte:02X}"));
+        }
+    }
+
+    if normalized.ends_with('/') && !normalized.ends_with("//") {
+        normalized.pop();
+    }
+    normalized
+}
+
+/// Percent-decodes a URI, except for `%2F` and `%25`, which are kept, in uppercase, since
+/// decoding them would change which location the URI points to.
+fn decode(mut href: &str) -> Vec<u8> {
+    let mut decoded = Vec::with_capacity(href.len());
+    while let Some(index) = href
+        .match_indices('%')
+        .map(|(index, _)| index)
+        .find(|&index| {
+            href.get(index + 1..index + 3)
+                .is_some_and(|hex| hex.eq_ignore_ascii_case("2F") || hex == "25")
+        })
+    {
+        decoded.extend(percent_decode_str(&href[..index]));
+        decoded.extend(href[index..index + 3].to_ascii_uppercase().bytes());
+        href = &href[index + 3..];
+    }
+    decoded.extend(percent_decode_str(href));
+    decoded
+}
+
+impl Bookmark {
+    /// The href normalized for comparison, which is how bookmarks are told apart when they
+    /// are compared or hashed.
+    ///
+    /// `file:///home/user/My%20Notes/` and `file:///home/user/My Notes` both normalize to
+    /// `file:///home/user/My Notes`.
+    pub fn normalized_href(&self) -> String {
+        normalize(&self.href)
+    }
+
+    /// An identifier for the location the bookmark points to, which stays the same across
+    /// reloads of the list and between processes.
+    pub fn id(&self) -> BookmarkId {
+        BookmarkId::new(&self.normalized_href())
+    }
+}
+
+/// An opaque identifier of a bookmark, derived from its normalized href, for use as a key in
+/// list models which must survive reloads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BookmarkId(u64);
+
+impl BookmarkId {
+    /// Hashes a normalized href with 64-bit FNV-1a, which unlike the standard library's
+    /// hasher is guaranteed to give the same result in every build.
+    fn new(normalized_href: &str) -> Self {
+        let hash = normalized_href
+            .bytes()
+            .fold(0xcbf2_9ce4_8422_2325, |hash: u64, byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+            });
+        Self(hash)
+    }
+
+    /// The identifier as a number.
+    pub fn as_u64(self) -> u64 {
+        self.0
+    }
+}
+
+/// Shows the identifier as 16 hexadecimal digits.
+impl fmt::Display for BookmarkId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl RecentlyUsed {
+    /// Finds the bookmark with the given identifier.
+    pub fn get(&self, id: BookmarkId) -> Option<&Bookmark> {
+        self.bookmarks.iter().find(|bookmark| bookmark.id() == id)
+    }
+
+    /// Finds the bookmark with the given identifier, to modify it.
+    pub fn get_mut(&mut self, id: BookmarkId) -> Option<&mut Bookmark> {
+        self.bookmarks
+            .iter_mut()
+            .find(|bookmark| bookmark.id() == id)
+    }
+}
+
+/// Bookmarks are equal if they point to the same location, regardless of how their hrefs are
+/// encoded or their other fields.
+impl PartialEq for Bookmark {
+    fn eq(&self, other: &Self) -> bool {
+        self.href == other.href || self.normalized_href() == other.normalized_href()
+    }
+}
+
+impl Eq for Bookmark {}
+
+impl Hash for Bookmark {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.normalized_href().hash(state);
+    }
+}
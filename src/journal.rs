@@ -0,0 +1,170 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{Bookmark, Error};
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// A change to the list recorded in its journal by a save.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JournalEntry {
+    /// When the change was saved.
+    pub time: String,
+    /// The name of the program which saved it.
+    pub application: String,
+    /// Whether the bookmark was added or removed.
+    pub change: JournalChange,
+    /// The location of the bookmark's file.
+    pub href: String,
+}
+
+/// How a [`JournalEntry`] changed the list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JournalChange {
+    Added,
+    Removed,
+}
+
+/// The size past which a journal is rotated to `.journal.1`, replacing the previous one, so
+/// that the two take up little more than twice this.
+pub(crate) const MAX_JOURNAL_LEN: u64 = 1024 * 1024;
+
+/// The location of the journal kept alongside a file, such as `recently-used.xbel.journal`.
+pub(crate) fn journal_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".journal");
+    PathBuf::from(name)
+}
+
+/// The location the journal is rotated to, such as `recently-used.xbel.journal.1`.
+pub(crate) fn rotated_journal_path(path: &Path) -> PathBuf {
+    let mut name = journal_path(path).into_os_string();
+    name.push(".1");
+    PathBuf::from(name)
+}
+
+/// Reads the journal of the file at the given location, oldest entry first.
+///
+/// Entries are appended by saves with [`WriteOptions::journal`](crate::WriteOptions::journal)
+/// set, and the oldest are dropped as the journal grows. A file without a journal has no
+/// entries, and lines which can't be read are skipped.
+pub fn read_journal(path: impl AsRef<Path>) -> Result<Vec<JournalEntry>, Error> {
+    let path = path.as_ref();
+    let mut entries = Vec::new();
+
+    for journal in [rotated_journal_path(path), journal_path(path)] {
+        match fs::read_to_string(journal) {
+            Ok(journal) => entries.extend(journal.lines().filter_map(parse_line)),
+            Err(why) if why.kind() == io::ErrorKind::NotFound => (),
+            Err(why) => return Err(Error::Read(why)),
+        }
+    }
+
+    Ok(entries)
+}
+
+fn parse_line(line: &str) -> Option<JournalEntry> {
+    let mut fields = line.split('\t');
+    let time = unescape(fields.next()?);
+    let application = unescape(fields.next()?);
+    let change = match fields.next()? {
+        "added" => JournalChange::Added,
+        "removed" => JournalChange::Removed,
+        _ => return None,
+    };
+    let href = unescape(fields.next()?);
+
+    Some(JournalEntry {
+        time,
+        application,
+        change,
+        href,
+    })
+}
+
+/// Appends the bookmarks added and removed by replacing `before` with `after` to the journal
+/// of the file at `path`, one tab-separated line each, rotating it first if it is too large.
+pub(crate) fn record(path: &Path, before: &[Bookmark], after: &[Bookmark]) -> io::Result<()> {
+    let before_hrefs: HashSet<&str> = before.iter().map(|b| b.href.as_str()).collect();
+    let after_hrefs: HashSet<&str> = after.iter().map(|b| b.href.as_str()).collect();
+
+    let added = after
+        .iter()
+        .filter(|b| !before_hrefs.contains(b.href.as_str()))
+        .map(|b| ("added", &b.href));
+    let removed = before
+        .iter()
+        .filter(|b| !after_hrefs.contains(b.href.as_str()))
+        .map(|b| ("removed", &b.href));
+
+    let time = escape(&crate::system_time_to_string(SystemTime::now()));
+    let application = escape(&application());
+    let mut lines = String::new();
+    for (change, href) in added.chain(removed) {
+        lines.push_str(&format!(
+            "{time}\t{application}\t{change}\t{}\n",
+            escape(href)
+        ));
+    }
+
+    if lines.is_empty() {
+        return Ok(());
+    }
+
+    let journal = journal_path(path);
+    match fs::metadata(&journal) {
+        Ok(metadata) if metadata.len() >= MAX_JOURNAL_LEN => {
+            fs::rename(&journal, rotated_journal_path(path))?;
+        }
+        Ok(_) => (),
+        Err(why) if why.kind() == io::ErrorKind::NotFound => (),
+        Err(why) => return Err(why),
+    }
+
+    let mut options = OpenOptions::new();
+    options.append(true).create(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    options.open(journal)?.write_all(lines.as_bytes())
+}
+
+/// The name of the running program.
+fn application() -> String {
+    std::env::current_exe()
+        .ok()
+        .and_then(|exe| Some(exe.file_name()?.to_string_lossy().into_owned()))
+        .unwrap_or_default()
+}
+
+fn escape(field: &str) -> String {
+    field
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+fn unescape(field: &str) -> String {
+    let mut unescaped = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            unescaped.push(ch);
+            continue;
+        }
+
+        match chars.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
@@ -0,0 +1,315 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{
+    Application, Applications, Bookmark, Error, Icon, Info, Metadata, MimeType, RecentlyUsed,
+    Unknown,
+};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+impl RecentlyUsed {
+    /// Serializes the list to JSON, for consumers without an XML parser.
+    ///
+    /// The field names are a stable contract:
+    ///
+    /// ```json
+    /// {
+    ///   "version": "1.0",
+    ///   "title": null,
+    ///   "desc": null,
+    ///   "info": null,
+    ///   "bookmarks": [{
+    ///     "href": "file:///home/user/notes.md",
+    ///     "added": "2024-09-01T10:00:00.000000Z",
+    ///     "modified": "2024-09-02T11:00:00.000000Z",
+    ///     "visited": null,
+    ///     "title": null,
+    ///     "desc": null,
+    ///     "info": {
+    ///       "metadata": [{
+    ///         "owner": "http://freedesktop.org",
+    ///         "mime_type": "text/markdown",
+    ///         "applications": [{
+    ///           "name": "org.gnome.TextEditor",
+    ///           "exec": "'gnome-text-editor %u'",
+    ///           "modified": "2024-09-02T11:00:00.000000Z",
+    ///           "count": 3
+    ///         }],
+    ///         "groups": ["Notes"],
+    ///         "icon": {"href": "text-x-generic", "mime_type": null},
+    ///         "private": false
+    ///       }]
+    ///     }
+    ///   }]
+    /// }
+    /// ```
+    ///
+    /// Every field is always written, with `null` for those which are absent. Unknown
+    /// attributes and elements, extension elements, and the prolog, are not included.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&DocumentJson::from(self))
+            .expect("serializing borrowed strings to JSON can't fail")
+    }
+
+    /// Parses a list serialized by [`RecentlyUsed::to_json`].
+    ///
+    /// Fields which may be absent can be either `null` or left out, and unknown fields are
+    /// ignored.
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        serde_json::from_str::<DocumentJson>(json)
+            .map(Self::from)
+            .map_err(Error::Json)
+    }
+}
+
+// The JSON format is a contract of its own, so it is mapped through these types rather than
+// through the serde derives of the model, which follow the XML document.
+
+#[derive(Serialize, Deserialize)]
+struct DocumentJson<'a> {
+    #[serde(default)]
+    version: Option<Cow<'a, str>>,
+    #[serde(default)]
+    title: Option<Cow<'a, str>>,
+    #[serde(default)]
+    desc: Option<Cow<'a, str>>,
+    #[serde(default)]
+    info: Option<InfoJson<'a>>,
+    #[serde(default)]
+    bookmarks: Option<Vec<BookmarkJson<'a>>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BookmarkJson<'a> {
+    href: Cow<'a, str>,
+    added: Cow<'a, str>,
+    #[serde(default)]
+    modified: Option<Cow<'a, str>>,
+    #[serde(default)]
+    visited: Option<Cow<'a, str>>,
+    #[serde(default)]
+    title: Option<Cow<'a, str>>,
+    #[serde(default)]
+    desc: Option<Cow<'a, str>>,
+    #[serde(default)]
+    info: Option<InfoJson<'a>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct InfoJson<'a> {
+    #[serde(default)]
+    metadata: Option<Vec<MetadataJson<'a>>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct MetadataJson<'a> {
+    owner: Cow<'a, str>,
+    #[serde(default)]
+    mime_type: Option<Cow<'a, str>>,
+    #[serde(default)]
+    applications: Option<Vec<ApplicationJson<'a>>>,
+    #[serde(default)]
+    groups: Option<Vec<Cow<'a, str>>>,
+    #[serde(default)]
+    icon: Option<IconJson<'a>>,
+    #[serde(default)]
+    private: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ApplicationJson<'a> {
+    name: Cow<'a, str>,
+    exec: Cow<'a, str>,
+    modified: Cow<'a, str>,
+    count: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct IconJson<'a> {
+    href: Cow<'a, str>,
+    #[serde(default)]
+    mime_type: Option<Cow<'a, str>>,
+}
+
+fn borrowed(value: &Option<String>) -> Option<Cow<'_, str>> {
+    value.as_deref().map(Cow::Borrowed)
+}
+
+fn owned(value: Option<Cow<'_, str>>) -> Option<String> {
+    value.map(Cow::into_owned)
+}
+
+impl<'a> From<&'a RecentlyUsed> for DocumentJson<'a> {
+    fn from(recently_used: &'a RecentlyUsed) -> Self {
+        Self {
+            version: Some(Cow::Borrowed(&recently_used.version)),
+            title: borrowed(&recently_used.title),
+            desc: borrowed(&recently_used.desc),
+            info: recently_used.info.as_ref().map(InfoJson::from),
+            bookmarks: Some(
+                recently_used
+                    .bookmarks
+                    .iter()
+                    .map(BookmarkJson::from)
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl From<DocumentJson<'_>> for RecentlyUsed {
+    fn from(document: DocumentJson<'_>) -> Self {
+        Self {
+            version: owned(document.version).unwrap_or_else(crate::xbel_version),
+            title: owned(document.title),
+            desc: owned(document.desc),
+            info: document.info.map(Info::from),
+            bookmarks: document
+                .bookmarks
+                .unwrap_or_default()
+                .into_iter()
+                .map(Bookmark::from)
+                .collect(),
+            ..RecentlyUsed::default()
+        }
+    }
+}
+
+impl<'a> From<&'a Bookmark> for BookmarkJson<'a> {
+    fn from(bookmark: &'a Bookmark) -> Self {
+        Self {
+            href: Cow::Borrowed(&bookmark.href),
+            added: Cow::Borrowed(&bookmark.added),
+            modified: borrowed(&bookmark.modified),
+            visited: borrowed(&bookmark.visited),
+            title: borrowed(&bookmark.title),
+            desc: borrowed(&bookmark.desc),
+            info: bookmark.info.as_ref().map(InfoJson::from),
+        }
+    }
+}
+
+impl From<BookmarkJson<'_>> for Bookmark {
+    fn from(bookmark: BookmarkJson<'_>) -> Self {
+        Self {
+            href: bookmark.href.into_owned(),
+            added: bookmark.added.into_owned(),
+            modified: owned(bookmark.modified),
+            visited: owned(bookmark.visited),
+            title: owned(bookmark.title),
+            desc: owned(bookmark.desc),
+            info: bookmark.info.map(Info::from),
+            #[cfg(feature = "spans")]
+            span: None,
+            unknown: Unknown::default(),
+        }
+    }
+}
+
+impl<'a> From<&'a Info> for InfoJson<'a> {
+    fn from(info: &'a Info) -> Self {
+        Self {
+            metadata: Some(info.metadata.iter().map(MetadataJson::from).collect()),
+        }
+    }
+}
+
+impl From<InfoJson<'_>> for Info {
+    fn from(info: InfoJson<'_>) -> Self {
+        Self {
+            metadata: info
+                .metadata
+                .unwrap_or_default()
+                .into_iter()
+                .map(Metadata::from)
+                .collect(),
+            unknown: Unknown::default(),
+        }
+    }
+}
+
+impl<'a> From<&'a Metadata> for MetadataJson<'a> {
+    fn from(metadata: &'a Metadata) -> Self {
+        Self {
+            owner: Cow::Borrowed(metadata.owner.as_str()),
+            mime_type: metadata
+                .mime_type
+                .as_ref()
+                .map(|mime| Cow::Borrowed(mime.mime_type.as_str())),
+            applications: Some(
+                metadata
+                    .applications
+                    .applications
+                    .iter()
+                    .map(ApplicationJson::from)
+                    .collect(),
+            ),
+            groups: Some(
+                metadata
+                    .groups
+                    .iter()
+                    .map(|group| Cow::Borrowed(group.as_str()))
+                    .collect(),
+            ),
+            icon: metadata.icon.as_ref().map(|icon| IconJson {
+                href: Cow::Borrowed(&icon.href),
+                mime_type: borrowed(&icon.mime_type),
+            }),
+            private: Some(metadata.private),
+        }
+    }
+}
+
+impl From<MetadataJson<'_>> for Metadata {
+    fn from(metadata: MetadataJson<'_>) -> Self {
+        Self {
+            owner: metadata.owner.into_owned().into(),
+            mime_type: owned(metadata.mime_type).map(|mime_type| MimeType { mime_type }),
+            applications: Applications {
+                applications: metadata
+                    .applications
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(Application::from)
+                    .collect(),
+            },
+            groups: metadata
+                .groups
+                .unwrap_or_default()
+                .into_iter()
+                .map(Cow::into_owned)
+                .collect(),
+            icon: metadata.icon.map(|icon| Icon {
+                href: icon.href.into_owned(),
+                mime_type: owned(icon.mime_type),
+            }),
+            private: metadata.private.unwrap_or(false),
+            extensions: Vec::new(),
+            unknown: Unknown::default(),
+        }
+    }
+}
+
+impl<'a> From<&'a Application> for ApplicationJson<'a> {
+    fn from(application: &'a Application) -> Self {
+        Self {
+            name: Cow::Borrowed(&application.name),
+            exec: Cow::Borrowed(&application.exec),
+            modified: Cow::Borrowed(&application.modified),
+            count: application.count,
+        }
+    }
+}
+
+impl From<ApplicationJson<'_>> for Application {
+    fn from(application: ApplicationJson<'_>) -> Self {
+        Self {
+            name: application.name.into_owned(),
+            exec: application.exec.into_owned(),
+            modified: application.modified.into_owned(),
+            count: application.count,
+            unknown: Unknown::default(),
+        }
+    }
+}
@@ -0,0 +1,992 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! A minimal XML pull parser and writer, used in place of quick-xml when the `quick-xml`
+//! feature is disabled.
+//!
+//! It mirrors the part of quick-xml's API that the reader and writer of this crate use, and
+//! handles what XBEL documents contain: elements, attributes, namespaces, text, CDATA
+//! sections, comments, processing instructions, and a document type declaration, in UTF-8.
+
+use std::borrow::Cow;
+use std::io::{self, BufRead, Write};
+use std::ops::Deref;
+use std::sync::Arc;
+
+const XML_NAMESPACE: &[u8] = b"http://www.w3.org/XML/1998/namespace";
+const XMLNS_NAMESPACE: &[u8] = b"http://www.w3.org/2000/xmlns/";
+
+fn is_whitespace(byte: u8) -> bool {
+    matches!(byte, b' ' | b'\t' | b'\r' | b'\n')
+}
+
+/// Why a document could not be read or written.
+#[derive(Debug, Clone, thiserror::Error)]
+pub(crate) enum Error {
+    #[error("I/O error: {0}")]
+    Io(Arc<io::Error>),
+    #[error("syntax error: {0}")]
+    Syntax(&'static str),
+    #[error("ill-formed document: {0}")]
+    IllFormed(String),
+    #[error("malformed UTF-8 input: {0}")]
+    NonDecodable(#[from] std::str::Utf8Error),
+    #[error(transparent)]
+    Escape(#[from] EscapeError),
+    #[error(transparent)]
+    InvalidAttr(#[from] AttrError),
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Self::Io(Arc::new(error))
+    }
+}
+
+/// Why text could not be unescaped.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub(crate) enum EscapeError {
+    #[error("entity is not terminated by `;`")]
+    Unterminated,
+    #[error("unrecognized entity `&{0};`")]
+    Unrecognized(String),
+}
+
+/// Why an attribute could not be read.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub(crate) enum AttrError {
+    #[error("attribute at position {0} is not of the form `key=\"value\"`")]
+    Malformed(usize),
+    #[error("attribute at position {0} duplicates the attribute at position {1}")]
+    Duplicated(usize, usize),
+}
+
+/// Escapes the markup characters of text.
+pub(crate) fn escape(raw: &str) -> Cow<'_, str> {
+    let is_markup = |byte: &u8| matches!(byte, b'<' | b'>' | b'&' | b'\'' | b'"');
+    let Some(first) = raw.bytes().position(|byte| is_markup(&byte)) else {
+        return Cow::Borrowed(raw);
+    };
+
+    let mut escaped = String::with_capacity(raw.len() + 8);
+    escaped.push_str(&raw[..first]);
+    for ch in raw[first..].chars() {
+        match ch {
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '&' => escaped.push_str("&amp;"),
+            '\'' => escaped.push_str("&apos;"),
+            '"' => escaped.push_str("&quot;"),
+            ch => escaped.push(ch),
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+/// Replaces the predefined entities and character references in text.
+pub(crate) fn unescape(raw: &str) -> Result<Cow<'_, str>, EscapeError> {
+    let Some(first) = raw.find('&') else {
+        return Ok(Cow::Borrowed(raw));
+    };
+
+    let mut unescaped = String::with_capacity(raw.len());
+    unescaped.push_str(&raw[..first]);
+    let mut rest = &raw[first..];
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        let entity = &rest[start + 1..];
+        let end = entity.find(';').ok_or(EscapeError::Unterminated)?;
+        let name = &entity[..end];
+        let ch = match name {
+            "lt" => '<',
+            "gt" => '>',
+            "amp" => '&',
+            "apos" => '\'',
+            "quot" => '"',
+            _ => character_reference(name)
+                .ok_or_else(|| EscapeError::Unrecognized(name.to_owned()))?,
+        };
+        unescaped.push(ch);
+        rest = &entity[end + 1..];
+    }
+    unescaped.push_str(rest);
+    Ok(Cow::Owned(unescaped))
+}
+
+/// Reads a character reference such as `#38` or `#x26`.
+fn character_reference(name: &str) -> Option<char> {
+    let number = name.strip_prefix('#')?;
+    let (digits, radix) = match number.strip_prefix('x') {
+        Some(hex) => (hex, 16),
+        None => (number, 10),
+    };
+    if digits.is_empty() || !digits.chars().all(|ch| ch.is_digit(radix)) {
+        return None;
+    }
+    let code = u32::from_str_radix(digits, radix).ok()?;
+    char::from_u32(code).filter(|&ch| ch != '\0')
+}
+
+/// A name, with its namespace prefix if it has one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct QName<'a>(pub &'a [u8]);
+
+impl<'a> QName<'a> {
+    pub fn prefix(&self) -> Option<&'a [u8]> {
+        let colon = self.0.iter().position(|&byte| byte == b':')?;
+        Some(&self.0[..colon])
+    }
+
+    pub fn local_name(&self) -> LocalName<'a> {
+        match self.0.iter().position(|&byte| byte == b':') {
+            Some(colon) => LocalName(&self.0[colon + 1..]),
+            None => LocalName(self.0),
+        }
+    }
+}
+
+impl AsRef<[u8]> for QName<'_> {
+    fn as_ref(&self) -> &[u8] {
+        self.0
+    }
+}
+
+/// A name without its namespace prefix.
+pub(crate) struct LocalName<'a>(&'a [u8]);
+
+impl<'a> LocalName<'a> {
+    pub fn into_inner(self) -> &'a [u8] {
+        self.0
+    }
+}
+
+/// The URI of a namespace.
+pub(crate) struct Namespace<'a>(&'a [u8]);
+
+impl<'a> Namespace<'a> {
+    pub fn into_inner(self) -> &'a [u8] {
+        self.0
+    }
+}
+
+/// The namespace that the prefix of a name is bound to.
+pub(crate) enum ResolveResult<'ns> {
+    Bound(Namespace<'ns>),
+    Unbound,
+    /// The name has a prefix which no namespace declaration in scope binds.
+    Unknown(Vec<u8>),
+}
+
+/// The content of a start tag or an empty-element tag, between `<` and `>` or `/>`.
+#[derive(Debug, Clone)]
+pub(crate) struct BytesStart<'a> {
+    buf: Cow<'a, [u8]>,
+    name_len: usize,
+}
+
+impl<'a> BytesStart<'a> {
+    fn new(name: impl Into<Cow<'a, str>>) -> Self {
+        let buf = match name.into() {
+            Cow::Borrowed(name) => Cow::Borrowed(name.as_bytes()),
+            Cow::Owned(name) => Cow::Owned(name.into_bytes()),
+        };
+        Self {
+            name_len: buf.len(),
+            buf,
+        }
+    }
+
+    fn wrap(content: &'a [u8]) -> Self {
+        Self {
+            name_len: content
+                .iter()
+                .position(|&byte| is_whitespace(byte))
+                .unwrap_or(content.len()),
+            buf: Cow::Borrowed(content),
+        }
+    }
+
+    fn borrow(&self) -> BytesStart<'_> {
+        BytesStart {
+            buf: Cow::Borrowed(&self.buf),
+            name_len: self.name_len,
+        }
+    }
+
+    pub fn into_owned(self) -> BytesStart<'static> {
+        BytesStart {
+            buf: Cow::Owned(self.buf.into_owned()),
+            name_len: self.name_len,
+        }
+    }
+
+    pub fn name(&self) -> QName<'_> {
+        QName(&self.buf[..self.name_len])
+    }
+
+    pub fn local_name(&self) -> LocalName<'_> {
+        self.name().local_name()
+    }
+
+    pub fn attributes(&self) -> Attributes<'_> {
+        Attributes {
+            bytes: &self.buf,
+            position: self.name_len,
+            keys: Vec::new(),
+            failed: false,
+        }
+    }
+
+    /// Appends an attribute, whose value is already escaped.
+    fn push_attribute(&mut self, attr: Attribute) {
+        let buf = self.buf.to_mut();
+        buf.push(b' ');
+        buf.extend_from_slice(attr.key.as_ref());
+        buf.extend_from_slice(b"=\"");
+        buf.extend_from_slice(&attr.value);
+        buf.push(b'"');
+    }
+}
+
+impl Deref for BytesStart<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+/// The name of an end tag.
+#[derive(Debug, Clone)]
+pub(crate) struct BytesEnd<'a>(Cow<'a, [u8]>);
+
+impl Deref for BytesEnd<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// Escaped text, or the content of other markup, such as a comment.
+#[derive(Debug, Clone)]
+pub(crate) struct BytesText<'a>(Cow<'a, [u8]>);
+
+impl<'a> BytesText<'a> {
+    /// Text with its markup characters escaped.
+    pub fn new(content: &'a str) -> Self {
+        Self(match escape(content) {
+            Cow::Borrowed(content) => Cow::Borrowed(content.as_bytes()),
+            Cow::Owned(content) => Cow::Owned(content.into_bytes()),
+        })
+    }
+
+    fn wrap(content: &'a [u8]) -> Self {
+        Self(Cow::Borrowed(content))
+    }
+}
+
+impl Deref for BytesText<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum Event<'a> {
+    Start(BytesStart<'a>),
+    End(BytesEnd<'a>),
+    Empty(BytesStart<'a>),
+    Text(BytesText<'a>),
+    CData(BytesText<'a>),
+    Comment(BytesText<'a>),
+    /// The XML declaration, between `<?` and `?>`.
+    Decl(BytesText<'a>),
+    /// A processing instruction, between `<?` and `?>`.
+    Pi(BytesText<'a>),
+    /// The document type declaration, after `<!DOCTYPE` and before `>`.
+    DocType(BytesText<'a>),
+    Eof,
+}
+
+/// An attribute, with its value as written in the document.
+pub(crate) struct Attribute<'a> {
+    pub key: QName<'a>,
+    pub value: Cow<'a, [u8]>,
+}
+
+impl Attribute<'_> {
+    pub fn unescape_value(&self) -> Result<Cow<'_, str>, Error> {
+        let value = std::str::from_utf8(&self.value)?;
+        Ok(unescape(value)?)
+    }
+}
+
+/// The attributes of a start tag, which stop at the first malformed or duplicated one.
+pub(crate) struct Attributes<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    /// Where the keys of the attributes read so far start and end.
+    keys: Vec<(usize, usize)>,
+    failed: bool,
+}
+
+impl<'a> Attributes<'a> {
+    fn skip_whitespace(&self, from: usize) -> usize {
+        self.bytes[from..]
+            .iter()
+            .position(|&byte| !is_whitespace(byte))
+            .map_or(self.bytes.len(), |len| from + len)
+    }
+
+    fn read(&mut self) -> Option<Result<Attribute<'a>, AttrError>> {
+        let bytes = self.bytes;
+        let start = self.skip_whitespace(self.position);
+        if start == bytes.len() {
+            return None;
+        }
+
+        let key_end = bytes[start..]
+            .iter()
+            .position(|&byte| byte == b'=' || is_whitespace(byte))
+            .map_or(bytes.len(), |len| start + len);
+        let equals = self.skip_whitespace(key_end);
+        if key_end == start || bytes.get(equals) != Some(&b'=') {
+            return Some(Err(AttrError::Malformed(start)));
+        }
+
+        let open = self.skip_whitespace(equals + 1);
+        let quote = match bytes.get(open) {
+            Some(&quote @ (b'"' | b'\'')) => quote,
+            _ => return Some(Err(AttrError::Malformed(start))),
+        };
+        let Some(len) = bytes[open + 1..].iter().position(|&byte| byte == quote) else {
+            return Some(Err(AttrError::Malformed(start)));
+        };
+        let close = open + 1 + len;
+        self.position = close + 1;
+
+        let key = &bytes[start..key_end];
+        let duplicate = self.keys.iter().find(|&&(s, e)| &bytes[s..e] == key);
+        if let Some(&(previous, _)) = duplicate {
+            return Some(Err(AttrError::Duplicated(start, previous)));
+        }
+        self.keys.push((start, key_end));
+
+        Some(Ok(Attribute {
+            key: QName(key),
+            value: Cow::Borrowed(&bytes[open + 1..close]),
+        }))
+    }
+}
+
+impl<'a> Iterator for Attributes<'a> {
+    type Item = Result<Attribute<'a>, AttrError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let result = self.read()?;
+        self.failed = result.is_err();
+        Some(result)
+    }
+}
+
+/// Decodes the content of events, which is always UTF-8.
+pub(crate) struct Decoder;
+
+impl Decoder {
+    pub fn decode<'b>(&self, bytes: &'b [u8]) -> Result<Cow<'b, str>, Error> {
+        Ok(Cow::Borrowed(std::str::from_utf8(bytes)?))
+    }
+}
+
+pub(crate) struct Config {
+    trim_text: bool,
+}
+
+impl Config {
+    /// Whether whitespace is trimmed from both ends of text, and whitespace-only text is
+    /// skipped.
+    pub fn trim_text(&mut self, trim: bool) {
+        self.trim_text = trim;
+    }
+}
+
+/// The kinds of markup which start with `<!`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Bang {
+    Comment,
+    CData,
+    DocType,
+}
+
+/// An element which is open.
+struct Open {
+    name: Vec<u8>,
+    /// The number of namespace bindings in scope outside of the element.
+    scope: usize,
+}
+
+/// Reads a document one event at a time, resolving the namespaces of elements.
+pub(crate) struct NsReader<R> {
+    reader: R,
+    config: Config,
+    /// The number of bytes consumed.
+    offset: u64,
+    /// Where the event being read starts.
+    event_offset: u64,
+    error_offset: u64,
+    open: Vec<Open>,
+    /// Namespace prefixes bound by the open elements, innermost last, with an empty prefix
+    /// for the default namespace.
+    bindings: Vec<(Vec<u8>, Vec<u8>)>,
+    /// The number of bindings to drop before the next event, when the element that
+    /// declared them ended with the last one.
+    ended_scope: Option<usize>,
+    done: bool,
+}
+
+impl<R: BufRead> NsReader<R> {
+    pub fn from_reader(reader: R) -> Self {
+        Self {
+            reader,
+            config: Config { trim_text: false },
+            offset: 0,
+            event_offset: 0,
+            error_offset: 0,
+            open: Vec::new(),
+            bindings: Vec::new(),
+            ended_scope: None,
+            done: false,
+        }
+    }
+
+    pub fn config_mut(&mut self) -> &mut Config {
+        &mut self.config
+    }
+
+    #[cfg(feature = "spans")]
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    pub fn decoder(&self) -> Decoder {
+        Decoder
+    }
+
+    /// The number of bytes consumed.
+    pub fn buffer_position(&self) -> u64 {
+        self.offset
+    }
+
+    /// Where the event that failed to be read starts.
+    pub fn error_position(&self) -> u64 {
+        self.error_offset
+    }
+
+    /// Reads the next event, along with the namespace of its name if it is a tag.
+    pub fn read_resolved_event_into<'b>(
+        &mut self,
+        buf: &'b mut Vec<u8>,
+    ) -> Result<(ResolveResult<'_>, Event<'b>), Error> {
+        let event = self.read_event_into(buf)?;
+        let resolved = match &event {
+            Event::Start(e) | Event::Empty(e) => self.resolve(e.name()),
+            Event::End(e) => self.resolve(QName(e)),
+            _ => ResolveResult::Unbound,
+        };
+        Ok((resolved, event))
+    }
+
+    /// Reads the next event, whose content is appended to `buf`.
+    pub fn read_event_into<'b>(&mut self, buf: &'b mut Vec<u8>) -> Result<Event<'b>, Error> {
+        if let Some(scope) = self.ended_scope.take() {
+            self.bindings.truncate(scope);
+        }
+        if self.done {
+            return Ok(Event::Eof);
+        }
+
+        let result = self.read_event(buf);
+        match &result {
+            Ok(Event::Eof) => self.done = true,
+            Err(_) => {
+                self.done = true;
+                self.error_offset = self.event_offset;
+            }
+            Ok(_) => (),
+        }
+        result
+    }
+
+    /// Reads up to the end tag which closes the element named `end`, whose start tag was
+    /// the last event read.
+    pub fn read_to_end_into(&mut self, end: QName, buf: &mut Vec<u8>) -> Result<(), Error> {
+        let mut depth = 0usize;
+        loop {
+            buf.clear();
+            match self.read_event_into(buf)? {
+                Event::Start(e) if e.name() == end => depth += 1,
+                Event::End(e) if *e == *end.0 => match depth.checked_sub(1) {
+                    Some(outer) => depth = outer,
+                    None => return Ok(()),
+                },
+                Event::Eof => {
+                    let name = String::from_utf8_lossy(end.0);
+                    return Err(Error::IllFormed(format!("missing end tag `</{name}>`")));
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn resolve(&self, name: QName) -> ResolveResult<'_> {
+        let prefix = name.prefix();
+        match prefix {
+            Some(b"xml") => return ResolveResult::Bound(Namespace(XML_NAMESPACE)),
+            Some(b"xmlns") => return ResolveResult::Bound(Namespace(XMLNS_NAMESPACE)),
+            _ => (),
+        }
+
+        let binding = self
+            .bindings
+            .iter()
+            .rev()
+            .find(|(bound, _)| bound.as_slice() == prefix.unwrap_or_default());
+        match (binding, prefix) {
+            (Some((_, uri)), _) if !uri.is_empty() => ResolveResult::Bound(Namespace(uri)),
+            (None, Some(prefix)) => ResolveResult::Unknown(prefix.to_vec()),
+            _ => ResolveResult::Unbound,
+        }
+    }
+
+    fn read_event<'b>(&mut self, buf: &'b mut Vec<u8>) -> Result<Event<'b>, Error> {
+        let start = buf.len();
+        if self.config.trim_text {
+            self.skip_whitespace()?;
+        }
+        self.event_offset = self.offset;
+
+        match self.next_byte()? {
+            None => Ok(Event::Eof),
+            Some(b'<') => self.read_markup(buf, start),
+            Some(byte) => {
+                buf.push(byte);
+                self.read_until(buf, false, |byte| byte == b'<')?;
+                let mut text = &buf[start..];
+                if self.config.trim_text {
+                    let len = text.iter().rposition(|&byte| !is_whitespace(byte));
+                    text = &text[..len.map_or(0, |len| len + 1)];
+                }
+                Ok(Event::Text(BytesText::wrap(text)))
+            }
+        }
+    }
+
+    /// Reads the markup which follows a `<`.
+    fn read_markup<'b>(&mut self, buf: &'b mut Vec<u8>, start: usize) -> Result<Event<'b>, Error> {
+        let closed = match self.peek_byte()? {
+            Some(b'!') => return self.read_bang(buf, start),
+            Some(b'?') => {
+                let mut previous = 0;
+                self.read_until(buf, true, |byte| {
+                    let end = byte == b'>' && previous == b'?';
+                    previous = byte;
+                    end
+                })?
+            }
+            Some(b'/') => self.read_until(buf, true, |byte| byte == b'>')?,
+            _ => {
+                let mut quote = None;
+                self.read_until(buf, true, |byte| match quote {
+                    Some(open) => {
+                        if byte == open {
+                            quote = None;
+                        }
+                        false
+                    }
+                    None if byte == b'"' || byte == b'\'' => {
+                        quote = Some(byte);
+                        false
+                    }
+                    None => byte == b'>',
+                })?
+            }
+        };
+        if !closed {
+            return Err(Error::Syntax("unclosed tag"));
+        }
+
+        let content = &buf[start..];
+        match content.first() {
+            Some(b'?') if content.len() > 1 && content.ends_with(b"?") => {
+                let content = &content[1..content.len() - 1];
+                let is_declaration = content.starts_with(b"xml")
+                    && content.get(3).is_none_or(|&byte| is_whitespace(byte));
+                Ok(match is_declaration {
+                    true => Event::Decl(BytesText::wrap(content)),
+                    false => Event::Pi(BytesText::wrap(content)),
+                })
+            }
+            Some(b'?') => Err(Error::Syntax("unclosed processing instruction")),
+            Some(b'/') => self.end(&content[1..]).map(Event::End),
+            Some(_) => Ok(self.start(content)),
+            None => Err(Error::IllFormed("tag without a name".to_owned())),
+        }
+    }
+
+    fn start<'b>(&mut self, content: &'b [u8]) -> Event<'b> {
+        let scope = self.bindings.len();
+        let (content, empty) = match content.strip_suffix(b"/") {
+            Some(content) => (content, true),
+            None => (content, false),
+        };
+        let start = BytesStart::wrap(content);
+
+        // Malformed attributes are reported when the element itself is read.
+        for attr in start.attributes().flatten() {
+            let key = attr.key.as_ref();
+            let prefix = match key.strip_prefix(b"xmlns") {
+                Some(b"") => &[][..],
+                Some(rest) => match rest.strip_prefix(b":") {
+                    Some(prefix) => prefix,
+                    None => continue,
+                },
+                None => continue,
+            };
+            self.bindings
+                .push((prefix.to_vec(), attr.value.into_owned()));
+        }
+
+        if empty {
+            self.ended_scope = Some(scope);
+            Event::Empty(start)
+        } else {
+            let name = start.name().as_ref().to_vec();
+            self.open.push(Open { name, scope });
+            Event::Start(start)
+        }
+    }
+
+    fn end<'b>(&mut self, content: &'b [u8]) -> Result<BytesEnd<'b>, Error> {
+        let len = content.iter().rposition(|&byte| !is_whitespace(byte));
+        let name = &content[..len.map_or(0, |len| len + 1)];
+
+        let Some(open) = self.open.pop() else {
+            let name = String::from_utf8_lossy(name);
+            return Err(Error::IllFormed(format!("unmatched end tag `</{name}>`")));
+        };
+        if open.name != name {
+            let expected = String::from_utf8_lossy(&open.name);
+            let name = String::from_utf8_lossy(name);
+            return Err(Error::IllFormed(format!(
+                "expected `</{expected}>`, found `</{name}>`"
+            )));
+        }
+
+        self.ended_scope = Some(open.scope);
+        Ok(BytesEnd(Cow::Borrowed(name)))
+    }
+
+    /// Reads a comment, CDATA section, or document type declaration, which follows `<!`.
+    fn read_bang<'b>(&mut self, buf: &'b mut Vec<u8>, start: usize) -> Result<Event<'b>, Error> {
+        const COMMENT: &[u8] = b"!--";
+        const CDATA: &[u8] = b"![CDATA[";
+        const DOCTYPE: &[u8] = b"!DOCTYPE";
+
+        // Reads just enough to tell which one it is.
+        let kind = loop {
+            let content = &buf[start..];
+            if content == COMMENT {
+                break Bang::Comment;
+            }
+            if content == CDATA {
+                break Bang::CData;
+            }
+            if content.eq_ignore_ascii_case(DOCTYPE) {
+                break Bang::DocType;
+            }
+            let is_prefix = COMMENT.starts_with(content)
+                || CDATA.starts_with(content)
+                || DOCTYPE[..content.len().min(DOCTYPE.len())].eq_ignore_ascii_case(content);
+            if !is_prefix {
+                return Err(Error::Syntax("unknown markup after `<!`"));
+            }
+            match self.next_byte()? {
+                Some(byte) => buf.push(byte),
+                None => return Err(Error::Syntax("unclosed markup after `<!`")),
+            }
+        };
+
+        let closed = match kind {
+            Bang::DocType => {
+                let mut quote = None;
+                let mut depth = 0usize;
+                self.read_until(buf, true, |byte| match (quote, byte) {
+                    (Some(open), _) => {
+                        if byte == open {
+                            quote = None;
+                        }
+                        false
+                    }
+                    (None, b'"' | b'\'') => {
+                        quote = Some(byte);
+                        false
+                    }
+                    (None, b'[') => {
+                        depth += 1;
+                        false
+                    }
+                    (None, b']') => {
+                        depth = depth.saturating_sub(1);
+                        false
+                    }
+                    (None, b'>') => depth == 0,
+                    (None, _) => false,
+                })?
+            }
+            // Comments end with `-->`, and CDATA sections with `]]>`.
+            Bang::Comment | Bang::CData => {
+                let delimiter = if kind == Bang::Comment { b'-' } else { b']' };
+                let mut repeated = 0;
+                self.read_until(buf, true, |byte| {
+                    if byte == b'>' && repeated >= 2 {
+                        return true;
+                    }
+                    repeated = if byte == delimiter { repeated + 1 } else { 0 };
+                    false
+                })?
+            }
+        };
+        if !closed {
+            return Err(Error::Syntax("unclosed markup after `<!`"));
+        }
+
+        let content = &buf[start..];
+        match kind {
+            Bang::DocType => {
+                let name = content[DOCTYPE.len()..]
+                    .iter()
+                    .position(|&byte| !is_whitespace(byte))
+                    .ok_or_else(|| Error::IllFormed("DOCTYPE without a name".to_owned()))?;
+                let content = &content[DOCTYPE.len() + name..];
+                Ok(Event::DocType(BytesText::wrap(content)))
+            }
+            Bang::Comment => {
+                let content = &content[COMMENT.len()..content.len() - 2];
+                Ok(Event::Comment(BytesText::wrap(content)))
+            }
+            Bang::CData => {
+                let content = &content[CDATA.len()..content.len() - 2];
+                Ok(Event::CData(BytesText::wrap(content)))
+            }
+        }
+    }
+
+    fn consume(&mut self, len: usize) {
+        self.reader.consume(len);
+        self.offset += len as u64;
+    }
+
+    fn peek_byte(&mut self) -> Result<Option<u8>, Error> {
+        Ok(self.reader.fill_buf()?.first().copied())
+    }
+
+    fn next_byte(&mut self) -> Result<Option<u8>, Error> {
+        let byte = self.peek_byte()?;
+        if byte.is_some() {
+            self.consume(1);
+        }
+        Ok(byte)
+    }
+
+    fn skip_whitespace(&mut self) -> Result<(), Error> {
+        loop {
+            let available = self.reader.fill_buf()?;
+            let len = available.len();
+            let whitespace = available
+                .iter()
+                .position(|&byte| !is_whitespace(byte))
+                .unwrap_or(len);
+            self.consume(whitespace);
+            if len == 0 || whitespace < len {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Appends the input to `buf` up to the first byte for which `end` returns true, which
+    /// is consumed if `consume_end` is set but never appended. Returns whether such a byte
+    /// was found before the end of the input.
+    fn read_until(
+        &mut self,
+        buf: &mut Vec<u8>,
+        consume_end: bool,
+        mut end: impl FnMut(u8) -> bool,
+    ) -> Result<bool, Error> {
+        loop {
+            let available = self.reader.fill_buf()?;
+            if available.is_empty() {
+                return Ok(false);
+            }
+
+            match available.iter().position(|&byte| end(byte)) {
+                Some(len) => {
+                    buf.extend_from_slice(&available[..len]);
+                    self.consume(len + usize::from(consume_end));
+                    return Ok(true);
+                }
+                None => {
+                    let len = available.len();
+                    buf.extend_from_slice(available);
+                    self.consume(len);
+                }
+            }
+        }
+    }
+}
+
+/// The indentation of the elements being written.
+struct Indentation {
+    /// Whether the next tag goes on a line of its own, which it doesn't after text.
+    should_line_break: bool,
+    indent_char: u8,
+    indent_size: usize,
+    current: Vec<u8>,
+}
+
+/// Writes XML events, optionally putting each tag on a line of its own, indented by how
+/// deeply it is nested, in the same layout as quick-xml.
+pub(crate) struct Writer<W> {
+    writer: W,
+    indent: Option<Indentation>,
+}
+
+impl<W: Write> Writer<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            indent: None,
+        }
+    }
+
+    pub fn new_with_indent(writer: W, indent_char: u8, indent_size: usize) -> Self {
+        Self {
+            writer,
+            indent: Some(Indentation {
+                should_line_break: false,
+                indent_char,
+                indent_size,
+                current: Vec::new(),
+            }),
+        }
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    pub fn create_element<'a>(&'a mut self, name: impl Into<Cow<'a, str>>) -> ElementWriter<'a, W> {
+        ElementWriter {
+            writer: self,
+            start: BytesStart::new(name),
+        }
+    }
+
+    pub fn write_event(&mut self, event: Event) -> Result<(), Error> {
+        let mut next_should_line_break = true;
+        match event {
+            Event::Start(e) => {
+                self.write_wrapped(b"<", &e, b">")?;
+                if let Some(indent) = &mut self.indent {
+                    let len = indent.current.len() + indent.indent_size;
+                    indent.current.resize(len, indent.indent_char);
+                }
+            }
+            Event::End(e) => {
+                if let Some(indent) = &mut self.indent {
+                    let len = indent.current.len().saturating_sub(indent.indent_size);
+                    indent.current.truncate(len);
+                }
+                self.write_wrapped(b"</", &e, b">")?;
+            }
+            Event::Empty(e) => self.write_wrapped(b"<", &e, b"/>")?,
+            Event::Text(e) => {
+                next_should_line_break = false;
+                self.writer.write_all(&e)?;
+            }
+            Event::CData(e) => {
+                next_should_line_break = false;
+                self.writer.write_all(b"<![CDATA[")?;
+                self.writer.write_all(&e)?;
+                self.writer.write_all(b"]]>")?;
+            }
+            Event::Comment(e) => self.write_wrapped(b"<!--", &e, b"-->")?,
+            Event::Decl(e) | Event::Pi(e) => self.write_wrapped(b"<?", &e, b"?>")?,
+            Event::DocType(e) => self.write_wrapped(b"<!DOCTYPE ", &e, b">")?,
+            Event::Eof => (),
+        }
+        if let Some(indent) = &mut self.indent {
+            indent.should_line_break = next_should_line_break;
+        }
+        Ok(())
+    }
+
+    fn write_wrapped(&mut self, before: &[u8], value: &[u8], after: &[u8]) -> Result<(), Error> {
+        if let Some(indent) = &self.indent {
+            if indent.should_line_break {
+                self.writer.write_all(b"\n")?;
+                self.writer.write_all(&indent.current)?;
+            }
+        }
+        self.writer.write_all(before)?;
+        self.writer.write_all(value)?;
+        self.writer.write_all(after)?;
+        Ok(())
+    }
+}
+
+/// Writes an element, once its attributes have been added.
+pub(crate) struct ElementWriter<'a, W> {
+    writer: &'a mut Writer<W>,
+    start: BytesStart<'a>,
+}
+
+impl<'a, W: Write> ElementWriter<'a, W> {
+    pub fn with_attribute(mut self, attr: Attribute) -> Self {
+        self.start.push_attribute(attr);
+        self
+    }
+
+    pub fn with_attributes<'b>(mut self, attrs: impl IntoIterator<Item = Attribute<'b>>) -> Self {
+        for attr in attrs {
+            self.start.push_attribute(attr);
+        }
+        self
+    }
+
+    pub fn write_empty(self) -> Result<&'a mut Writer<W>, Error> {
+        self.writer.write_event(Event::Empty(self.start))?;
+        Ok(self.writer)
+    }
+
+    pub fn write_text_content(self, text: BytesText) -> Result<&'a mut Writer<W>, Error> {
+        self.write_inner_content(|writer| writer.write_event(Event::Text(text)))
+    }
+
+    pub fn write_inner_content<F, E>(self, closure: F) -> Result<&'a mut Writer<W>, E>
+    where
+        F: FnOnce(&mut Writer<W>) -> Result<(), E>,
+        E: From<Error>,
+    {
+        self.writer.write_event(Event::Start(self.start.borrow()))?;
+        closure(self.writer)?;
+        let end = BytesEnd(Cow::Borrowed(self.start.name().0));
+        self.writer.write_event(Event::End(end))?;
+        Ok(self.writer)
+    }
+}
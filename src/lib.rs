@@ -15,116 +15,549 @@
 //! }
 //! ```
 
-use chrono::{DateTime, SecondsFormat, Utc};
-use custom_writer::custom_write;
-use quick_xml::DeError;
+pub use bookmark_iter::BookmarkIter;
+#[cfg(feature = "serde")]
+pub use borrowed::{
+    ApplicationRef, ApplicationsRef, BookmarkRef, InfoRef, MetadataRef, MimeTypeRef,
+    RecentlyUsedRef,
+};
+pub use builder::{ApplicationBuilder, BookmarkBuilder};
+pub use cached::CachedRecents;
+use chrono::{DateTime, Utc};
+pub use custom_writer::{custom_write, custom_write_to, custom_write_to_with, custom_write_with};
+pub use de_error::{DeError, XmlError};
+pub use exclude::ExcludeRules;
+pub use exec::Exec;
+pub use export::{export_csv, export_netscape_html};
+#[cfg(feature = "gsettings")]
+pub use gsettings::PrivacySettings;
+pub use href::BookmarkId;
+pub use journal::{read_journal, JournalChange, JournalEntry};
+pub use lock::{FileLock, DEFAULT_LOCK_TIMEOUT};
+pub use mime_type::{DefaultMimeDetector, MimeDetector};
+pub use owner::MetadataOwner;
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 use std::{
-    fs::{self},
-    path::PathBuf,
-    time::SystemTime,
+    borrow::Cow,
+    collections::HashMap,
+    ffi::OsStr,
+    fs::{self, File},
+    io::{self, BufRead, Write},
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime},
 };
+pub use store::{RecentlyUsedStore, Transaction};
+pub use strict::Violation;
+pub use time::TimePeriod;
 use url::Url;
+pub use validate::Issue;
+pub use visitor::{visit, XbelVisitor};
+mod bookmark_iter;
+#[cfg(feature = "serde")]
+mod borrowed;
+mod builder;
+mod cached;
+mod custom_reader;
 mod custom_writer;
+mod de_error;
+mod dedup;
+mod display;
+mod encoding;
+mod exclude;
+mod exec;
+mod export;
+mod frecency;
+#[cfg(feature = "gsettings")]
+mod gsettings;
+mod href;
+mod journal;
+#[cfg(feature = "json")]
+mod json;
+#[cfg(not(feature = "quick-xml"))]
+mod lean_xml;
+#[cfg(feature = "spans")]
+mod line_counter;
+mod lock;
+mod mime_type;
+#[cfg(all(feature = "mmap", unix))]
+mod mmap;
+mod owner;
+mod recent;
+mod repair;
+mod retention;
+mod save;
+#[cfg(feature = "sniff")]
+mod sniff;
+mod store;
+mod strict;
+mod tail;
+mod time;
+mod trace;
+mod validate;
+mod visitor;
+pub mod xbel;
+mod xml;
+
+/// The namespace of the `bookmark:` elements in recently-used.xbel.
+pub const BOOKMARK_NAMESPACE: &str = "http://www.freedesktop.org/standards/desktop-bookmarks";
+
+/// The namespace of the `mime:` elements in recently-used.xbel.
+pub const MIME_NAMESPACE: &str = "http://www.freedesktop.org/standards/shared-mime-info";
+
+/// The version of XBEL which this crate reads and writes.
+pub const XBEL_VERSION: &str = "1.0";
+
+/// The owner of the metadata block which holds the MIME type and applications of a bookmark.
+pub const FREEDESKTOP_OWNER: &str = "http://freedesktop.org";
 
 /// Stores recently-opened files accessed by the desktop user.
-#[derive(Debug, Clone, Deserialize, Serialize)]
-#[serde(rename = "xbel", rename_all = "kebab-case")]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "serde", serde(rename = "xbel", rename_all = "kebab-case"))]
 pub struct RecentlyUsed {
-    #[serde(rename = "@xmlns:bookmark")]
+    /// The version of XBEL the document conforms to, which is `1.0` if it wasn't specified.
+    #[cfg_attr(
+        feature = "serde",
+        serde(rename = "@version", default = "xbel_version")
+    )]
+    pub version: String,
+    #[cfg_attr(
+        feature = "serde",
+        serde(rename = "@xmlns:bookmark", default = "bookmark_namespace")
+    )]
     pub xmlns_bookmark: String,
-    #[serde(rename = "@xmlns:mime")]
+    #[cfg_attr(
+        feature = "serde",
+        serde(rename = "@xmlns:mime", default = "mime_namespace")
+    )]
     pub xmlns_mime: String,
 
+    /// The title of the collection.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub title: Option<String>,
+    /// A description of the collection.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub desc: Option<String>,
+    /// Metadata about the collection as a whole.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub info: Option<Info>,
+
     /// Files that have been recently used.
-    #[serde(rename = "bookmark", default)]
+    #[cfg_attr(feature = "serde", serde(rename = "bookmark", default))]
     pub bookmarks: Vec<Bookmark>,
+
+    /// The XML declaration and DOCTYPE which preceded the root element.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub prolog: Prolog,
+
+    /// Attributes and elements which are not modeled, kept so that they survive a rewrite.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub unknown: Unknown,
+}
+
+/// The markup preceding the root element of a document, retained so that rewriting the file
+/// keeps it intact.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Prolog {
+    /// The XML declaration, such as `<?xml version="1.0" encoding="UTF-8"?>`.
+    pub declaration: Option<String>,
+    /// The document type declaration, such as `<!DOCTYPE xbel>`.
+    pub doctype: Option<String>,
+}
+
+impl Default for RecentlyUsed {
+    fn default() -> Self {
+        Self {
+            version: xbel_version(),
+            xmlns_bookmark: bookmark_namespace(),
+            xmlns_mime: mime_namespace(),
+            title: None,
+            desc: None,
+            info: None,
+            bookmarks: Vec::new(),
+            prolog: Prolog::default(),
+            unknown: Unknown::default(),
+        }
+    }
+}
+
+fn xbel_version() -> String {
+    XBEL_VERSION.to_owned()
+}
+
+fn bookmark_namespace() -> String {
+    BOOKMARK_NAMESPACE.to_owned()
+}
+
+fn mime_namespace() -> String {
+    MIME_NAMESPACE.to_owned()
+}
+
+impl RecentlyUsed {
+    /// The bookmarks an application should show in its recent files, which are those not
+    /// registered privately, and those the application registered itself.
+    pub fn visible_to<'a>(&'a self, application: &'a str) -> impl Iterator<Item = &'a Bookmark> {
+        self.bookmarks
+            .iter()
+            .filter(move |bookmark| !bookmark.is_private() || bookmark.has_application(application))
+    }
+
+    /// The bookmarks keyed by their href, for looking up many files without scanning the list
+    /// each time. If a file is listed more than once, the last bookmark for it is kept.
+    pub fn by_href(&self) -> HashMap<&str, &Bookmark> {
+        self.bookmarks
+            .iter()
+            .map(|bookmark| (bookmark.href.as_str(), bookmark))
+            .collect()
+    }
+
+    /// Converts the list into its bookmarks keyed by their href, as in
+    /// [`RecentlyUsed::by_href`].
+    pub fn into_map(self) -> HashMap<String, Bookmark> {
+        self.bookmarks
+            .into_iter()
+            .map(|bookmark| (bookmark.href.clone(), bookmark))
+            .collect()
+    }
 }
 
 /// A file that was recently opened by the desktop user.
-#[derive(Debug, Clone, Deserialize, Serialize)]
-#[serde(rename_all = "kebab-case")]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
 pub struct Bookmark {
     /// The location of the file.
-    #[serde(rename = "@href")]
+    #[cfg_attr(feature = "serde", serde(rename = "@href"))]
     pub href: String,
     /// When the file was added to the list.
-    #[serde(rename = "@added")]
+    #[cfg_attr(feature = "serde", serde(rename = "@added"))]
     pub added: String,
-    /// When the file was last modified.
-    #[serde(rename = "@modified")]
-    pub modified: String,
-    /// When the file was last visited.
-    #[serde(rename = "@visited")]
-    pub visited: String,
+    /// When the file was last modified, if known.
+    #[cfg_attr(
+        feature = "serde",
+        serde(rename = "@modified", default, skip_serializing_if = "Option::is_none")
+    )]
+    pub modified: Option<String>,
+    /// When the file was last visited, if known.
+    #[cfg_attr(
+        feature = "serde",
+        serde(rename = "@visited", default, skip_serializing_if = "Option::is_none")
+    )]
+    pub visited: Option<String>,
+    /// The title of the bookmark, which GTK stores the display name of remote files in.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub title: Option<String>,
+    /// A description of the bookmark.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub desc: Option<String>,
     /// Additional metadata and applications related to the bookmark.
-    #[serde(rename = "info")]
+    #[cfg_attr(feature = "serde", serde(rename = "info"))]
     pub info: Option<Info>,
+    /// Where the bookmark was found in the document it was parsed from.
+    #[cfg(feature = "spans")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub span: Option<Span>,
+
+    /// Attributes and elements which are not modeled, kept so that they survive a rewrite.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub unknown: Unknown,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
-#[serde(rename_all = "kebab-case")]
+impl Bookmark {
+    /// Sets the title of the bookmark.
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Sets the description of the bookmark.
+    pub fn with_desc(mut self, desc: impl Into<String>) -> Self {
+        self.desc = Some(desc.into());
+        self
+    }
+
+    /// The location of the file as a URL.
+    pub fn url(&self) -> Result<Url, Error> {
+        Url::parse(&self.href).map_err(Error::Url)
+    }
+
+    /// Whether the bookmark should only be shown by the applications which registered it.
+    pub fn is_private(&self) -> bool {
+        let metadata = self.info.as_ref().and_then(Info::freedesktop);
+        metadata.is_some_and(|metadata| metadata.private)
+    }
+
+    /// Whether the given application has registered the bookmark.
+    pub fn has_application(&self, name: &str) -> bool {
+        let metadata = self.info.as_ref().and_then(Info::freedesktop);
+        metadata.is_some_and(|metadata| {
+            metadata
+                .applications
+                .applications
+                .iter()
+                .any(|app| app.name == name)
+        })
+    }
+
+    /// The entry of an application which registered the file, to modify it.
+    pub fn application_mut(&mut self, name: &str) -> Option<&mut Application> {
+        self.info
+            .iter_mut()
+            .flat_map(|info| &mut info.metadata)
+            .flat_map(|metadata| &mut metadata.applications.applications)
+            .find(|app| app.name == name)
+    }
+
+    /// The application which most recently modified the bookmark, preferring those listed
+    /// later when their timestamps can't be told apart.
+    pub fn last_application(&self) -> Option<&Application> {
+        let metadata = self.info.as_ref()?.freedesktop()?;
+        metadata
+            .applications
+            .applications
+            .iter()
+            .max_by_key(|app| app.modified_time())
+    }
+
+    /// The host the file is on, such as the server of a `file://server/path` URL on an NFS
+    /// mount. Local files, whose URLs have no host or `localhost`, have none.
+    pub fn host(&self) -> Option<String> {
+        let url = self.url().ok()?;
+        url.host_str()
+            .filter(|host| !(url.scheme() == "file" && *host == "localhost"))
+            .map(str::to_owned)
+    }
+
+    /// Whether the bookmark is of a file on this machine.
+    pub fn is_local(&self) -> bool {
+        self.url()
+            .is_ok_and(|url| url.scheme() == "file" && self.host().is_none())
+    }
+
+    /// The path of the file, if it is a local file.
+    ///
+    /// Percent-encoded characters, such as the spaces and non-ASCII characters in a file's
+    /// name, are decoded. Files on other hosts have no path, since it would refer to the file
+    /// of the same name on this machine.
+    pub fn to_path(&self) -> Option<PathBuf> {
+        self.url().ok()?.to_file_path().ok()
+    }
+}
+
+/// The location of a bookmark in the document it was parsed from.
+#[cfg(feature = "spans")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    /// The byte offset of the start of the bookmark's element.
+    pub offset: u64,
+    /// The byte offset just past the end of the bookmark's element.
+    pub end: u64,
+    /// The line on which the bookmark's element starts, counting from 1.
+    pub line: u64,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
 pub struct Info {
-    /// Metadata about the bookmark.
-    #[serde(rename = "metadata")]
-    pub metadata: Metadata,
+    /// Metadata about the bookmark, one block per owner.
+    #[cfg_attr(feature = "serde", serde(rename = "metadata", default))]
+    pub metadata: Vec<Metadata>,
+
+    /// Attributes and elements which are not modeled, kept so that they survive a rewrite.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub unknown: Unknown,
+}
+
+impl Info {
+    /// The metadata block owned by freedesktop.org, if the bookmark has one.
+    pub fn freedesktop(&self) -> Option<&Metadata> {
+        self.metadata
+            .iter()
+            .find(|metadata| metadata.owner == MetadataOwner::Freedesktop)
+    }
+
+    /// The metadata block owned by freedesktop.org, if the bookmark has one.
+    pub fn freedesktop_mut(&mut self) -> Option<&mut Metadata> {
+        self.metadata
+            .iter_mut()
+            .find(|metadata| metadata.owner == MetadataOwner::Freedesktop)
+    }
 }
 
 /// Metadata containing MIME type and application info.
-#[derive(Debug, Clone, Deserialize, Serialize)]
-#[serde(rename_all = "kebab-case")]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
 pub struct Metadata {
     /// The owner of the metadata.
-    #[serde(rename = "@owner")]
-    pub owner: String,
+    #[cfg_attr(feature = "serde", serde(rename = "@owner"))]
+    pub owner: MetadataOwner,
 
     /// The MIME type information.
-    #[serde(rename = "mime-type")]
+    #[cfg_attr(feature = "serde", serde(rename = "mime-type"))]
     pub mime_type: Option<MimeType>,
 
     /// The applications that have accessed the file.
-    #[serde(rename = "applications")]
+    ///
+    /// Blocks of owners other than freedesktop.org may omit this.
+    #[cfg_attr(feature = "serde", serde(rename = "applications", default))]
     pub applications: Applications,
+
+    /// The groups the bookmark belongs to, which GTK's `RecentManager` lets applications
+    /// filter on. Read from the `<bookmark:groups>` element.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub groups: Vec<String>,
+
+    /// The icon the registering application recorded for the file, read from the
+    /// `<bookmark:icon>` element.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub icon: Option<Icon>,
+
+    /// Whether the bookmark was registered privately, meaning it should only be shown by the
+    /// applications which registered it. Read from the `<bookmark:private/>` element.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub private: bool,
+
+    /// Elements in namespaces of their own, which applications attach to store custom data,
+    /// such as the tags of a project. An element's namespace must be declared on it or on the
+    /// root element of the document.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub extensions: Vec<RawElement>,
+
+    /// Attributes and elements which are not modeled, kept so that they survive a rewrite.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub unknown: Unknown,
+}
+
+impl Metadata {
+    /// Whether the bookmark belongs to the given group.
+    pub fn has_group(&self, group: &str) -> bool {
+        self.groups.iter().any(|g| g == group)
+    }
+
+    /// Adds the bookmark to a group, returning `false` if it already belonged to it.
+    pub fn add_group(&mut self, group: impl Into<String>) -> bool {
+        let group = group.into();
+        if self.has_group(&group) {
+            return false;
+        }
+        self.groups.push(group);
+        true
+    }
+
+    /// The first extension element with the given qualified name, such as `ex:tags`.
+    pub fn extension(&self, name: &str) -> Option<&RawElement> {
+        self.extensions.iter().find(|element| element.name == name)
+    }
+
+    /// Removes the bookmark from a group, returning `false` if it didn't belong to it.
+    pub fn remove_group(&mut self, group: &str) -> bool {
+        let len = self.groups.len();
+        self.groups.retain(|g| g != group);
+        self.groups.len() != len
+    }
+}
+
+/// An icon for a bookmark.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Icon {
+    /// The location of the icon, which may be a URI or the name of a themed icon.
+    pub href: String,
+    /// The MIME type of the icon, if known.
+    pub mime_type: Option<String>,
 }
 
 /// The MIME type of the file.
-#[derive(Debug, Clone, Deserialize, Serialize)]
-#[serde(rename_all = "kebab-case")]
+///
+/// Only [`MimeType::new`] validates the type. One read from a document is kept as it is
+/// written, so that [`RecentlyUsed::validate`] can report it if it is malformed.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
 pub struct MimeType {
     /// The type of the file (e.g., "text/markdown").
-    #[serde(rename = "@type")]
+    #[cfg_attr(feature = "serde", serde(rename = "@type"))]
     pub mime_type: String,
 }
 
 /// A list of applications that accessed the bookmark.
-#[derive(Debug, Clone, Deserialize, Serialize)]
-#[serde(rename_all = "kebab-case")]
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
 pub struct Applications {
     /// The list of applications.
     //#[serde(rename(deserialize="application", serialize="bookmark:applications"))]
-    #[serde(rename = "application")]
+    #[cfg_attr(feature = "serde", serde(rename = "application"))]
     pub applications: Vec<Application>,
 }
 
 /// An application that accessed the bookmark.
-#[derive(Debug, Clone, Deserialize, Serialize)]
-#[serde(rename_all = "kebab-case")]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
 pub struct Application {
     /// The name of the application.
-    #[serde(rename = "@name")]
+    #[cfg_attr(feature = "serde", serde(rename = "@name"))]
     pub name: String,
 
     /// The command used to execute the application.
-    #[serde(rename = "@exec")]
+    #[cfg_attr(feature = "serde", serde(rename = "@exec"))]
     pub exec: String,
 
     /// When the application last modified the bookmark.
-    #[serde(rename = "@modified")]
+    #[cfg_attr(feature = "serde", serde(rename = "@modified"))]
     pub modified: String,
 
     /// The number of times the application has accessed the bookmark.
-    #[serde(rename = "@count")]
+    ///
+    /// Counts too large to fit are read as `u32::MAX`, and those which aren't numbers at all
+    /// as 1, rather than failing to parse the document.
+    #[cfg_attr(feature = "serde", serde(rename = "@count"))]
     pub count: u32,
+
+    /// Attributes and elements which are not modeled, kept so that they survive a rewrite.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub unknown: Unknown,
+}
+
+/// The content of an element which is not modeled by this crate.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Unknown {
+    /// Attributes as `(name, value)` pairs, with values unescaped.
+    pub attributes: Vec<(String, String)>,
+    /// Child elements, which are written after the modeled children.
+    pub elements: Vec<RawElement>,
+}
+
+/// An arbitrary XML element, such as one added by an application for its own purposes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RawElement {
+    /// The qualified name of the element, including any namespace prefix.
+    pub name: String,
+    /// Attributes as `(name, value)` pairs, with values unescaped.
+    pub attributes: Vec<(String, String)>,
+    /// Child elements.
+    pub children: Vec<RawElement>,
+    /// The unescaped text content of the element, with surrounding whitespace trimmed.
+    pub text: String,
 }
 
 /// An error that can occur when accessing recently-used files.
@@ -136,6 +569,8 @@ pub enum Error {
     Deserialization(#[source] DeError),
     #[error("could not serialize new file")]
     Serialization(#[source] Option<DeError>),
+    #[error("document is encoded as `{0}`, which is not supported")]
+    UnsupportedEncoding(String),
     #[error("could not read recents file")]
     Read(#[source] std::io::Error),
     #[error("could not read metadata from path")]
@@ -144,6 +579,38 @@ pub enum Error {
     Path,
     #[error("could not update recent files")]
     Update,
+    #[error("could not write recents file")]
+    Write(#[source] std::io::Error),
+    #[error("document violates the XBEL specification")]
+    Violations(Vec<Violation>),
+    #[error("could not lock recent files")]
+    Lock(#[source] std::io::Error),
+    #[error("timed out waiting for another process to finish updating recent files")]
+    LockTimeout,
+    #[error("invalid MIME type")]
+    Mime(#[source] mime::FromStrError),
+    #[error("bookmark location is not a valid URL")]
+    Url(#[source] url::ParseError),
+    #[error("invalid exec command line: {0}")]
+    Exec(&'static str),
+    #[error("bookmark has no registered applications")]
+    NoApplications,
+    #[error("file is not in the list of recently used files")]
+    NotFound,
+    #[cfg(feature = "json")]
+    #[error("could not parse JSON")]
+    Json(#[source] serde_json::Error),
+}
+
+/// A part of the document that was skipped by a lenient parse.
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("skipped invalid content at byte {offset}")]
+pub struct ParseWarning {
+    /// The byte offset in the document where the skipped content starts.
+    pub offset: u64,
+    /// Why the content was skipped.
+    #[source]
+    pub error: DeError,
 }
 
 /// The path where the recently-used.xbel file is expected to be found.
@@ -153,28 +620,209 @@ pub fn dir() -> Option<PathBuf> {
 
 /// Convenience function for parsing the recently-used.xbel file in its default location.
 pub fn parse_file() -> Result<RecentlyUsed, Error> {
-    let path = dir().ok_or(Error::DoesNotExist)?;
-    let file_content = fs::read_to_string(&path).map_err(|err| Error::Read(err))?;
-    quick_xml::de::from_str(&file_content).map_err(|err| Error::Deserialization(err))
+    parse_path(dir().ok_or(Error::DoesNotExist)?)
+}
+
+/// Parses a recently-used.xbel file at the given location.
+///
+/// Useful for inspecting files of other users, backups, or sandboxed applications.
+pub fn parse_path(path: impl AsRef<Path>) -> Result<RecentlyUsed, Error> {
+    parse_reader(open(path.as_ref())?)
+}
+
+/// Parses the recently-used.xbel file in its default location, treating a missing or empty
+/// file as an empty list.
+///
+/// Fresh user accounts often have no recently-used.xbel file at all.
+pub fn parse_file_or_default() -> Result<RecentlyUsed, Error> {
+    parse_path_or_default(dir().ok_or(Error::DoesNotExist)?)
+}
+
+/// Parses a recently-used.xbel file at the given location, treating a missing or empty file
+/// as an empty list.
+pub fn parse_path_or_default(path: impl AsRef<Path>) -> Result<RecentlyUsed, Error> {
+    let options = ParseOptions {
+        missing_as_empty: true,
+        ..ParseOptions::default()
+    };
+    parse_path_with(path, &options)
+}
+
+/// Parses only the `n` most recently visited bookmarks of the recently-used.xbel file in its
+/// default location.
+///
+/// The returned bookmarks are ordered from the most to the least recently visited. Older
+/// bookmarks are skipped over without being deserialized, which makes this considerably
+/// faster than [`parse_file`] for large files when only the latest entries are displayed.
+pub fn parse_recent(n: usize) -> Result<RecentlyUsed, Error> {
+    parse_path_recent(dir().ok_or(Error::DoesNotExist)?, n)
+}
+
+/// Parses only the `n` most recently visited bookmarks of a recently-used.xbel file at the
+/// given location.
+///
+/// See [`parse_recent`] for details.
+pub fn parse_path_recent(path: impl AsRef<Path>, n: usize) -> Result<RecentlyUsed, Error> {
+    recent::read(open(path.as_ref())?, n)
+}
+
+/// Parses only the bookmarks at the end of the recently-used.xbel file in its default location.
+///
+/// New bookmarks are appended to the end of the file, so this is a fast way to find the most
+/// recently added files without reading a large file in full. Only the last `bytes_from_end`
+/// bytes are read, and the bookmarks starting within them are returned in document order.
+/// The attributes of the root element and the prolog are not read, and are left at their
+/// defaults.
+///
+/// Files which are not encoded in UTF-8 are read in full.
+pub fn parse_tail(bytes_from_end: u64) -> Result<RecentlyUsed, Error> {
+    parse_path_tail(dir().ok_or(Error::DoesNotExist)?, bytes_from_end)
+}
+
+/// Parses only the bookmarks at the end of a recently-used.xbel file at the given location.
+///
+/// See [`parse_tail`] for details.
+pub fn parse_path_tail(path: impl AsRef<Path>, bytes_from_end: u64) -> Result<RecentlyUsed, Error> {
+    tail::read(
+        File::open(path.as_ref()).map_err(Error::Read)?,
+        bytes_from_end,
+    )
+}
+
+/// Parses the recently-used.xbel file in its default location, enforcing the rules of the
+/// XBEL 1.0 specification.
+///
+/// In addition to the document being well-formed, the root element must have a version of 1.0,
+/// every href must be a valid URI, every timestamp must be in RFC 3339 format, and every
+/// bookmark must have at least one application. All violations are collected and returned
+/// together in [`Error::Violations`].
+pub fn parse_strict() -> Result<RecentlyUsed, Error> {
+    parse_path_strict(dir().ok_or(Error::DoesNotExist)?)
+}
+
+/// Parses a recently-used.xbel file at the given location, enforcing the rules of the XBEL
+/// 1.0 specification.
+///
+/// See [`parse_strict`] for details.
+pub fn parse_path_strict(path: impl AsRef<Path>) -> Result<RecentlyUsed, Error> {
+    strict::read(open(path.as_ref())?)
+}
+
+/// Parses a recently-used.xbel document from any buffered reader.
+///
+/// Useful when the contents are already available in memory, or come from somewhere other
+/// than the local filesystem.
+pub fn parse_reader<R: BufRead>(reader: R) -> Result<RecentlyUsed, Error> {
+    parse_reader_with(reader, &ParseOptions::default())
+}
+
+/// Options controlling how a document is parsed.
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    /// Rejects attributes which are not part of the format, rather than preserving them in
+    /// [`Unknown`]. Namespace declarations are always accepted.
+    ///
+    /// Off by default, since applications are free to extend the file with their own data.
+    pub deny_unknown_attributes: bool,
+    /// Parses a file which doesn't exist or is empty as an empty list, as
+    /// [`parse_path_or_default`] does, rather than failing. Only applies when parsing a file.
+    ///
+    /// Off by default.
+    pub missing_as_empty: bool,
+}
+
+/// Parses a recently-used.xbel document from any buffered reader with the given options.
+pub fn parse_reader_with<R: BufRead>(
+    reader: R,
+    options: &ParseOptions,
+) -> Result<RecentlyUsed, Error> {
+    custom_reader::read(reader, false, options).map(|(recently_used, _)| recently_used)
+}
+
+/// Parses the recently-used.xbel file in its default location with the given options.
+pub fn parse_file_with(options: &ParseOptions) -> Result<RecentlyUsed, Error> {
+    parse_path_with(dir().ok_or(Error::DoesNotExist)?, options)
+}
+
+/// Parses a recently-used.xbel file at the given location with the given options.
+pub fn parse_path_with(
+    path: impl AsRef<Path>,
+    options: &ParseOptions,
+) -> Result<RecentlyUsed, Error> {
+    let path = path.as_ref();
+    if options.missing_as_empty {
+        match fs::metadata(path) {
+            Ok(metadata) if metadata.len() == 0 => return Ok(RecentlyUsed::default()),
+            Ok(_) => (),
+            Err(why) if why.kind() == io::ErrorKind::NotFound => return Ok(RecentlyUsed::default()),
+            Err(why) => return Err(Error::Read(why)),
+        }
+    }
+    parse_reader_with(open(path)?, options)
+}
+
+/// Parses as much of a recently-used.xbel document as possible, never failing nor panicking.
+///
+/// Everything which could be read is returned, whatever state the document is in, along
+/// with a warning for each part which had to be skipped. After the first error in the
+/// document's markup, the remaining bookmarks are recovered as in [`repair_path`]. Intended
+/// as an entry point for fuzzing and for triaging crashes caused by damaged files.
+pub fn parse_bytes_partial(bytes: &[u8]) -> (RecentlyUsed, Vec<ParseWarning>) {
+    repair::salvage(bytes)
+}
+
+/// Parses a recently-used.xbel document without copying its values out of `input`.
+///
+/// Intended for applications which parse large files repeatedly, and don't need to keep the
+/// result around longer than the buffer it was read into.
+#[cfg(feature = "serde")]
+pub fn parse_borrowed(input: &str) -> Result<RecentlyUsedRef<'_>, Error> {
+    quick_xml::de::from_str(input).map_err(|why| Error::Deserialization(why.into()))
+}
+
+/// Parses the recently-used.xbel file in its default location, skipping invalid bookmarks.
+///
+/// Bookmarks which cannot be parsed are left out of the returned list and reported as
+/// warnings instead, so that the remaining recent files can still be shown. If the document
+/// itself is malformed, every bookmark before the error is returned.
+///
+/// A document declaring an XBEL version other than 1.0 is read as usual, but reported with
+/// a warning as well.
+pub fn parse_file_lenient() -> Result<(RecentlyUsed, Vec<ParseWarning>), Error> {
+    parse_path_lenient(dir().ok_or(Error::DoesNotExist)?)
+}
+
+/// Parses a recently-used.xbel file at the given location, skipping invalid bookmarks.
+///
+/// See [`parse_file_lenient`] for details.
+pub fn parse_path_lenient(
+    path: impl AsRef<Path>,
+) -> Result<(RecentlyUsed, Vec<ParseWarning>), Error> {
+    custom_reader::read(open(path.as_ref())?, true, &ParseOptions::default())
 }
 
 /// Updates the list of recently used files.
 ///
 /// This function checks if the specified file already exists in the recently used list.
-/// If it exists, the function updates the file's metadata, including the times when the file was
-/// added, modified, and last visited. If the file does not exist in the list, the function adds
-/// a new entry for the file.
+/// If it exists, the function updates the times when the file was modified and last visited,
+/// keeping the time it was first added. If the file does not exist in the list, the function
+/// adds a new entry for the file.
 ///
 /// If the file already exists in the list, the function also updates the application's usage count,
 /// or adds a new application entry if it hasn't been recorded previously.
 ///
+/// The list is locked with a [`FileLock`] while it is being updated, so that concurrent updates
+/// don't lose each other's entries. If the list doesn't exist yet, it is created.
+///
+/// Files excluded by [`UpdateOptions::exclude`] are left out of the list, without an error.
+///
 /// # Arguments
 ///
-/// * `element_path` - A `PathBuf` that represents the path to the file being updated or added.
+/// * `element_path` - A `Path` that represents the path to the file being updated or added.
+///   A relative path is taken relative to the current directory.
 /// * `app_name` - A `String` representing the name of the application associated with the file.
 /// * `exec` - A `String` representing the command to execute the application.
-/// * `owner` - An optional `String` representing the owner of the metadata. If not provided,
-///   defaults to `"http://freedesktop.org"`.
+/// * `options` - How the file is recorded, such as its groups or the owner of its metadata.
 ///
 /// # Returns
 ///
@@ -186,178 +834,4504 @@ pub fn parse_file() -> Result<RecentlyUsed, Error> {
 ///
 /// This function can return errors in the following cases:
 ///
-/// - If the file's metadata cannot be accessed or read.
+/// - If the file's metadata cannot be accessed or read. Timestamps which the file system
+///   doesn't record, such as when the file was created, fall back to others or to now.
 /// - If the recently used file list cannot be parsed or serialized.
 /// - If there is an issue writing the updated list back to the file system.
+/// - If another process holds the lock for longer than [`UpdateOptions::lock_timeout`].
 pub fn update_recently_used(
-    element_path: &PathBuf,
+    element_path: &Path,
     app_name: String,
     exec: String,
-    owner: Option<String>,
+    options: &UpdateOptions,
 ) -> Result<(), Error> {
-    let owner = match owner {
-        Some(owner) => owner,
-        None => "http://freedesktop.org".to_string(),
-    };
-    let mut parsed_file = parse_file()?;
-    let href = path_to_href(element_path).ok_or(Error::Path)?;
+    if options.excludes(element_path) {
+        return Ok(());
+    }
+
+    let bookmark = local_bookmark(element_path, app_name, exec, options)?;
+    record_update([bookmark], options)
+}
+
+/// Builds the bookmark recording an application's use of a local file.
+fn local_bookmark(
+    element_path: &Path,
+    app_name: String,
+    exec: String,
+    options: &UpdateOptions,
+) -> Result<Bookmark, Error> {
+    let element_path = options.recorded_path(element_path)?;
+    let element_path = element_path.as_ref();
     let metadata = element_path.metadata().map_err(Error::Metadata)?;
-    let added = system_time_to_string(metadata.created().map_err(Error::Metadata)?);
-    let modified = system_time_to_string(metadata.modified().map_err(Error::Metadata)?);
-    let visited = system_time_to_string(metadata.accessed().map_err(Error::Metadata)?);
-
-    // Attempt to find the existing bookmark and update it if found
-    let existing_bookmark = parsed_file.bookmarks.iter_mut().find(|b| b.href == href);
-
-    if let Some(bookmark) = existing_bookmark {
-        // Bookmark exists, update the metadata
-        bookmark.added = added;
-        bookmark.modified = modified.clone();
-        bookmark.visited = visited;
-
-        // Find the application entry or insert a new one
-        if let Some(info) = bookmark.info.as_mut() {
-            if let Some(app) = info
-                .metadata
-                .applications
-                .applications
-                .iter_mut()
-                .find(|el| el.name == app_name)
-            {
-                app.count += 1;
-                app.modified = modified.clone();
-            } else {
-                // Application not found, insert a new one
-                info.metadata.applications.applications.push(Application {
-                    name: app_name,
-                    exec,
-                    modified: modified.clone(),
-                    count: 1,
-                });
-            }
+    let now = options.now();
+    let mut builder = options.apply(Bookmark::builder(element_path).app(app_name, exec));
+    if options.mime_override.is_none() && !options.is_dir {
+        if metadata.is_dir() {
+            builder = builder.mime(DIRECTORY_MIME_TYPE);
+        } else {
+            // A detector which gives a malformed type is treated as not knowing the type
+            let mime_type = options
+                .mime_detector
+                .detect(element_path)
+                .filter(|mime_type| MimeType::new(mime_type).is_ok());
+            builder = builder.mime(mime_type.as_deref().unwrap_or(builder::DEFAULT_MIME_TYPE));
         }
-    } else {
-        // Bookmark does not exist, create a new one
-        let mime = mime_from_path(&element_path).map(|mime| MimeType { mime_type: mime });
-
-        let applications = vec![Application {
-            name: app_name,
-            exec,
-            modified: modified.clone(),
-            count: 1,
-        }];
-
-        let info = Info {
-            metadata: Metadata {
-                owner,
-                mime_type: mime,
-                applications: Applications { applications },
-            },
-        };
+    }
 
-        let new_bookmark = Bookmark {
-            href,
-            added,
-            modified,
-            visited,
-            info: Some(info),
-        };
+    // Many Linux file systems don't record when files were created, so the file is recorded
+    // as added when it was modified, or else now. A file which is already listed keeps the
+    // time it was added regardless.
+    let modified = metadata.modified().ok().map(DateTime::from);
+    builder = builder
+        .added(
+            metadata
+                .created()
+                .ok()
+                .map(DateTime::from)
+                .or(modified)
+                .unwrap_or(now),
+        )
+        .modified(modified.unwrap_or(now));
+    builder = match options.visited_now {
+        true => builder.visited(now),
+        false => builder.visited(metadata.accessed().map_err(Error::Metadata)?),
+    };
+
+    builder.build()
+}
+
+/// Options controlling how a file is recorded by [`update_recently_used`].
+///
+/// New options may be added, so construct this with `..UpdateOptions::default()`.
+#[derive(Debug, Clone)]
+pub struct UpdateOptions {
+    /// The owner of the metadata the application is recorded in. `"http://freedesktop.org"`
+    /// by default.
+    pub owner: Option<String>,
+    /// The MIME type of the file, such as `text/markdown`, for callers which already know it.
+    /// By default, it is found by [`UpdateOptions::mime_detector`].
+    pub mime_override: Option<String>,
+    /// Determines the MIME types of local files which aren't directories, unless one is given
+    /// by [`UpdateOptions::mime_override`]. [`DefaultMimeDetector`] by default.
+    pub mime_detector: Arc<dyn MimeDetector>,
+    /// Groups to add the file to, such as the name of a project.
+    pub groups: Vec<String>,
+    /// Records the file as private to the applications which registered it.
+    ///
+    /// Off by default.
+    pub private: bool,
+    /// Records the file as a directory, with the MIME type `inode/directory`. Local
+    /// directories are detected regardless, so this is only needed for URLs.
+    ///
+    /// Off by default.
+    pub is_dir: bool,
+    /// Records the file as visited when it is registered, rather than when the file system
+    /// says it was last accessed. Access times are stale on file systems mounted with
+    /// `noatime` or `relatime`.
+    ///
+    /// On by default.
+    pub visited_now: bool,
+    /// The time to record the update at, such as for reproducible tests. The current time by
+    /// default.
+    pub clock: Option<DateTime<Utc>>,
+    /// Where a file which is already listed is placed in the list when it is registered
+    /// again. [`Placement::KeepPosition`] by default.
+    pub placement: Placement,
+    /// Files which are never recorded. Updates with them succeed without changing the list.
+    ///
+    /// Empty by default.
+    pub exclude: ExcludeRules,
+    /// Records local files by their canonical paths, with symlinks resolved, rather than by
+    /// the paths they were given as. Document portals want the real path, while menus shown
+    /// to users want the one they opened.
+    ///
+    /// Off by default.
+    pub resolve_symlinks: bool,
+    /// How long to wait for other processes to release their lock on the list before
+    /// failing with [`Error::LockTimeout`]. [`DEFAULT_LOCK_TIMEOUT`] by default.
+    pub lock_timeout: Duration,
+}
+
+impl Default for UpdateOptions {
+    fn default() -> Self {
+        Self {
+            owner: None,
+            mime_override: None,
+            mime_detector: Arc::new(DefaultMimeDetector),
+            groups: Vec::new(),
+            private: false,
+            is_dir: false,
+            visited_now: true,
+            clock: None,
+            placement: Placement::default(),
+            exclude: ExcludeRules::default(),
+            resolve_symlinks: false,
+            lock_timeout: DEFAULT_LOCK_TIMEOUT,
+        }
+    }
+}
 
-        parsed_file.bookmarks.push(new_bookmark);
+impl UpdateOptions {
+    fn now(&self) -> DateTime<Utc> {
+        self.clock.unwrap_or_else(Utc::now)
     }
 
-    let serialized = custom_write(parsed_file.clone())?;
-    let recently_used_file_path = dir().ok_or(Error::DoesNotExist)?;
-    let xml_declaration = r#"<?xml version="1.0" encoding="UTF-8"?>"#;
-    let full_content = format!("{}{}", xml_declaration, serialized);
+    /// The absolute path a local file is recorded as. Hrefs can only be made from absolute
+    /// paths, so relative ones are taken relative to the current directory.
+    fn recorded_path<'a>(&self, path: &'a Path) -> Result<Cow<'a, Path>, Error> {
+        let recorded = match self.resolve_symlinks {
+            true => path.canonicalize(),
+            false if path.is_relative() => absolute_path(path),
+            false => return Ok(Cow::Borrowed(path)),
+        };
+        recorded.map(Cow::Owned).map_err(Error::Metadata)
+    }
 
-    fs::write(recently_used_file_path, full_content).map_err(|_| Error::Update)?;
+    /// Whether a local file is excluded, either by the path it was given as or by the path
+    /// it would be recorded as.
+    fn excludes(&self, path: &Path) -> bool {
+        let excluded = self.exclude.is_excluded(path)
+            || self
+                .recorded_path(path)
+                .is_ok_and(|recorded| self.exclude.is_excluded(&*recorded));
+        #[cfg(feature = "tracing")]
+        if excluded {
+            tracing::debug!(path = %path.display(), "not recording an excluded file");
+        }
+        excluded
+    }
 
-    Ok(())
+    /// Applies the options which don't depend on the file to a bookmark.
+    fn apply(&self, mut builder: BookmarkBuilder) -> BookmarkBuilder {
+        builder = builder.private(self.private);
+        if let Some(owner) = &self.owner {
+            builder = builder.owner(owner.as_str());
+        }
+        if let Some(mime_type) = &self.mime_override {
+            builder = builder.mime(mime_type);
+        } else if self.is_dir {
+            builder = builder.mime(DIRECTORY_MIME_TYPE);
+        }
+        for group in &self.groups {
+            builder = builder.group(group);
+        }
+        builder
+    }
 }
 
-fn system_time_to_string(time: SystemTime) -> String {
-    let datetime: DateTime<Utc> = time.into();
-    datetime.to_rfc3339_opts(SecondsFormat::Micros, true)
+/// Where a file which is already listed is placed in the list when it is registered again.
+///
+/// Some readers take the order of the file to be the order the files were registered in,
+/// while others sort it themselves.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Placement {
+    /// The bookmark stays where it was, and only new files are appended.
+    #[default]
+    KeepPosition,
+    /// The bookmark is moved to the end, as if the file were registered for the first time.
+    Append,
+    /// The whole list is sorted by when the files were last visited, oldest first.
+    SortByVisited,
 }
 
-fn path_to_href(path: &PathBuf) -> Option<String> {
-    let path_str = path.to_str()?;
-    Url::from_file_path(path_str).ok().map(Into::into)
+/// The MIME type of directories, as shared-mime-info names it.
+const DIRECTORY_MIME_TYPE: &str = "inode/directory";
+
+/// A file opened by an application, to be recorded by [`update_many`].
+#[derive(Debug, Clone)]
+pub struct RecentItem {
+    /// The path of the file.
+    pub path: PathBuf,
+    /// The name of the application which opened the file.
+    pub app_name: String,
+    /// The command the application opens files with.
+    pub exec: String,
 }
 
-fn mime_from_path(path: &PathBuf) -> Option<String> {
-    let path = path.to_string_lossy().to_string();
-    let kind = mime_guess::from_path(path);
-    let mime = kind.first();
-    let mime = match mime {
-        Some(mime) => mime,
-        None => return None,
-    };
-    Some(format!("{}/{}", mime.type_(), mime.subtype()))
+impl RecentItem {
+    /// A file opened by an application.
+    pub fn new(
+        path: impl Into<PathBuf>,
+        app_name: impl Into<String>,
+        exec: impl Into<String>,
+    ) -> Self {
+        Self {
+            path: path.into(),
+            app_name: app_name.into(),
+            exec: exec.into(),
+        }
+    }
+
+    fn to_bookmark(&self, options: &UpdateOptions) -> Result<Bookmark, Error> {
+        local_bookmark(
+            &self.path,
+            self.app_name.clone(),
+            self.exec.clone(),
+            options,
+        )
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::{
-        fs::{self, OpenOptions},
-        io::Write,
-    };
-    use tempfile::tempdir;
+/// Updates the list of recently used files with many files at once, such as those of a
+/// project which was opened, as if [`update_recently_used`] were called for each.
+///
+/// The list is read and written once, rather than once per file. Fails without changing the
+/// list if any of the files which aren't excluded can't be recorded.
+pub fn update_many(items: &[RecentItem], options: &UpdateOptions) -> Result<(), Error> {
+    let bookmarks = items
+        .iter()
+        .filter(|item| !options.excludes(&item.path))
+        .map(|item| item.to_bookmark(options))
+        .collect::<Result<Vec<_>, _>>()?;
 
-    #[test]
-    fn test_update_recenty_used() -> Result<(), Box<dyn std::error::Error>> {
-        let temp_dir = tempdir()?;
-        let temp_file_path = temp_dir.path().join("test_file.txt");
-        let recently_used_path = dir().ok_or(Error::DoesNotExist)?;
+    record_update(bookmarks, options)
+}
 
-        fs::write(&temp_file_path, b"Test content")?;
+/// Updates the list of recently used files with a location which may not be a local file,
+/// such as an `sftp://` or `smb://` URL.
+///
+/// Nothing is looked up about the location: it is recorded as added, modified, and visited
+/// now, with a MIME type guessed from its extension unless one is given. Otherwise this
+/// behaves as [`update_recently_used`].
+pub fn update_recently_used_url(
+    url: &Url,
+    app_name: String,
+    exec: String,
+    options: &UpdateOptions,
+) -> Result<(), Error> {
+    if options.exclude.is_excluded(url.as_str()) {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(%url, "not recording an excluded file");
+        return Ok(());
+    }
 
-        if !recently_used_path.exists() {
-            create_empty_recently_used_file(&recently_used_path)?;
-        }
+    let now = options.now();
+    let bookmark = options
+        .apply(Bookmark::builder_from_url(url).app(app_name, exec))
+        .added(now)
+        .modified(now)
+        .visited(now)
+        .build()?;
 
-        update_recently_used(
-            &temp_file_path,
-            String::from("org.test"),
-            String::from("test"),
-            None,
-        )?;
+    record_update([bookmark], options)
+}
 
-        // check new file name is in recents
-        let content = fs::read_to_string(recently_used_path)?;
-        assert!(content.contains("test_file.txt"));
+/// Records bookmarks in the list in its default location as the update functions do.
+///
+/// With the `gsettings` feature, nothing is recorded if the user turned off recent files in
+/// GNOME's privacy settings, and files older than the maximum age set there are dropped.
+fn record_update(
+    bookmarks: impl IntoIterator<Item = Bookmark>,
+    options: &UpdateOptions,
+) -> Result<(), Error> {
+    let path = dir().ok_or(Error::DoesNotExist)?;
+    record_update_path(&path, bookmarks, options, update_write_options())
+}
 
-        let deserialized = parse_file()?;
+/// Records bookmarks in the list at `path` with the options [`update_write_options`] gave,
+/// or not at all if it gave none.
+fn record_update_path(
+    path: &Path,
+    bookmarks: impl IntoIterator<Item = Bookmark>,
+    options: &UpdateOptions,
+    write_options: Option<WriteOptions>,
+) -> Result<(), Error> {
+    match write_options {
+        Some(write_options) => {
+            let write_options = WriteOptions {
+                lock_timeout: options.lock_timeout,
+                ..write_options
+            };
+            record_all(path, bookmarks, options.placement, &write_options)
+        }
+        None => {
+            #[cfg(feature = "tracing")]
+            tracing::debug!("not recording files, which the privacy settings turn off");
+            Ok(())
+        }
+    }
+}
 
-        assert!(deserialized.bookmarks.len() > 0);
+/// The options updates are written with, or `None` if the user turned off recent files in
+/// GNOME's privacy settings.
+///
+/// The settings are read once, by the first update, rather than running `gsettings` for
+/// every file recorded.
+#[cfg(feature = "gsettings")]
+fn update_write_options() -> Option<WriteOptions> {
+    static PRIVACY: std::sync::OnceLock<PrivacySettings> = std::sync::OnceLock::new();
+    privacy_write_options(PRIVACY.get_or_init(PrivacySettings::read))
+}
 
-        let bookmark = deserialized
-            .bookmarks
-            .iter()
-            .find(|el| el.href.contains("test_file"));
+/// The options updates are written with under the given privacy settings.
+#[cfg(feature = "gsettings")]
+fn privacy_write_options(privacy: &PrivacySettings) -> Option<WriteOptions> {
+    privacy.remember_recent_files.then(|| WriteOptions {
+        retention: privacy.recent_files_max_age,
+        ..WriteOptions::default()
+    })
+}
 
-        assert!(bookmark.is_some());
+/// The options updates are written with.
+#[cfg(not(feature = "gsettings"))]
+fn update_write_options() -> Option<WriteOptions> {
+    Some(WriteOptions::default())
+}
 
-        Ok(())
-    }
+/// The list as an update would leave it, from [`preview_update`].
+#[derive(Debug, Clone)]
+pub struct UpdatePreview {
+    /// The updated list, without any bookmarks which would be dropped as it is written.
+    pub recently_used: RecentlyUsed,
+    /// The document which would be written.
+    pub document: String,
+}
+
+/// Previews an update of the list of recently used files in its default location.
+///
+/// See [`preview_update_path`] for details.
+pub fn preview_update(
+    element_path: &Path,
+    app_name: String,
+    exec: String,
+    options: &UpdateOptions,
+) -> Result<UpdatePreview, Error> {
+    preview_update_path(
+        dir().ok_or(Error::DoesNotExist)?,
+        element_path,
+        app_name,
+        exec,
+        options,
+    )
+}
+
+/// Does everything [`update_recently_used`] does to the list at `path` except writing it,
+/// returning the updated list and the document which would replace the file.
+///
+/// This lets callers show what would change, test their options, or save the document
+/// through their own storage. The file is neither locked nor created. If the file is
+/// excluded, or recent files are turned off, the list is returned as it is.
+pub fn preview_update_path(
+    path: impl AsRef<Path>,
+    element_path: &Path,
+    app_name: String,
+    exec: String,
+    options: &UpdateOptions,
+) -> Result<UpdatePreview, Error> {
+    let mut recently_used = parse_path_or_default(path)?;
+    let write_options = match update_write_options() {
+        Some(write_options) if !options.excludes(element_path) => {
+            let bookmark = local_bookmark(element_path, app_name, exec, options)?;
+            recently_used.dedup();
+            merge(&mut recently_used, bookmark, options.placement);
+            write_options
+        }
+        _ => WriteOptions::default(),
+    };
+
+    let recently_used = retention::apply(&recently_used, &write_options).into_owned();
+    let document = custom_write_with(&recently_used, &write_options)?;
+    Ok(UpdatePreview {
+        recently_used,
+        document,
+    })
+}
+
+/// Adds each bookmark to the list at `path`, or if its file is already listed, updates when
+/// that bookmark was modified and visited and counts another use by the bookmark's
+/// application. Bookmarks already listed are placed as `placement` says, and the list is read
+/// and written once with `write_options`.
+///
+/// Files which are listed more than once under differently spelled hrefs are merged first,
+/// as in [`RecentlyUsed::dedup`].
+///
+/// A list which doesn't exist yet is created, along with its directory, as on a fresh user
+/// account.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(path = %path.display()))
+)]
+fn record_all(
+    path: &Path,
+    bookmarks: impl IntoIterator<Item = Bookmark>,
+    placement: Placement,
+    write_options: &WriteOptions,
+) -> Result<(), Error> {
+    create_parent(path)?;
+    let _lock = FileLock::acquire(path, write_options.lock_timeout)?;
+    let mut parsed_file = parse_path_or_default(path)?;
+    parsed_file.dedup();
+    for bookmark in bookmarks {
+        merge(&mut parsed_file, bookmark, placement);
+    }
+
+    write_path(path, &parsed_file, write_options).map(|_| ())
+}
+
+/// Creates the directory a list is kept in if it doesn't exist.
+fn create_parent(path: &Path) -> Result<(), Error> {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => {
+            fs::create_dir_all(parent).map_err(Error::Write)
+        }
+        _ => Ok(()),
+    }
+}
+
+fn merge(parsed_file: &mut RecentlyUsed, bookmark: Bookmark, placement: Placement) {
+    // Attempt to find the existing bookmark and update it if found, however its href is
+    // spelled
+    let mut existing_index = parsed_file.bookmarks.iter().position(|b| *b == bookmark);
+
+    if placement == Placement::Append {
+        if let Some(index) = existing_index {
+            let existing = parsed_file.bookmarks.remove(index);
+            parsed_file.bookmarks.push(existing);
+            existing_index = Some(parsed_file.bookmarks.len() - 1);
+        }
+    }
+
+    if let Some(existing) = existing_index.map(|index| &mut parsed_file.bookmarks[index]) {
+        // Bookmark exists, update the metadata. It keeps when it was first added, as GTK does.
+        existing.modified = bookmark.modified;
+        existing.visited = bookmark.visited;
+
+        let new_metadata = bookmark.info.into_iter().flat_map(|info| info.metadata);
+        for new_metadata in new_metadata {
+            // Find the metadata block of the same owner, or add the new one as it is
+            let info = existing.info.get_or_insert_with(|| Info {
+                metadata: Vec::new(),
+                unknown: Unknown::default(),
+            });
+            let index = info
+                .metadata
+                .iter()
+                .position(|metadata| metadata.owner == new_metadata.owner);
+            let Some(index) = index else {
+                info.metadata.push(new_metadata);
+                continue;
+            };
+            let metadata = &mut info.metadata[index];
+
+            for group in new_metadata.groups {
+                metadata.add_group(group);
+            }
+            metadata.private |= new_metadata.private;
+
+            for new_app in new_metadata.applications.applications {
+                if let Some(app) = metadata
+                    .applications
+                    .applications
+                    .iter_mut()
+                    .find(|el| el.name == new_app.name)
+                {
+                    app.count = app.count.saturating_add(1);
+                    app.modified = new_app.modified;
+                } else {
+                    // Application not found, insert a new one
+                    metadata.applications.applications.push(new_app);
+                }
+            }
+        }
+    } else {
+        // Bookmark does not exist, add the new one
+        parsed_file.bookmarks.push(bookmark);
+    }
+
+    if placement == Placement::SortByVisited {
+        parsed_file
+            .bookmarks
+            .sort_by_key(|bookmark| bookmark.effective_visited());
+    }
+}
+
+/// Marks a file in the list of recently used files in its default location as visited.
+///
+/// See [`touch_visited_path`] for details.
+pub fn touch_visited(location: impl AsRef<OsStr>, app_name: &str) -> Result<(), Error> {
+    touch_visited_path(
+        dir().ok_or(Error::DoesNotExist)?,
+        location,
+        app_name,
+        &WriteOptions::default(),
+    )
+}
+
+/// Marks a file, given as a URL or an absolute path, as visited now in the list at `path`,
+/// and counts another use by the application if it registered the file before.
+///
+/// Unlike [`update_recently_used`], a file which isn't listed is not added, and fails with
+/// [`Error::NotFound`] instead. Intended for previews, which shouldn't add to the history.
+///
+/// The list is written with `options`.
+pub fn touch_visited_path(
+    path: impl AsRef<Path>,
+    location: impl AsRef<OsStr>,
+    app_name: &str,
+    options: &WriteOptions,
+) -> Result<(), Error> {
+    edit_listed(path.as_ref(), location.as_ref(), options, |bookmark| {
+        bookmark.set_visited(Utc::now());
+        if let Some(app) = bookmark.application_mut(app_name) {
+            app.count = app.count.saturating_add(1);
+        }
+        true
+    })
+}
+
+/// Counts another use of a file in the list of recently used files in its default location.
+///
+/// See [`record_access_path`] for details.
+pub fn record_access(location: impl AsRef<OsStr>, app_name: &str) -> Result<(), Error> {
+    record_access_path(
+        dir().ok_or(Error::DoesNotExist)?,
+        location,
+        app_name,
+        &WriteOptions::default(),
+    )
+}
+
+/// Counts another use of a file, given as a URL or an absolute path, by an application which
+/// registered it before, updating only when that application last used it. The times the
+/// bookmark itself was modified and visited are left alone.
+///
+/// Fails with [`Error::NotFound`] if the file isn't listed, or the application didn't
+/// register it. The list is written with `options`.
+pub fn record_access_path(
+    path: impl AsRef<Path>,
+    location: impl AsRef<OsStr>,
+    app_name: &str,
+    options: &WriteOptions,
+) -> Result<(), Error> {
+    let mut found = false;
+    edit_listed(path.as_ref(), location.as_ref(), options, |bookmark| {
+        if let Some(app) = bookmark.application_mut(app_name) {
+            app.count = app.count.saturating_add(1);
+            app.set_modified(Utc::now());
+            found = true;
+        }
+        found
+    })?;
+
+    if !found {
+        return Err(Error::NotFound);
+    }
+    Ok(())
+}
+
+/// Edits the bookmark of a file in the list at `path`, failing with [`Error::NotFound`] if
+/// it isn't listed.
+///
+/// The list is only written if `edit` returns true.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(path = %path.display()))
+)]
+fn edit_listed(
+    path: &Path,
+    location: &OsStr,
+    options: &WriteOptions,
+    edit: impl FnOnce(&mut Bookmark) -> bool,
+) -> Result<(), Error> {
+    let href = href::normalize(&location_to_href(location).ok_or(Error::Path)?);
+
+    let _lock = FileLock::acquire(path, options.lock_timeout)?;
+    let mut recently_used = parse_path(path)?;
+    let bookmark = recently_used
+        .bookmarks
+        .iter_mut()
+        .find(|bookmark| bookmark.normalized_href() == href)
+        .ok_or(Error::NotFound)?;
+    if !edit(bookmark) {
+        return Ok(());
+    }
+
+    write_path(path, &recently_used, options).map(|_| ())
+}
+
+/// Removes a file from the list of recently used files in its default location.
+///
+/// See [`remove_recently_used_path`] for details.
+pub fn remove_recently_used(location: impl AsRef<OsStr>) -> Result<bool, Error> {
+    remove_recently_used_path(
+        dir().ok_or(Error::DoesNotExist)?,
+        location,
+        &WriteOptions::default(),
+    )
+}
+
+/// Removes the bookmark for a file, given as a URL or an absolute path, from the list at
+/// `path`, such as for a "Remove from recent files" action.
+///
+/// Bookmarks are matched by their normalized hrefs, as they are compared. The list is locked
+/// while it is updated, and only rewritten, with `options`, if a bookmark was removed.
+/// Returns whether one was.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(path = %path.as_ref().display()))
+)]
+pub fn remove_recently_used_path(
+    path: impl AsRef<Path>,
+    location: impl AsRef<OsStr>,
+    options: &WriteOptions,
+) -> Result<bool, Error> {
+    let path = path.as_ref();
+    let href = location_to_href(location.as_ref()).ok_or(Error::Path)?;
+    let href = href::normalize(&href);
+
+    let _lock = FileLock::acquire(path, options.lock_timeout)?;
+    let mut recently_used = parse_path(path)?;
+    let len = recently_used.bookmarks.len();
+    recently_used
+        .bookmarks
+        .retain(|bookmark| bookmark.normalized_href() != href);
+
+    if recently_used.bookmarks.len() == len {
+        return Ok(false);
+    }
+
+    write_path(path, &recently_used, options)?;
+    Ok(true)
+}
+
+/// Clears the list of recently used files in its default location.
+///
+/// See [`purge_all_path`] for details.
+pub fn purge_all() -> Result<usize, Error> {
+    purge_all_path(
+        dir().ok_or(Error::DoesNotExist)?,
+        |_| false,
+        &WriteOptions::default(),
+    )
+}
+
+/// Removes every bookmark from the list at `path` except those `keep` returns true for, such
+/// as for a "Clear recent history" action. Returns the number of bookmarks removed.
+///
+/// The rest of the document, such as its declaration and namespaces, is written back as it
+/// was. The list is locked while it is cleared and written with `options`, and a missing or
+/// empty file is left alone.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(path = %path.as_ref().display()))
+)]
+pub fn purge_all_path(
+    path: impl AsRef<Path>,
+    mut keep: impl FnMut(&Bookmark) -> bool,
+    options: &WriteOptions,
+) -> Result<usize, Error> {
+    let path = path.as_ref();
+    let _lock = FileLock::acquire(path, options.lock_timeout)?;
+    let mut recently_used = parse_path_or_default(path)?;
+    let len = recently_used.bookmarks.len();
+    recently_used.bookmarks.retain(|bookmark| keep(bookmark));
+
+    let removed = len - recently_used.bookmarks.len();
+    if removed > 0 {
+        write_path(path, &recently_used, options)?;
+    }
+    Ok(removed)
+}
+
+/// Removes an application from the list of recently used files in its default location.
+///
+/// See [`remove_by_application_path`] for details.
+pub fn remove_by_application(app_name: &str) -> Result<usize, Error> {
+    remove_by_application_path(
+        dir().ok_or(Error::DoesNotExist)?,
+        app_name,
+        &WriteOptions::default(),
+    )
+}
+
+/// Removes an application from every bookmark in the list at `path`, such as when it is
+/// uninstalled, dropping the bookmarks which no other application registered. Returns the
+/// number of bookmarks dropped.
+///
+/// The list is locked while it is updated, and only rewritten, with `options`, if it changed.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(path = %path.as_ref().display()))
+)]
+pub fn remove_by_application_path(
+    path: impl AsRef<Path>,
+    app_name: &str,
+    options: &WriteOptions,
+) -> Result<usize, Error> {
+    let path = path.as_ref();
+    let _lock = FileLock::acquire(path, options.lock_timeout)?;
+    let mut recently_used = parse_path(path)?;
+    let len = recently_used.bookmarks.len();
+    let mut changed = false;
+
+    recently_used.bookmarks.retain_mut(|bookmark| {
+        let Some(info) = bookmark.info.as_mut() else {
+            return true;
+        };
+
+        let mut removed = false;
+        let mut remaining = 0;
+        for metadata in &mut info.metadata {
+            let applications = &mut metadata.applications.applications;
+            let before = applications.len();
+            applications.retain(|app| app.name != app_name);
+            removed |= applications.len() != before;
+            remaining += applications.len();
+        }
+
+        changed |= removed;
+        !removed || remaining > 0
+    });
+
+    if changed {
+        write_path(path, &recently_used, options)?;
+    }
+    Ok(len - recently_used.bookmarks.len())
+}
+
+/// Removes bookmarks of files which no longer exist from the list in its default location.
+///
+/// See [`prune_missing_path`] for details.
+pub fn prune_missing() -> Result<Vec<String>, Error> {
+    prune_missing_path(dir().ok_or(Error::DoesNotExist)?, &WriteOptions::default())
+}
+
+/// Removes the bookmarks of local files which no longer exist from the list at `path`,
+/// returning the hrefs of those removed.
+///
+/// Files on other hosts or behind other schemes, such as `sftp://`, are kept, as are files
+/// whose existence can't be checked, such as for lack of permission. The list is locked while
+/// it is updated, and only rewritten, with `options`, if a bookmark was removed.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(path = %path.as_ref().display()))
+)]
+pub fn prune_missing_path(
+    path: impl AsRef<Path>,
+    options: &WriteOptions,
+) -> Result<Vec<String>, Error> {
+    let path = path.as_ref();
+    let _lock = FileLock::acquire(path, options.lock_timeout)?;
+    let mut recently_used = parse_path(path)?;
+    let mut removed = Vec::new();
+
+    recently_used.bookmarks.retain(|bookmark| {
+        let missing = bookmark.is_local()
+            && bookmark
+                .to_path()
+                .is_some_and(|path| matches!(path.try_exists(), Ok(false)));
+        if missing {
+            removed.push(bookmark.href.clone());
+        }
+        !missing
+    });
+
+    if !removed.is_empty() {
+        write_path(path, &recently_used, options)?;
+    }
+    Ok(removed)
+}
+
+/// Updates the list of recently used files in its default location after a file moved.
+///
+/// See [`rename_entry_path`] for details.
+pub fn rename_entry(
+    old_location: impl AsRef<OsStr>,
+    new_location: impl AsRef<OsStr>,
+) -> Result<bool, Error> {
+    rename_entry_path(
+        dir().ok_or(Error::DoesNotExist)?,
+        old_location,
+        new_location,
+        &WriteOptions::default(),
+    )
+}
+
+/// Points the bookmark of a file which was moved or renamed at its new location, given as a
+/// URL or an absolute path, keeping its timestamps, groups, and applications. Returns
+/// whether the file was listed.
+///
+/// A title which was the file's old name is changed to its new name. A bookmark already at
+/// the new location is replaced, since the moved file replaced that file too. The list is
+/// locked while it is updated, and written with `options`.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(path = %path.as_ref().display()))
+)]
+pub fn rename_entry_path(
+    path: impl AsRef<Path>,
+    old_location: impl AsRef<OsStr>,
+    new_location: impl AsRef<OsStr>,
+    options: &WriteOptions,
+) -> Result<bool, Error> {
+    let path = path.as_ref();
+    let old_href = location_to_href(old_location.as_ref()).ok_or(Error::Path)?;
+    let new_href = location_to_href(new_location.as_ref()).ok_or(Error::Path)?;
+    let (old, new) = (href::normalize(&old_href), href::normalize(&new_href));
+
+    let _lock = FileLock::acquire(path, options.lock_timeout)?;
+    let mut recently_used = parse_path(path)?;
+    let bookmarks = &mut recently_used.bookmarks;
+    if !bookmarks
+        .iter()
+        .any(|bookmark| bookmark.normalized_href() == old)
+    {
+        return Ok(false);
+    }
+
+    bookmarks.retain(|bookmark| {
+        let href = bookmark.normalized_href();
+        href != new || href == old
+    });
+    let bookmark = bookmarks
+        .iter_mut()
+        .find(|bookmark| bookmark.normalized_href() == old)
+        .ok_or(Error::Update)?;
+
+    let old_name = bookmark.display_name();
+    bookmark.href = new_href;
+    if bookmark.title.as_deref() == Some(old_name.as_str()) {
+        // Derive the new name from the href alone.
+        bookmark.title = None;
+        bookmark.title = Some(bookmark.display_name());
+    }
+
+    write_path(path, &recently_used, options)?;
+    Ok(true)
+}
+
+/// Repairs a truncated or corrupted recently-used.xbel file in its default location.
+///
+/// See [`repair_path`] for details.
+pub fn repair_file(use_backup: bool) -> Result<usize, Error> {
+    repair_path(
+        dir().ok_or(Error::DoesNotExist)?,
+        use_backup,
+        &WriteOptions::default(),
+    )
+}
+
+/// Repairs a truncated or corrupted recently-used.xbel file, such as one left half-written by
+/// a power loss.
+///
+/// Every complete bookmark that can still be read is kept, including those following a
+/// damaged region, and the file is rewritten as a well-formed document. When `use_backup` is
+/// set, bookmarks which could not be salvaged are restored from the `.bak` file next to it,
+/// if there is one. The repaired file is written with `options`. Returns the number of
+/// bookmarks in it.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(path = %path.as_ref().display()))
+)]
+pub fn repair_path(
+    path: impl AsRef<Path>,
+    use_backup: bool,
+    options: &WriteOptions,
+) -> Result<usize, Error> {
+    let path = path.as_ref();
+    let _lock = FileLock::acquire(path, options.lock_timeout)?;
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(why) if use_backup && why.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(why) => return Err(Error::Read(why)),
+    };
+
+    let (mut recently_used, _) = repair::salvage(&bytes);
+
+    if use_backup {
+        if let Ok(backup) = fs::read(save::backup_path(path, 0)) {
+            for bookmark in repair::salvage(&backup).0.bookmarks {
+                // However the file's href is spelled in the backup
+                if !recently_used.bookmarks.contains(&bookmark) {
+                    recently_used.bookmarks.push(bookmark);
+                }
+            }
+        }
+    }
+
+    let report = write_path(path, &recently_used, options)?;
+    Ok(report.entries_written)
+}
+
+/// Restores a backup of the recently-used.xbel file in its default location.
+///
+/// See [`restore_backup_path`] for details.
+pub fn restore_backup(generation: usize) -> Result<usize, Error> {
+    restore_backup_path(
+        dir().ok_or(Error::DoesNotExist)?,
+        generation,
+        &WriteOptions::default(),
+    )
+}
+
+/// Swaps a backup kept by [`WriteOptions::backups`] back into place, such as to roll back an
+/// accidental purge. Generation 0 is the latest backup, `recently-used.xbel.bak`.
+///
+/// The backup must be readable as a document. It replaces the file atomically, and the
+/// contents it replaced become that backup in turn, so that restoring it again undoes the
+/// restore. Only the lock timeout and durability of `options` apply. Returns the number of
+/// bookmarks in the restored file.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(path = %path.as_ref().display()))
+)]
+pub fn restore_backup_path(
+    path: impl AsRef<Path>,
+    generation: usize,
+    options: &WriteOptions,
+) -> Result<usize, Error> {
+    let path = path.as_ref();
+    let backup = save::backup_path(path, generation);
+    let _lock = FileLock::acquire(path, options.lock_timeout)?;
+
+    let restored = fs::read(&backup).map_err(Error::Read)?;
+    let entries = parse_reader(&restored[..])?.bookmarks.len();
+    let replaced = match fs::read(path) {
+        Ok(bytes) => Some(bytes),
+        Err(why) if why.kind() == io::ErrorKind::NotFound => None,
+        Err(why) => return Err(Error::Read(why)),
+    };
+
+    let durability = options.durability;
+    save::write_atomic(path, durability, |file| {
+        file.write_all(&restored).map_err(Error::Write)
+    })?;
+    match replaced {
+        Some(bytes) => save::write_atomic(&backup, durability, |file| {
+            file.write_all(&bytes).map_err(Error::Write)
+        })?,
+        None => fs::remove_file(&backup).map_err(Error::Write)?,
+    }
+
+    Ok(entries)
+}
+
+/// Options controlling how a document is written.
+///
+/// By default, documents are formatted as GTK writes them.
+#[derive(Debug, Clone)]
+pub struct WriteOptions {
+    /// Begins the document with an XML declaration, which some readers require. The
+    /// declaration the document was parsed with is kept, or else one declaring UTF-8 is
+    /// written.
+    ///
+    /// On by default.
+    pub declaration: bool,
+    /// The number of spaces to indent each level of nested elements by, or `None` to write
+    /// the document without any whitespace between elements.
+    ///
+    /// Two by default.
+    pub indent: Option<usize>,
+    /// The line endings written between elements.
+    pub newline_style: NewlineStyle,
+    /// Ends the document with a line break.
+    ///
+    /// On by default.
+    pub trailing_newline: bool,
+    /// The number of backups of the file to keep when replacing it. The previous contents
+    /// are copied to `recently-used.xbel.bak`, and older backups are moved along to
+    /// `recently-used.xbel.bak.1`, `recently-used.xbel.bak.2`, and so on.
+    ///
+    /// Only applies when saving to a file. None are kept by default.
+    pub backups: usize,
+    /// How much of a save is flushed to disk before it returns.
+    ///
+    /// Only applies when saving to a file. [`Durability::Data`] by default.
+    pub durability: Durability,
+    /// The most bookmarks to keep in the file. When there are more, those visited least
+    /// recently are dropped as it is saved, so that it doesn't grow without bound.
+    ///
+    /// Only applies when saving to a file. Unlimited by default.
+    pub max_entries: Option<usize>,
+    /// How long bookmarks are kept after they were last visited. Older bookmarks are dropped
+    /// as the file is saved, as GNOME does with its maximum age for recent files. Bookmarks
+    /// without a valid visited timestamp are kept, since their age is unknown.
+    ///
+    /// Only applies when saving to a file. Unlimited by default.
+    pub retention: Option<Duration>,
+    /// The prefix and URI of the desktop-bookmarks namespace, or `None` to bind `bookmark`
+    /// to the document's [`xmlns_bookmark`](RecentlyUsed::xmlns_bookmark).
+    ///
+    /// `None` by default.
+    pub bookmark_namespace: Option<Namespace>,
+    /// The prefix and URI of the shared-mime-info namespace, or `None` to bind `mime` to
+    /// the document's [`xmlns_mime`](RecentlyUsed::xmlns_mime).
+    ///
+    /// `None` by default.
+    pub mime_namespace: Option<Namespace>,
+    /// Appends the hrefs of the bookmarks added and removed by each save to a journal next
+    /// to the file, such as `recently-used.xbel.journal`, along with when it was saved and
+    /// by which program. The journal is read with [`read_journal`].
+    ///
+    /// Once the journal reaches a megabyte, it is moved to `recently-used.xbel.journal.1`,
+    /// replacing the entries moved there before. The file is saved even if the journal can't
+    /// be written.
+    ///
+    /// Only applies when saving to a file. Off by default.
+    pub journal: bool,
+    /// How long to wait for other processes to release their lock on the file before
+    /// failing with [`Error::LockTimeout`].
+    ///
+    /// Only applies when updating a file. [`DEFAULT_LOCK_TIMEOUT`] by default.
+    pub lock_timeout: Duration,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        Self {
+            declaration: true,
+            indent: Some(2),
+            newline_style: NewlineStyle::default(),
+            trailing_newline: true,
+            backups: 0,
+            durability: Durability::default(),
+            max_entries: None,
+            retention: None,
+            bookmark_namespace: None,
+            mime_namespace: None,
+            journal: false,
+            lock_timeout: DEFAULT_LOCK_TIMEOUT,
+        }
+    }
+}
+
+/// A namespace declared on the root element of a written document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Namespace {
+    /// The prefix of the elements in the namespace, which must not be empty.
+    pub prefix: String,
+    /// The URI identifying the namespace.
+    pub uri: String,
+}
+
+/// How much of a save is flushed to disk, trading speed for safety against power loss.
+///
+/// Whatever is chosen, the file is replaced atomically, so a crash of the application never
+/// leaves it half-written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Durability {
+    /// Nothing is flushed, leaving it to the operating system. After a power loss, the file
+    /// may be empty or hold its previous contents.
+    None,
+    /// The new contents are flushed before they replace the file, so that after a power loss
+    /// it holds either its previous or its new contents.
+    #[default]
+    Data,
+    /// The directory is also flushed after the file is replaced, so that the new contents
+    /// are on disk once the save returns.
+    Full,
+}
+
+/// The line endings of a written document.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NewlineStyle {
+    /// `\n`, as written by GTK.
+    #[default]
+    Lf,
+    /// `\r\n`.
+    CrLf,
+}
+
+/// What a save wrote, for logging and metrics.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SaveReport {
+    /// The number of bookmarks in the saved file.
+    pub entries_written: usize,
+    /// The size of the saved file in bytes.
+    pub bytes: u64,
+    /// The number of bookmarks dropped by [`WriteOptions::max_entries`] and
+    /// [`WriteOptions::retention`].
+    pub pruned: usize,
+}
+
+impl RecentlyUsed {
+    /// Writes the list back to the recently-used.xbel file in its default location.
+    ///
+    /// See [`RecentlyUsed::save_to`] for details.
+    pub fn save(&self) -> Result<SaveReport, Error> {
+        self.save_to(dir().ok_or(Error::DoesNotExist)?)
+    }
+
+    /// Writes the list to the given location, replacing the file there.
+    ///
+    /// The file is replaced atomically, so that other applications reading it concurrently
+    /// never see it half-written. To avoid losing entries added by other processes between
+    /// parsing the file and saving it, hold a [`FileLock`] on it throughout.
+    pub fn save_to(&self, path: impl AsRef<Path>) -> Result<SaveReport, Error> {
+        self.save_to_with(path, &WriteOptions::default())
+    }
+
+    /// Writes the list to the given location with the given options, replacing the file
+    /// there.
+    pub fn save_to_with(
+        &self,
+        path: impl AsRef<Path>,
+        options: &WriteOptions,
+    ) -> Result<SaveReport, Error> {
+        write_path(path.as_ref(), self, options)
+    }
+}
+
+/// Serializes a document and atomically replaces the file at the given location with it.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(path = %path.display()))
+)]
+fn write_path(
+    path: &Path,
+    recently_used: &RecentlyUsed,
+    options: &WriteOptions,
+) -> Result<SaveReport, Error> {
+    let kept = retention::apply(recently_used, options);
+    let mut report = SaveReport {
+        entries_written: kept.bookmarks.len(),
+        bytes: 0,
+        pruned: recently_used.bookmarks.len() - kept.bookmarks.len(),
+    };
+
+    // The journal is only a record of the saves, so it never fails one.
+    let before = match options.journal {
+        true => match fs::read(path) {
+            Ok(bytes) => Some(repair::salvage(&bytes).0.bookmarks),
+            Err(why) if why.kind() == io::ErrorKind::NotFound => Some(Vec::new()),
+            Err(_) => None,
+        },
+        false => None,
+    };
+
+    save::rotate_backups(path, options.backups).map_err(Error::Write)?;
+    save::write_atomic(path, options.durability, |file| {
+        let mut file = save::Counted::new(file);
+        custom_write_to_with(&kept, &mut file, options)?;
+        report.bytes = file.bytes;
+        Ok(())
+    })?;
+
+    if let Some(before) = before {
+        let result = journal::record(path, &before, &kept.bookmarks);
+        #[cfg(feature = "tracing")]
+        if let Err(why) = result {
+            tracing::warn!(error = %why, "could not append to the journal");
+        }
+        #[cfg(not(feature = "tracing"))]
+        let _ = result;
+    }
+
+    Ok(report)
+}
+
+/// Opens a file to be parsed, memory-mapping it if the `mmap` feature is enabled.
+fn open(path: &Path) -> Result<impl BufRead, Error> {
+    let file = File::open(path).map_err(Error::Read)?;
+
+    #[cfg(all(feature = "mmap", unix))]
+    let reader = mmap::MappedFile::map(&file).map_err(Error::Read)?;
+    #[cfg(not(all(feature = "mmap", unix)))]
+    let reader = io::BufReader::new(file);
+
+    Ok(reader)
+}
+
+fn system_time_to_string(time: SystemTime) -> String {
+    time::format(time.into())
+}
+
+/// The href of a location given as either a URL or an absolute path.
+fn location_to_href(location: &OsStr) -> Option<String> {
+    // Single letter schemes are more likely to be Windows drive letters.
+    match location.to_str().map(Url::parse) {
+        Some(Ok(url)) if url.scheme().len() > 1 => Some(url.into()),
+        _ => path_to_href(Path::new(location)),
+    }
+}
+
+/// Bytes which aren't UTF-8 are percent-encoded, as GLib does.
+fn path_to_href(path: &Path) -> Option<String> {
+    Url::from_file_path(path).ok().map(Into::into)
+}
+
+/// Joins a relative path to the current directory, resolving `.` and `..` without following
+/// symlinks, as GLib does for paths given on the command line.
+fn absolute_path(path: &Path) -> io::Result<PathBuf> {
+    let mut absolute = std::env::current_dir()?;
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => {
+                absolute.pop();
+            }
+            component => absolute.push(component),
+        }
+    }
+    Ok(absolute)
+}
+
+fn mime_from_path(path: &Path) -> Option<String> {
+    let path = path.to_string_lossy().to_string();
+    let kind = mime_guess::from_path(path);
+    let mime = kind.first()?;
+    Some(format!("{}/{}", mime.type_(), mime.subtype()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use std::{fs, io::Write, sync::Arc, time::Duration};
+    use tempfile::tempdir;
+
+    fn record(path: &Path, bookmark: Bookmark) -> Result<(), Error> {
+        record_all(
+            path,
+            [bookmark],
+            Placement::default(),
+            &WriteOptions::default(),
+        )
+    }
+
+    const SAMPLE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<xbel version="1.0"
+      xmlns:bookmark="http://www.freedesktop.org/standards/desktop-bookmarks"
+      xmlns:mime="http://www.freedesktop.org/standards/shared-mime-info"
+>
+  <bookmark href="file:///home/user/notes.md" added="2024-09-01T10:00:00.000000Z" modified="2024-09-02T11:00:00.000000Z" visited="2024-09-03T12:00:00.000000Z">
+    <info>
+      <metadata owner="http://freedesktop.org">
+        <mime:mime-type type="text/markdown"/>
+        <bookmark:applications>
+          <bookmark:application name="org.gnome.TextEditor" exec="&apos;gnome-text-editor %u&apos;" modified="2024-09-02T11:00:00.000000Z" count="3"/>
+        </bookmark:applications>
+      </metadata>
+    </info>
+  </bookmark>
+</xbel>
+"#;
+
+    #[test]
+    fn test_parse_reader() -> Result<(), Box<dyn std::error::Error>> {
+        let recently_used = parse_reader(SAMPLE.as_bytes())?;
+
+        assert_eq!(recently_used.bookmarks.len(), 1);
+        let bookmark = &recently_used.bookmarks[0];
+        assert_eq!(bookmark.href, "file:///home/user/notes.md");
+
+        let app = &bookmark
+            .info
+            .as_ref()
+            .unwrap()
+            .freedesktop()
+            .unwrap()
+            .applications
+            .applications[0];
+        assert_eq!(app.exec, "'gnome-text-editor %u'");
+        assert_eq!(app.count, 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_missing_optional_timestamps() -> Result<(), Box<dyn std::error::Error>> {
+        let xbel = r#"<xbel version="1.0"><bookmark href="file:///tmp/a" added="2024-09-01T10:00:00Z"/></xbel>"#;
+        let recently_used = parse_reader(xbel.as_bytes())?;
+
+        let bookmark = &recently_used.bookmarks[0];
+        assert_eq!(bookmark.modified, None);
+        assert_eq!(bookmark.visited, None);
+
+        let added = bookmark.added_time();
+        assert!(added.is_some());
+        assert_eq!(bookmark.effective_modified(), added);
+        assert_eq!(bookmark.effective_visited(), added);
+
+        let mut modified = bookmark.clone();
+        modified.modified = Some("2024-09-02T10:00:00Z".to_owned());
+        assert_eq!(modified.effective_visited(), modified.modified_time());
+
+        let written = custom_writer::custom_write(&recently_used)?;
+        assert!(!written.contains("modified="));
+        assert!(!written.contains("visited="));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_multiple_metadata() -> Result<(), Box<dyn std::error::Error>> {
+        let input = SAMPLE.replace(
+            "<info>",
+            "<info>\n      <metadata owner=\"http://example.org\"/>",
+        );
+
+        let recently_used = parse_reader(input.as_bytes())?;
+        let info = recently_used.bookmarks[0].info.as_ref().unwrap();
+        assert_eq!(info.metadata.len(), 2);
+        assert_eq!(info.metadata[0].owner, "http://example.org");
+        assert_eq!(
+            info.metadata[0].owner,
+            MetadataOwner::Other("http://example.org".to_owned())
+        );
+        assert_eq!(info.freedesktop().unwrap().owner, FREEDESKTOP_OWNER);
+        assert_eq!(
+            MetadataOwner::from(FREEDESKTOP_OWNER),
+            MetadataOwner::Freedesktop
+        );
+        let spelled_out = MetadataOwner::Other(FREEDESKTOP_OWNER.to_owned());
+        assert_eq!(spelled_out, MetadataOwner::Freedesktop);
+        let owners: std::collections::HashSet<_> = [spelled_out, MetadataOwner::Freedesktop].into();
+        assert_eq!(owners.len(), 1);
+
+        let options = WriteOptions {
+            indent: None,
+            ..WriteOptions::default()
+        };
+        let written = custom_write_with(&recently_used, &options)?;
+        assert!(written.contains(r#"<metadata owner="http://example.org"></metadata>"#));
+
+        let parsed = parse_reader(written.as_bytes())?;
+        let info = parsed.bookmarks[0].info.as_ref().unwrap();
+        assert_eq!(info.metadata.len(), 2);
+        assert_eq!(
+            info.freedesktop().unwrap().applications.applications.len(),
+            1
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+        let input = SAMPLE
+            .replace(
+                "\n>",
+                " xmlns:ex=\"urn:example\">\n  <title>Recent</title>\n  <ex:tags n=\"2\">a &amp; b<ex:tag/></ex:tags>",
+            )
+            .replace("<info>", "<title>Notes</title>\n    <info>")
+            .replace(
+                "</bookmark:applications>",
+                "</bookmark:applications>\n        <bookmark:groups><bookmark:group>Work</bookmark:group></bookmark:groups>\n        <bookmark:private/>",
+            )
+            .replace("count=\"3\"", "count=\"3\" ex:pinned=\"yes\"");
+
+        let recently_used = parse_reader(input.as_bytes())?;
+        let bookmark = &recently_used.bookmarks[0];
+        let metadata = bookmark.info.as_ref().unwrap().freedesktop().unwrap();
+        assert_eq!(
+            recently_used.unknown.attributes,
+            [("xmlns:ex".to_owned(), "urn:example".to_owned())]
+        );
+        assert_eq!(recently_used.title.as_deref(), Some("Recent"));
+        assert_eq!(recently_used.unknown.elements.len(), 1);
+        assert_eq!(recently_used.unknown.elements[0].text, "a & b");
+        assert_eq!(bookmark.title.as_deref(), Some("Notes"));
+        assert!(bookmark.unknown.elements.is_empty());
+        assert_eq!(metadata.groups, ["Work"]);
+        assert!(metadata.private);
+        assert!(metadata.unknown.elements.is_empty());
+        assert_eq!(
+            metadata.applications.applications[0].unknown.attributes,
+            [("ex:pinned".to_owned(), "yes".to_owned())]
+        );
+
+        let written = custom_write(&recently_used)?;
+        let parsed = parse_reader(written.as_bytes())?;
+        assert_eq!(parsed.unknown, recently_used.unknown);
+        assert_eq!(parsed.bookmarks[0].unknown, bookmark.unknown);
+        let reparsed = parsed.bookmarks[0]
+            .info
+            .as_ref()
+            .unwrap()
+            .freedesktop()
+            .unwrap();
+        assert_eq!(reparsed.unknown, metadata.unknown);
+        assert_eq!(reparsed.groups, metadata.groups);
+        assert_eq!(reparsed.private, metadata.private);
+        assert_eq!(
+            reparsed.applications.applications[0].unknown,
+            metadata.applications.applications[0].unknown
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_repair_path() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join("recently-used.xbel");
+
+        let bookmark = |name: &str| {
+            let start = SAMPLE.find("  <bookmark ").unwrap();
+            let end = SAMPLE.find("</xbel>").unwrap();
+            SAMPLE[start..end].replace("notes.md", name)
+        };
+        let header = &SAMPLE[..SAMPLE.find("  <bookmark ").unwrap()];
+
+        let corrupted = format!(
+            "{}{}{}{}{}",
+            header,
+            bookmark("a.md"),
+            bookmark("b.md").replace("<info>", "<info><<"),
+            bookmark("c.md"),
+            &bookmark("d.md")[..100],
+        );
+        fs::write(&path, &corrupted)?;
+
+        assert_eq!(repair_path(&path, false, &WriteOptions::default())?, 2);
+        let repaired = parse_path(&path)?;
+        let hrefs: Vec<_> = repaired.bookmarks.iter().map(|b| b.href.as_str()).collect();
+        assert_eq!(hrefs, ["file:///home/user/a.md", "file:///home/user/c.md"]);
+
+        // a.md is spelled differently in the backup, but is the same file
+        let backup = format!(
+            "{}{}{}</xbel>",
+            header,
+            bookmark("%61.md"),
+            bookmark("b.md")
+        );
+        fs::write(save::backup_path(&path, 0), backup)?;
+        fs::write(&path, &corrupted)?;
+
+        assert_eq!(repair_path(&path, true, &WriteOptions::default())?, 3);
+        assert_eq!(parse_path(&path)?.bookmarks.len(), 3);
+
+        fs::remove_file(&path)?;
+        assert_eq!(repair_path(&path, true, &WriteOptions::default())?, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate() -> Result<(), Box<dyn std::error::Error>> {
+        let mut recently_used = parse_reader(SAMPLE.as_bytes())?;
+        assert_eq!(recently_used.validate(), []);
+
+        let mut duplicate = recently_used.bookmarks[0].clone();
+        duplicate.added = "yesterday".to_owned();
+        duplicate.visited = Some("2999-01-01T00:00:00Z".to_owned());
+        duplicate.info.as_mut().unwrap().metadata[0]
+            .applications
+            .applications[0]
+            .count = 0;
+        recently_used.bookmarks.push(duplicate);
+        recently_used.bookmarks[0].href = "not a uri".to_owned();
+        recently_used.bookmarks[1].href = "not a uri".to_owned();
+
+        recently_used.bookmarks[1].info.as_mut().unwrap().metadata[0].mime_type = Some(MimeType {
+            mime_type: "markdown".to_owned(),
+        });
+
+        let issues = recently_used.validate();
+        assert_eq!(issues.len(), 7);
+        assert!(matches!(issues[0], Issue::InvalidHref { index: 0, .. }));
+        assert!(matches!(
+            issues[2],
+            Issue::DuplicateHref {
+                index: 1,
+                first: 0,
+                ..
+            }
+        ));
+        assert!(matches!(
+            issues[3],
+            Issue::InvalidTimestamp {
+                index: 1,
+                attribute: "added",
+                ..
+            }
+        ));
+        assert!(matches!(
+            issues[4],
+            Issue::FutureTimestamp {
+                index: 1,
+                attribute: "visited",
+                ..
+            }
+        ));
+        assert!(matches!(issues[5], Issue::ZeroCount { index: 1, .. }));
+
+        let mut respelled = parse_reader(SAMPLE.as_bytes())?;
+        respelled.bookmarks[0].href = "file:///home/user/My Notes.md".to_owned();
+        let mut duplicate = respelled.bookmarks[0].clone();
+        duplicate.href = "file:///home/user/My%20Notes.md".to_owned();
+        respelled.bookmarks.push(duplicate);
+        assert!(matches!(
+            respelled.validate()[..],
+            [Issue::DuplicateHref {
+                index: 1,
+                first: 0,
+                ..
+            }]
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cdata_and_text_nodes() -> Result<(), Box<dyn std::error::Error>> {
+        let input = SAMPLE
+            .replace(
+                "<info>",
+                "<![CDATA[ stray <text> ]]>\n    stray text\n    <title><![CDATA[Tom & Jerry]]></title>\n    <info>",
+            )
+            .replace("</metadata>", "</metadata><![CDATA[]]>a<![CDATA[b]]>c")
+            .replace("<metadata ", "text<metadata ")
+            .replace("\n  <bookmark ", "\n  <![CDATA[x]]>\n  <bookmark ");
+
+        let recently_used = parse_reader(input.as_bytes())?;
+        assert_eq!(recently_used.bookmarks.len(), 1);
+        assert!(recently_used.bookmarks[0].info.is_some());
+        assert_eq!(
+            recently_used.bookmarks[0].title.as_deref(),
+            Some("Tom & Jerry")
+        );
+
+        #[cfg(feature = "serde")]
+        {
+            let borrowed = parse_borrowed(&input)?;
+            assert_eq!(borrowed.bookmarks.len(), 1);
+            assert_eq!(borrowed.bookmarks[0].title.as_deref(), Some("Tom & Jerry"));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cached_recents() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join("recently-used.xbel");
+        let mut recents = CachedRecents::with_path(&path);
+
+        assert!(recents.get()?.bookmarks.is_empty());
+
+        fs::write(&path, SAMPLE)?;
+        let first = recents.get()?;
+        assert_eq!(first.bookmarks.len(), 1);
+        assert!(Arc::ptr_eq(&first, &recents.get()?));
+
+        let mut recently_used = parse_reader(SAMPLE.as_bytes())?;
+        recently_used
+            .bookmarks
+            .push(recently_used.bookmarks[0].clone());
+        fs::write(&path, custom_write(&recently_used)?)?;
+        assert_eq!(recents.get()?.bookmarks.len(), 2);
+
+        recents.invalidate();
+        assert!(!Arc::ptr_eq(&first, &recents.get()?));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_tail() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join("recently-used.xbel");
+
+        let mut recently_used = parse_reader(SAMPLE.as_bytes())?;
+        for n in 0..20 {
+            let mut bookmark = recently_used.bookmarks[0].clone();
+            bookmark.href = format!("file:///home/user/{}.md", n);
+            recently_used.bookmarks.push(bookmark);
+        }
+        fs::write(&path, custom_write(&recently_used)?)?;
+
+        let tail = parse_path_tail(&path, 1000)?;
+        assert!(!tail.bookmarks.is_empty() && tail.bookmarks.len() < 21);
+        assert_eq!(
+            tail.bookmarks.last().unwrap().href,
+            "file:///home/user/19.md"
+        );
+
+        assert_eq!(parse_path_tail(&path, 10)?.bookmarks.len(), 0);
+        assert_eq!(parse_path_tail(&path, u64::MAX)?.bookmarks.len(), 21);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_deny_unknown_attributes() -> Result<(), Box<dyn std::error::Error>> {
+        let deny = ParseOptions {
+            deny_unknown_attributes: true,
+            ..ParseOptions::default()
+        };
+        let input = SAMPLE.replace("\n>", " xmlns:ex=\"urn:example\">");
+        assert_eq!(
+            parse_reader_with(input.as_bytes(), &deny)?.bookmarks.len(),
+            1
+        );
+
+        let input = input.replace("count=\"3\"", "count=\"3\" ex:pinned=\"yes\"");
+        assert_eq!(parse_reader(input.as_bytes())?.bookmarks.len(), 1);
+        match parse_reader_with(input.as_bytes(), &deny) {
+            Err(Error::Deserialization(DeError::Custom(message))) => {
+                assert_eq!(message, "unknown field `@ex:pinned`")
+            }
+            other => panic!("expected an unknown field error, got {:?}", other),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_version() -> Result<(), Box<dyn std::error::Error>> {
+        assert_eq!(parse_reader(SAMPLE.as_bytes())?.version, XBEL_VERSION);
+
+        let input = SAMPLE.replace("<xbel version=\"1.0\"", "<xbel version=\"1.1\"");
+        let (recently_used, warnings) =
+            custom_reader::read(input.as_bytes(), true, &ParseOptions::default())?;
+        assert_eq!(recently_used.version, "1.1");
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].offset, SAMPLE.find("<xbel").unwrap() as u64);
+
+        let written = custom_write(&recently_used)?;
+        assert!(written.contains("<xbel version=\"1.1\""));
+
+        let input = SAMPLE.replace("<xbel version=\"1.0\"", "<xbel");
+        assert_eq!(parse_reader(input.as_bytes())?.version, XBEL_VERSION);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_root_title_and_info() -> Result<(), Box<dyn std::error::Error>> {
+        let input = SAMPLE.replacen(
+            "\n  <bookmark ",
+            "\n  <title>Recent &amp; files</title>\n  <desc>Shared</desc>\n  <info><metadata owner=\"http://example.org\"/></info>\n  <bookmark ",
+            1,
+        );
+
+        let recently_used = parse_reader(input.as_bytes())?;
+        assert_eq!(recently_used.title.as_deref(), Some("Recent & files"));
+        assert_eq!(recently_used.desc.as_deref(), Some("Shared"));
+        assert_eq!(
+            recently_used.info.as_ref().unwrap().metadata[0].owner,
+            "http://example.org"
+        );
+        assert!(recently_used.unknown.elements.is_empty());
+
+        #[cfg(feature = "serde")]
+        {
+            let borrowed = parse_borrowed(&input)?.into_owned();
+            assert_eq!(borrowed.title, recently_used.title);
+        }
+
+        let written = custom_write(&recently_used)?;
+        let parsed = parse_reader(written.as_bytes())?;
+        assert_eq!(parsed.title, recently_used.title);
+        assert_eq!(parsed.desc, recently_used.desc);
+        assert_eq!(parsed.info.unwrap().metadata.len(), 1);
+        assert_eq!(parsed.bookmarks.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_bytes_partial() {
+        let (recently_used, warnings) = parse_bytes_partial(SAMPLE.as_bytes());
+        assert_eq!(recently_used.bookmarks.len(), 1);
+        assert!(warnings.is_empty());
+
+        for len in 0..SAMPLE.trim_end().len() {
+            let (recently_used, warnings) = parse_bytes_partial(&SAMPLE.as_bytes()[..len]);
+            assert!(recently_used.bookmarks.len() <= 1);
+            assert!(!warnings.is_empty());
+        }
+
+        let garbage: Vec<u8> = (0..=255).chain((0..=255).rev()).collect();
+        parse_bytes_partial(&garbage);
+        parse_bytes_partial(b"\xFF\xFE<\0x");
+        parse_bytes_partial(b"<?xml encoding=\"shift_jis\"?><xbel><bookmark/></xbel>");
+    }
+
+    #[test]
+    fn test_deep_nesting() {
+        let depth = 200_000;
+        let nested = format!(
+            "{}{}",
+            "<x:deep xmlns:x=\"urn:x\">".repeat(depth),
+            "</x:deep>".repeat(depth)
+        );
+        let document = SAMPLE.replace("</info>", &format!("</info>{nested}"));
+        let document = document.replace(
+            "</xbel>",
+            r#"<bookmark href="file:///home/user/todo.md" added="2024-09-01T10:00:00Z"/></xbel>"#,
+        );
+
+        let (recently_used, warnings) = parse_bytes_partial(document.as_bytes());
+        assert_eq!(recently_used.bookmarks.len(), 1);
+        assert_eq!(recently_used.bookmarks[0].href, "file:///home/user/todo.md");
+        assert!(!warnings.is_empty());
+        assert!(parse_reader(document.as_bytes()).is_err());
+
+        let shallow = "<x:deep xmlns:x=\"urn:x\">".repeat(100) + &"</x:deep>".repeat(100);
+        let document = SAMPLE.replace("</info>", &format!("</info>{shallow}"));
+        assert_eq!(
+            parse_reader(document.as_bytes()).unwrap().bookmarks.len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_file_lock() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join("recently-used.xbel");
+
+        let lock = FileLock::acquire(&path, Duration::ZERO)?;
+        #[cfg(unix)]
+        assert!(matches!(
+            FileLock::acquire(&path, Duration::from_millis(20)),
+            Err(Error::LockTimeout)
+        ));
+        #[cfg(unix)]
+        {
+            let options = WriteOptions {
+                lock_timeout: Duration::from_millis(20),
+                ..WriteOptions::default()
+            };
+            assert!(matches!(
+                remove_recently_used_path(&path, "/tmp/file.txt", &options),
+                Err(Error::LockTimeout)
+            ));
+            let store = RecentlyUsedStore::with_path(&path).write_options(options);
+            assert!(matches!(
+                store.transaction(|_| Ok::<_, Error>(())),
+                Err(Error::LockTimeout)
+            ));
+        }
+
+        drop(lock);
+        FileLock::acquire(&path, Duration::ZERO)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_to() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join("recently-used.xbel");
+        fs::write(&path, SAMPLE)?;
+
+        let mut recently_used = parse_path(&path)?;
+        recently_used.bookmarks.clear();
+        recently_used.save_to(&path)?;
+
+        let saved = parse_path(&path)?;
+        assert!(saved.bookmarks.is_empty());
+        assert_eq!(fs::read_dir(temp_dir.path())?.count(), 1);
+
+        for durability in [Durability::None, Durability::Data, Durability::Full] {
+            recently_used.title = Some(format!("{durability:?}"));
+            let options = WriteOptions {
+                durability,
+                ..WriteOptions::default()
+            };
+            recently_used.save_to_with(&path, &options)?;
+            assert_eq!(parse_path(&path)?.title, recently_used.title);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_concurrently() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join("recently-used.xbel");
+        let recently_used = parse_reader(SAMPLE.as_bytes())?;
+
+        std::thread::scope(|scope| {
+            let threads: Vec<_> = (0..8)
+                .map(|_| {
+                    scope.spawn(|| {
+                        (0..25).try_for_each(|_| recently_used.save_to(&path).map(|_| ()))
+                    })
+                })
+                .collect();
+            threads
+                .into_iter()
+                .try_for_each(|thread| thread.join().unwrap())
+        })?;
+
+        assert_eq!(parse_path(&path)?.bookmarks.len(), 1);
+        assert_eq!(fs::read_dir(temp_dir.path())?.count(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_custom_write_to() -> Result<(), Box<dyn std::error::Error>> {
+        struct Closed;
+
+        impl Write for Closed {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                Err(io::ErrorKind::BrokenPipe.into())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let recently_used = parse_reader(SAMPLE.as_bytes())?;
+        let mut written = Vec::new();
+        custom_write_to(&recently_used, &mut written)?;
+        assert_eq!(written, custom_write(&recently_used)?.into_bytes());
+
+        assert!(matches!(
+            custom_write_to(&recently_used, Closed),
+            Err(Error::Write(why)) if why.kind() == io::ErrorKind::BrokenPipe
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_declaration() -> Result<(), Box<dyn std::error::Error>> {
+        let written = custom_write(&RecentlyUsed::default())?;
+        assert!(written.starts_with(concat!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+            "\n",
+            r#"<xbel version="1.0" xmlns:bookmark="http://www.freedesktop.org/standards/desktop-bookmarks" xmlns:mime="http://www.freedesktop.org/standards/shared-mime-info">"#
+        )));
+
+        let options = WriteOptions {
+            declaration: false,
+            ..WriteOptions::default()
+        };
+        let written = custom_write_with(&parse_reader(SAMPLE.as_bytes())?, &options)?;
+        assert!(written.starts_with("<xbel "));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_formatting() -> Result<(), Box<dyn std::error::Error>> {
+        let recently_used = RecentlyUsed {
+            title: Some("Recent".to_owned()),
+            unknown: Unknown {
+                attributes: Vec::new(),
+                elements: vec![RawElement {
+                    name: "separator".to_owned(),
+                    attributes: Vec::new(),
+                    children: Vec::new(),
+                    text: String::new(),
+                }],
+            },
+            ..RecentlyUsed::default()
+        };
+
+        let options = WriteOptions {
+            declaration: false,
+            ..WriteOptions::default()
+        };
+        let written = custom_write_with(&recently_used, &options)?;
+        let root = written.lines().next().unwrap();
+        assert_eq!(
+            written,
+            format!("{root}\n  <title>Recent</title>\n  <separator/>\n</xbel>\n")
+        );
+
+        let options = WriteOptions {
+            newline_style: NewlineStyle::CrLf,
+            ..options
+        };
+        let written = custom_write_with(&recently_used, &options)?;
+        assert_eq!(
+            written,
+            format!("{root}\r\n  <title>Recent</title>\r\n  <separator/>\r\n</xbel>\r\n")
+        );
+
+        let options = WriteOptions {
+            indent: None,
+            trailing_newline: false,
+            ..options
+        };
+        let written = custom_write_with(&recently_used, &options)?;
+        assert_eq!(
+            written,
+            format!("{root}<title>Recent</title><separator/></xbel>")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_gtk_layout() -> Result<(), Box<dyn std::error::Error>> {
+        let written = custom_write(&parse_reader(SAMPLE.as_bytes())?)?;
+
+        // GTK splits the root element's attributes over several lines, which is the only
+        // difference in layout.
+        let body = |document: &str| document[document.find("\n  <bookmark").unwrap()..].to_owned();
+        assert_eq!(body(&written), body(SAMPLE));
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_save_permissions() -> Result<(), Box<dyn std::error::Error>> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join("recently-used.xbel");
+        let mode = |path: &Path| -> io::Result<u32> {
+            Ok(fs::metadata(path)?.permissions().mode() & 0o777)
+        };
+
+        RecentlyUsed::default().save_to(&path)?;
+        assert_eq!(mode(&path)?, 0o600);
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640))?;
+        RecentlyUsed::default().save_to(&path)?;
+        assert_eq!(mode(&path)?, 0o640);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_backups() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join("recently-used.xbel");
+        let options = WriteOptions {
+            backups: 2,
+            ..WriteOptions::default()
+        };
+
+        let mut recently_used = RecentlyUsed::default();
+        for title in ["first", "second", "third", "fourth"] {
+            recently_used.title = Some(title.to_owned());
+            recently_used.save_to_with(&path, &options)?;
+        }
+
+        let title = |path| -> Result<_, Error> { Ok(parse_path(path)?.title) };
+        assert_eq!(title(path.clone())?.as_deref(), Some("fourth"));
+        assert_eq!(
+            title(save::backup_path(&path, 0))?.as_deref(),
+            Some("third")
+        );
+        assert_eq!(
+            title(save::backup_path(&path, 1))?.as_deref(),
+            Some("second")
+        );
+        assert!(!save::backup_path(&path, 2).exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_deterministic() -> Result<(), Box<dyn std::error::Error>> {
+        let input = SAMPLE
+            .replace("<bookmark href", "<bookmark z=\"1\" a=\"2\" href")
+            .replace(
+                "</bookmark>",
+                "</bookmark>\n  <bookmark href=\"file:///a\" added=\"2024-01-01T00:00:00Z\"/>",
+            );
+
+        let recently_used = parse_reader(input.as_bytes())?;
+        let written = custom_write(&recently_used)?;
+        assert_eq!(written, custom_write(&recently_used)?);
+        assert!(written.contains(r#"visited="2024-09-03T12:00:00.000000Z" z="1" a="2">"#));
+
+        let reparsed = parse_reader(written.as_bytes())?;
+        assert_eq!(reparsed.bookmarks[1].href, "file:///a");
+        assert_eq!(custom_write(&reparsed)?, written);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_retention() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join("recently-used.xbel");
+
+        let mut recently_used = RecentlyUsed::default();
+        for (href, visited) in [
+            ("file:///a", Some("2024-03-01T00:00:00Z")),
+            ("file:///b", None),
+            ("file:///c", Some("2024-01-01T00:00:00Z")),
+            ("file:///d", Some("2024-02-01T00:00:00Z")),
+        ] {
+            recently_used.bookmarks.push(Bookmark {
+                href: href.to_owned(),
+                added: "2024-01-01T00:00:00Z".to_owned(),
+                modified: None,
+                visited: visited.map(str::to_owned),
+                title: None,
+                desc: None,
+                info: None,
+                #[cfg(feature = "spans")]
+                span: None,
+                unknown: Unknown::default(),
+            });
+        }
+
+        let options = WriteOptions {
+            max_entries: Some(2),
+            ..WriteOptions::default()
+        };
+
+        let report = recently_used.save_to_with(&path, &options)?;
+        assert_eq!(report.entries_written, 2);
+        assert_eq!(report.pruned, 2);
+        assert_eq!(report.bytes, fs::metadata(&path)?.len());
+
+        let saved = parse_path(&path)?;
+        let hrefs: Vec<_> = saved.bookmarks.iter().map(|b| b.href.as_str()).collect();
+        assert_eq!(hrefs, ["file:///a", "file:///d"]);
+        assert_eq!(recently_used.bookmarks.len(), 4);
+
+        let a_day = Duration::from_secs(24 * 60 * 60);
+        recently_used.bookmarks[0].visited = Some(system_time_to_string(SystemTime::now()));
+        let options = WriteOptions {
+            retention: Some(a_day),
+            ..WriteOptions::default()
+        };
+        recently_used.save_to_with(&path, &options)?;
+
+        let saved = parse_path(&path)?;
+        let hrefs: Vec<_> = saved.bookmarks.iter().map(|b| b.href.as_str()).collect();
+        assert_eq!(hrefs, ["file:///a", "file:///b"]);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_json_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+        let mut recently_used = parse_reader(SAMPLE.as_bytes())?;
+        recently_used.title = Some("\"Recent\"\n\u{1}\u{1F600}".to_owned());
+
+        let json = recently_used.to_json();
+        assert!(json.starts_with(r#"{"version":"1.0","title":"\"Recent\"\n\u0001😀","desc":null"#));
+
+        let parsed = RecentlyUsed::from_json(&json)?;
+        assert_eq!(custom_write(&parsed)?, custom_write(&recently_used)?);
+
+        let parsed = RecentlyUsed::from_json(
+            r#" { "bookmarks": [ { "href": "file:///a", "added": "2024-01-01T00:00:00Z",
+                "info": { "metadata": [ { "owner": "x", "extra": [1.5e3, true],
+                "applications": [ { "name": "\ud83d\ude00", "exec": "e", "modified": "m", "count": 2 } ] } ] } } ] } "#,
+        )?;
+        assert_eq!(parsed.version, XBEL_VERSION);
+        let metadata = &parsed.bookmarks[0].info.as_ref().unwrap().metadata[0];
+        assert_eq!(metadata.applications.applications[0].name, "\u{1F600}");
+        assert_eq!(metadata.applications.applications[0].count, 2);
+
+        for invalid in [
+            "",
+            "[",
+            r#"{"bookmarks": [{"href": "file:///a"}]}"#,
+            r#"{"title": 1}"#,
+            r#"{"bookmarks": [{"href": "a", "added": "b", "info": {"metadata": [{"owner": "o", "applications": [{"name": "n", "exec": "e", "modified": "m", "count": -1}]}]}}]}"#,
+            r#"{"title": "\ud800"}"#,
+            "{} {}",
+            &"[".repeat(1000),
+        ] {
+            assert!(matches!(
+                RecentlyUsed::from_json(invalid),
+                Err(Error::Json(_))
+            ));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_csv() -> Result<(), Box<dyn std::error::Error>> {
+        let mut recently_used = parse_reader(SAMPLE.as_bytes())?;
+        let mut bookmark = recently_used.bookmarks[0].clone();
+        bookmark.href = "file:///home/user/a,\"b\".txt".to_owned();
+        bookmark.visited = None;
+        bookmark.info = None;
+        recently_used.bookmarks.push(bookmark);
+
+        let metadata = recently_used.bookmarks[0]
+            .info
+            .as_mut()
+            .unwrap()
+            .freedesktop_mut()
+            .unwrap();
+        let mut app = metadata.applications.applications[0].clone();
+        app.name = "org.gnome.Nautilus".to_owned();
+        app.modified = "2024-09-01T00:00:00Z".to_owned();
+        metadata.applications.applications.push(app);
+
+        let mut csv = Vec::new();
+        export_csv(&recently_used, &mut csv)?;
+        assert_eq!(
+            String::from_utf8(csv)?,
+            concat!(
+                "href,added,modified,visited,mime_type,last_application\r\n",
+                "file:///home/user/notes.md,2024-09-01T10:00:00.000000Z,2024-09-02T11:00:00.000000Z,",
+                "2024-09-03T12:00:00.000000Z,text/markdown,org.gnome.TextEditor\r\n",
+                "\"file:///home/user/a,\"\"b\"\".txt\",2024-09-01T10:00:00.000000Z,",
+                "2024-09-02T11:00:00.000000Z,,,\r\n",
+            )
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_netscape_html() -> Result<(), Box<dyn std::error::Error>> {
+        let mut recently_used = parse_reader(SAMPLE.as_bytes())?;
+        recently_used.bookmarks[0].href = "file:///home/user/Tom%20&%20Jerry.md".to_owned();
+        recently_used.bookmarks[0].visited = Some("yesterday".to_owned());
+
+        let mut html = Vec::new();
+        export_netscape_html(&recently_used, &mut html)?;
+        let html = String::from_utf8(html)?;
+        assert!(html.starts_with("<!DOCTYPE NETSCAPE-Bookmark-file-1>\n"));
+        assert!(html.contains(concat!(
+            r#"<DT><A HREF="file:///home/user/Tom%20&amp;%20Jerry.md" ADD_DATE="1725184800" "#,
+            r#"LAST_MODIFIED="1725274800">Tom &amp; Jerry.md</A>"#
+        )));
+        assert!(html.ends_with("</DL><p>\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_titles() -> Result<(), Box<dyn std::error::Error>> {
+        let input = SAMPLE
+            .replacen("\n>\n", "\n>\n  <title></title>\n", 1)
+            .replace(
+                "<info>",
+                "<title>Tom &amp; Jerry &lt;3</title>\n    <desc/>\n    <info>",
+            );
+
+        let recently_used = parse_reader(input.as_bytes())?;
+        assert_eq!(recently_used.title.as_deref(), Some(""));
+
+        let written = custom_write(&recently_used)?;
+        assert!(written.contains("<title/>"));
+        assert!(written.contains("<title>Tom &amp; Jerry &lt;3</title>"));
+        assert!(written.contains("<desc/>"));
+
+        let bookmark = &parse_reader(written.as_bytes())?.bookmarks[0];
+        assert_eq!(bookmark.title.as_deref(), Some("Tom & Jerry <3"));
+        assert_eq!(bookmark.desc.as_deref(), Some(""));
+
+        let bookmark = bookmark
+            .clone()
+            .with_title("Remote")
+            .with_desc("On the server");
+        let written = custom_write(&RecentlyUsed {
+            bookmarks: vec![bookmark],
+            ..RecentlyUsed::default()
+        })?;
+        assert!(
+            written.contains("<title>Remote</title>\n    <desc>On the server</desc>\n    <info>")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_namespace_prefixes() -> Result<(), Box<dyn std::error::Error>> {
+        let input = SAMPLE
+            .replace("xmlns:mime=", "xmlns:m=\"urn:other\" xmlns:mime=")
+            .replace("</metadata>", "<bookmark:private/></metadata>");
+        let recently_used = parse_reader(input.as_bytes())?;
+
+        let options = WriteOptions {
+            bookmark_namespace: Some(Namespace {
+                prefix: "bm".to_owned(),
+                uri: BOOKMARK_NAMESPACE.to_owned(),
+            }),
+            mime_namespace: Some(Namespace {
+                prefix: "m".to_owned(),
+                uri: MIME_NAMESPACE.to_owned(),
+            }),
+            ..WriteOptions::default()
+        };
+        let written = custom_write_with(&recently_used, &options)?;
+        assert!(written.contains("<bm:applications>"));
+        assert!(written.contains("<bm:private/>"));
+        assert!(written.contains("<m:mime-type "));
+        assert!(!written.contains("urn:other"));
+
+        let parsed = parse_reader(written.as_bytes())?;
+        let info = parsed.bookmarks[0].info.as_ref().unwrap();
+        let metadata = info.freedesktop().unwrap();
+        assert_eq!(
+            metadata.mime_type.as_ref().unwrap().mime_type,
+            "text/markdown"
+        );
+        assert_eq!(metadata.applications.applications.len(), 1);
+
+        // Without namespaces in the options, those of the document are declared.
+        let mut recently_used = recently_used;
+        recently_used.xmlns_mime = "urn:mime".to_owned();
+        let written = custom_write(&recently_used)?;
+        assert!(written.contains(r#"xmlns:mime="urn:mime""#));
+        assert!(written.contains(&format!(r#"xmlns:bookmark="{BOOKMARK_NAMESPACE}""#)));
+        assert_eq!(parse_reader(written.as_bytes())?.xmlns_mime, "urn:mime");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_journal() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join("recently-used.xbel");
+        fs::write(&path, SAMPLE)?;
+        let options = WriteOptions {
+            journal: true,
+            ..WriteOptions::default()
+        };
+
+        let mut recently_used = parse_path(&path)?;
+        let mut bookmark = recently_used.bookmarks.remove(0);
+        bookmark.href = "file:///home/user/tab\there.md".to_owned();
+        recently_used.bookmarks.push(bookmark);
+        recently_used.save_to_with(&path, &options)?;
+        recently_used.save_to_with(&path, &options)?;
+
+        let journal = read_journal(&path)?;
+        let changes: Vec<_> = journal
+            .iter()
+            .map(|entry| (entry.change, entry.href.as_str()))
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                (JournalChange::Added, "file:///home/user/tab\there.md"),
+                (JournalChange::Removed, "file:///home/user/notes.md"),
+            ]
+        );
+        assert!(!journal[0].application.is_empty());
+
+        // A full journal is rotated, keeping the entries from before.
+        let full = "x".repeat(journal::MAX_JOURNAL_LEN as usize);
+        let journal_path = journal::journal_path(&path);
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&journal_path)?
+            .write_all(full.as_bytes())?;
+        recently_used.bookmarks.clear();
+        recently_used.save_to_with(&path, &options)?;
+        assert!(fs::metadata(&journal_path)?.len() < 1024);
+        assert!(journal::rotated_journal_path(&path).exists());
+        assert_eq!(read_journal(&path)?.len(), 3);
+
+        // Failing to write the journal doesn't fail the save.
+        fs::remove_file(&journal_path)?;
+        fs::create_dir(&journal_path)?;
+        recently_used.bookmarks = parse_reader(SAMPLE.as_bytes())?.bookmarks;
+        recently_used.save_to_with(&path, &options)?;
+        assert_eq!(parse_path(&path)?.bookmarks.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_restore_backup() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join("recently-used.xbel");
+        fs::write(&path, SAMPLE)?;
+        let original = parse_path(&path)?;
+        let options = WriteOptions {
+            backups: 2,
+            ..WriteOptions::default()
+        };
+
+        let mut purged = original.clone();
+        purged.bookmarks.clear();
+        purged.save_to_with(&path, &options)?;
+        assert!(parse_path(&path)?.bookmarks.is_empty());
+
+        assert_eq!(
+            restore_backup_path(&path, 0, &WriteOptions::default())?,
+            original.bookmarks.len()
+        );
+        assert_eq!(fs::read_to_string(&path)?, SAMPLE);
+
+        // Restoring again swaps the purged list back.
+        assert_eq!(restore_backup_path(&path, 0, &WriteOptions::default())?, 0);
+        assert!(parse_path(&path)?.bookmarks.is_empty());
+
+        assert!(matches!(
+            restore_backup_path(&path, 1, &WriteOptions::default()),
+            Err(Error::Read(why)) if why.kind() == io::ErrorKind::NotFound
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_typed_timestamps() -> Result<(), Box<dyn std::error::Error>> {
+        let mut bookmark = parse_reader(SAMPLE.as_bytes())?.bookmarks.remove(0);
+        bookmark.added = "2024-05-01T14:30:00+02:00".to_owned();
+        bookmark.visited = Some("yesterday".to_owned());
+        bookmark.modified = None;
+
+        let added = bookmark.added_time().ok_or("added should parse")?;
+        assert_eq!(added, Utc.with_ymd_and_hms(2024, 5, 1, 12, 30, 0).unwrap());
+        assert_eq!(bookmark.visited_time(), None);
+        assert_eq!(bookmark.modified_time(), None);
+
+        bookmark.set_added(added);
+        bookmark.set_visited(added + chrono::Duration::microseconds(1_500_000));
+        assert_eq!(bookmark.added, "2024-05-01T12:30:00.000000Z");
+        assert_eq!(
+            bookmark.visited.as_deref(),
+            Some("2024-05-01T12:30:01.500000Z")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bookmark_paths() -> Result<(), Box<dyn std::error::Error>> {
+        let mut bookmark = parse_reader(SAMPLE.as_bytes())?.bookmarks.remove(0);
+        bookmark.href = "file:///home/user/My%20Notes/caf%C3%A9.md".to_owned();
+        assert_eq!(bookmark.url()?.scheme(), "file");
+        assert_eq!(
+            bookmark.to_path(),
+            Some(PathBuf::from("/home/user/My Notes/café.md"))
+        );
+
+        assert!(bookmark.is_local());
+        assert_eq!(bookmark.host(), None);
+
+        bookmark.href = "file://localhost/home/user/notes.md".to_owned();
+        assert!(bookmark.is_local());
+        assert_eq!(
+            bookmark.to_path(),
+            Some(PathBuf::from("/home/user/notes.md"))
+        );
+
+        bookmark.href = "file://nas.local/export/notes.md".to_owned();
+        assert!(!bookmark.is_local());
+        assert_eq!(bookmark.host().as_deref(), Some("nas.local"));
+        assert_eq!(bookmark.to_path(), None);
+
+        let remote = Bookmark::builder("/export/notes.md")
+            .host("nas.local")
+            .app("org.test", "'test %u'")
+            .build()?;
+        assert_eq!(remote.href, bookmark.href);
+        assert!(matches!(
+            Bookmark::builder("https://example.com/")
+                .host("nas.local")
+                .app("org.test", "'test %u'")
+                .build(),
+            Err(Error::Path)
+        ));
+
+        bookmark.href = "https://example.com/notes.md".to_owned();
+        assert!(bookmark.url().is_ok());
+        assert!(!bookmark.is_local());
+        assert_eq!(bookmark.to_path(), None);
+
+        bookmark.href = "not a url".to_owned();
+        assert!(matches!(bookmark.url(), Err(Error::Url(_))));
+        assert_eq!(bookmark.to_path(), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_metadata_groups() -> Result<(), Box<dyn std::error::Error>> {
+        let input = SAMPLE.replace(
+            "        </bookmark:applications>\n",
+            "        </bookmark:applications>\n        <bookmark:groups>\n          <bookmark:group>Notes</bookmark:group>\n          <bookmark:group>Work &amp; Play</bookmark:group>\n        </bookmark:groups>\n",
+        );
+        let mut recently_used = parse_reader(input.as_bytes())?;
+        assert!(custom_write(&recently_used)?.contains(
+            "        </bookmark:applications>\n        <bookmark:groups>\n          <bookmark:group>Notes</bookmark:group>\n          <bookmark:group>Work &amp; Play</bookmark:group>\n        </bookmark:groups>\n      </metadata>"
+        ));
+
+        let metadata = recently_used.bookmarks[0]
+            .info
+            .as_mut()
+            .and_then(Info::freedesktop_mut)
+            .ok_or("missing metadata")?;
+        assert_eq!(metadata.groups, ["Notes", "Work & Play"]);
+        assert!(metadata.unknown.elements.is_empty());
+
+        assert!(!metadata.add_group("Notes"));
+        assert!(metadata.add_group("Drafts"));
+        assert!(metadata.remove_group("Notes"));
+        assert!(!metadata.remove_group("Notes"));
+        assert!(metadata.has_group("Drafts"));
+
+        let parsed = parse_reader(custom_write(&recently_used)?.as_bytes())?;
+        let metadata = parsed.bookmarks[0].info.as_ref().unwrap().freedesktop();
+        assert_eq!(metadata.unwrap().groups, ["Work & Play", "Drafts"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_private_bookmarks() -> Result<(), Box<dyn std::error::Error>> {
+        let input = SAMPLE.replace(
+            "        </bookmark:applications>\n",
+            "        </bookmark:applications>\n        <bookmark:private/>\n",
+        );
+        let mut recently_used = parse_reader(input.as_bytes())?;
+        assert!(recently_used.bookmarks[0].is_private());
+        assert!(custom_write(&recently_used)?
+            .contains("</bookmark:applications>\n        <bookmark:private/>\n      </metadata>"));
+
+        let shown = |recently_used: &RecentlyUsed, app| recently_used.visible_to(app).count();
+        assert_eq!(shown(&recently_used, "org.gnome.TextEditor"), 1);
+        assert_eq!(shown(&recently_used, "org.gnome.Nautilus"), 0);
+
+        let metadata = recently_used.bookmarks[0]
+            .info
+            .as_mut()
+            .and_then(Info::freedesktop_mut)
+            .ok_or("missing metadata")?;
+        metadata.private = false;
+        assert_eq!(shown(&recently_used, "org.gnome.Nautilus"), 1);
+        assert!(!custom_write(&recently_used)?.contains("private"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_metadata_icon() -> Result<(), Box<dyn std::error::Error>> {
+        let icon = r#"<bookmark:icon href="file:///usr/share/icons/notes.png" type="image/png"/>"#;
+        let input = SAMPLE.replace(
+            "        </bookmark:applications>\n",
+            &format!("        </bookmark:applications>\n        {icon}\n"),
+        );
+        let mut recently_used = parse_reader(input.as_bytes())?;
+        assert!(custom_write(&recently_used)?.contains(icon));
+
+        let metadata = recently_used.bookmarks[0]
+            .info
+            .as_mut()
+            .and_then(Info::freedesktop_mut)
+            .ok_or("missing metadata")?;
+        assert_eq!(
+            metadata.icon,
+            Some(Icon {
+                href: "file:///usr/share/icons/notes.png".to_owned(),
+                mime_type: Some("image/png".to_owned()),
+            })
+        );
+        assert!(metadata.unknown.elements.is_empty());
+        metadata.icon = Some(Icon {
+            href: "text-x-generic".to_owned(),
+            mime_type: None,
+        });
+        let written = custom_write(&recently_used)?;
+        assert!(written.contains(r#"<bookmark:icon href="text-x-generic"/>"#));
+        let parsed = parse_reader(written.as_bytes())?;
+        let metadata = parsed.bookmarks[0].info.as_ref().unwrap().freedesktop();
+        assert_eq!(
+            metadata.unwrap().icon.as_ref().unwrap().href,
+            "text-x-generic"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_metadata_extensions() -> Result<(), Box<dyn std::error::Error>> {
+        let input = SAMPLE
+            .replace("\n>", " xmlns:ex=\"urn:example\">")
+            .replace(
+                "        </bookmark:applications>\n",
+                "        </bookmark:applications>\n        <ex:tags><ex:tag>rust</ex:tag></ex:tags>\n        <bookmark:unmodeled/>\n",
+            );
+        let mut recently_used = parse_reader(input.as_bytes())?;
+        let metadata = recently_used.bookmarks[0]
+            .info
+            .as_mut()
+            .and_then(Info::freedesktop_mut)
+            .ok_or("missing metadata")?;
+        assert_eq!(metadata.extensions.len(), 1);
+        assert_eq!(
+            metadata.extension("ex:tags").unwrap().children[0].text,
+            "rust"
+        );
+        assert_eq!(metadata.unknown.elements[0].name, "bookmark:unmodeled");
+
+        metadata.extensions.push(RawElement {
+            name: "app:project".to_owned(),
+            attributes: vec![("xmlns:app".to_owned(), "urn:app".to_owned())],
+            text: "crate".to_owned(),
+            ..RawElement::default()
+        });
+
+        let written = custom_write(&recently_used)?;
+        assert!(written.contains(
+            "<ex:tags>\n          <ex:tag>rust</ex:tag>\n        </ex:tags>\n        <app:project xmlns:app=\"urn:app\">crate</app:project>\n        <bookmark:unmodeled/>"
+        ));
+        let parsed = parse_reader(written.as_bytes())?;
+        let metadata = parsed.bookmarks[0].info.as_ref().unwrap().freedesktop();
+        assert_eq!(
+            metadata.unwrap().extension("app:project").unwrap().text,
+            "crate"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bookmark_builder() -> Result<(), Box<dyn std::error::Error>> {
+        let visited = Utc.with_ymd_and_hms(2024, 9, 3, 12, 0, 0).unwrap();
+        let bookmark = Bookmark::builder("/home/user/My Notes.md")
+            .visited(visited)
+            .app("org.gnome.TextEditor", "gnome-text-editor %u")
+            .application(Application::builder("org.gnome.Nautilus", "nautilus %u").count(2))
+            .group("Notes")
+            .build()?;
+
+        assert_eq!(bookmark.href, "file:///home/user/My%20Notes.md");
+        assert_eq!(bookmark.visited_time(), Some(visited));
+        let metadata = bookmark.info.as_ref().and_then(Info::freedesktop).unwrap();
+        assert_eq!(
+            metadata.mime_type.as_ref().unwrap().mime_type,
+            "text/markdown"
+        );
+        assert_eq!(metadata.applications.applications[1].count, 2);
+        assert_eq!(
+            metadata.applications.applications[0].modified,
+            bookmark.modified.clone().unwrap()
+        );
+        assert!(metadata.has_group("Notes"));
+
+        let recently_used = RecentlyUsed {
+            bookmarks: vec![bookmark],
+            ..RecentlyUsed::default()
+        };
+        let written = custom_write(&recently_used)?;
+        assert!(parse_reader(written.as_bytes())?.validate().is_empty());
+
+        let bookmark = Bookmark::builder("https://example.com/archive")
+            .mime("application/zip")
+            .app("org.test", "'test %u'")
+            .build()?;
+        assert_eq!(bookmark.href, "https://example.com/archive");
+
+        assert!(matches!(
+            Bookmark::builder("relative/notes.md")
+                .app("org.test", "'test %u'")
+                .build(),
+            Err(Error::Path)
+        ));
+        assert!(matches!(
+            Bookmark::builder("/home/user/notes.md").build(),
+            Err(Error::NoApplications)
+        ));
+        let bookmark = Bookmark::builder("/tmp/unknown")
+            .app("org.test", "'test %u'")
+            .build()?;
+        let metadata = bookmark.info.as_ref().and_then(Info::freedesktop).unwrap();
+        assert_eq!(
+            metadata.mime_type.as_ref().unwrap().mime_type,
+            "application/octet-stream"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_mime_types() -> Result<(), Box<dyn std::error::Error>> {
+        assert_eq!(MimeType::new("text/x-markdown")?.mime_type, "text/markdown");
+        assert_eq!(
+            MimeType::new("Text/Plain; charset=utf-8")?.mime_type,
+            "text/plain; charset=utf-8"
+        );
+        assert!(matches!(MimeType::new("markdown"), Err(Error::Mime(_))));
+
+        let mime = MimeType::new("image/jpg")?.mime()?;
+        assert_eq!(mime, mime::IMAGE_JPEG);
+
+        let bookmark = Bookmark::builder("/home/user/notes")
+            .mime("text/x-markdown")
+            .app("org.test", "'test %u'")
+            .build()?;
+        let metadata = bookmark.info.as_ref().and_then(Info::freedesktop).unwrap();
+        assert_eq!(
+            metadata.mime_type.as_ref().unwrap().mime_type,
+            "text/markdown"
+        );
+        assert!(matches!(
+            Bookmark::builder("/home/user/notes")
+                .mime("text")
+                .app("org.test", "'test %u'")
+                .build(),
+            Err(Error::Mime(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bookmark_equality() -> Result<(), Box<dyn std::error::Error>> {
+        let bookmark = parse_reader(SAMPLE.as_bytes())?.bookmarks.remove(0);
+        let with_href = |href: &str| Bookmark {
+            href: href.to_owned(),
+            visited: None,
+            ..bookmark.clone()
+        };
+
+        let notes = with_href("file:///home/user/My%20Notes/");
+        assert_eq!(notes, with_href("file:///home/user/My Notes"));
+        assert_eq!(notes, with_href("FILE:///home/user/./My%20Notes"));
+        assert_ne!(notes, with_href("file:///home/user/My Notes.md"));
+        assert_eq!(with_href("file:///"), with_href("file:///"));
+
+        // Bytes which aren't UTF-8 and escaped slashes are told apart, not decoded lossily.
+        let (ff, fe) = (
+            with_href("file:///tmp/%FF.txt"),
+            with_href("file:///tmp/%FE.txt"),
+        );
+        assert_ne!(ff, fe);
+        assert_ne!(ff.id(), fe.id());
+        assert_eq!(ff, with_href("file:///tmp/%ff.txt"));
+        let (escaped, nested) = (with_href("file:///tmp/a%2Fb"), with_href("file:///tmp/a/b"));
+        assert_ne!(escaped, nested);
+        assert_ne!(escaped.id(), nested.id());
+        assert_eq!(escaped, with_href("file:///tmp/a%2fb"));
+        assert_ne!(with_href("file:///tmp/%25FF.txt"), ff);
+
+        let unique: std::collections::HashSet<_> = [
+            notes.clone(),
+            with_href("file:///home/user/My Notes"),
+            with_href("https://Example.com"),
+            with_href("https://example.com/"),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(unique.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_display() -> Result<(), Box<dyn std::error::Error>> {
+        let mut recently_used = parse_reader(SAMPLE.as_bytes())?;
+        let bookmark = &mut recently_used.bookmarks[0];
+        bookmark.href = "file:///home/user/My%20Notes.md".to_owned();
+        bookmark.set_visited(Utc::now() - chrono::Duration::minutes(185));
+
+        assert_eq!(
+            bookmark.to_string(),
+            "My Notes.md, visited 3 hours ago by org.gnome.TextEditor"
+        );
+
+        let mut titled = bookmark.clone();
+        titled.title = Some("Meeting notes".to_owned());
+        assert!(titled.to_string().starts_with("Meeting notes, "));
+
+        let mut other = bookmark.clone();
+        other.href = "https://example.com/".to_owned();
+        other.info = None;
+        other.visited = None;
+        other.set_modified(Utc::now() - chrono::Duration::days(1));
+        recently_used.bookmarks.push(other);
+
+        assert_eq!(
+            recently_used.to_string(),
+            "My Notes.md, visited 3 hours ago by org.gnome.TextEditor\nexample.com, modified 1 day ago\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_display_name() -> Result<(), Box<dyn std::error::Error>> {
+        let mut bookmark = Bookmark::builder("file:///home/user/My%20Notes.md")
+            .app("org.test", "'test %u'")
+            .build()?;
+        assert_eq!(bookmark.display_name(), "My Notes.md");
+
+        bookmark.href = "file:///home/user/Projects/".to_owned();
+        assert_eq!(bookmark.display_name(), "Projects");
+
+        bookmark.href = "file:///home/user/caf%E9.txt".to_owned();
+        assert_eq!(bookmark.display_name(), "caf\u{FFFD}.txt");
+
+        bookmark.href = "file:///".to_owned();
+        assert_eq!(bookmark.display_name(), "file:///");
+
+        bookmark.title = Some("Coffee".to_owned());
+        assert_eq!(bookmark.display_name(), "Coffee");
+        bookmark.title = Some(" ".to_owned());
+        assert_eq!(bookmark.display_name(), "file:///");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lenient_counts() -> Result<(), Box<dyn std::error::Error>> {
+        for (count, expected) in [
+            ("4294967296", u32::MAX),
+            ("99999999999999999999999", u32::MAX),
+            (" 7 ", 7),
+            ("-1", 1),
+            ("many", 1),
+            ("", 1),
+        ] {
+            let input = SAMPLE.replace("count=\"3\"", &format!("count=\"{count}\""));
+            let recently_used = parse_reader(input.as_bytes())?;
+            let metadata = recently_used.bookmarks[0].info.as_ref().unwrap();
+            let app = &metadata.freedesktop().unwrap().applications.applications[0];
+            assert_eq!(app.count, expected, "{count}");
+
+            #[cfg(feature = "serde")]
+            {
+                let borrowed = parse_borrowed(&input)?.into_owned();
+                let metadata = borrowed.bookmarks[0].info.as_ref().unwrap();
+                let app = &metadata.freedesktop().unwrap().applications.applications[0];
+                assert_eq!(app.count, expected, "{count}");
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_record_remote_url() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join("recently-used.xbel");
+        fs::write(&path, SAMPLE)?;
+
+        let url = Url::parse("sftp://example.com/home/user/report.pdf")?;
+        for _ in 0..2 {
+            let bookmark = Bookmark::builder_from_url(&url)
+                .app("org.gnome.Nautilus", "nautilus %u")
+                .build()?;
+            record(&path, bookmark)?;
+        }
+
+        let recently_used = parse_path(&path)?;
+        assert_eq!(recently_used.bookmarks.len(), 2);
+        let bookmark = &recently_used.bookmarks[1];
+        assert_eq!(bookmark.href, "sftp://example.com/home/user/report.pdf");
+        assert!(!bookmark.is_local());
+        let metadata = &bookmark.info.as_ref().unwrap().metadata[0];
+        assert_eq!(
+            metadata.mime_type.as_ref().unwrap().mime_type,
+            "application/pdf"
+        );
+        assert_eq!(metadata.applications.applications[0].count, 2);
+
+        let trash = Bookmark::builder_from_url(&Url::parse("trash:///notes.txt")?)
+            .app("org.test", "'test %u'")
+            .build()?;
+        assert_eq!(trash.href, "trash:///notes.txt");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_exec() -> Result<(), Box<dyn std::error::Error>> {
+        let exec = Exec::with_uri_placeholder("gedit");
+        assert_eq!(exec.to_string(), "'gedit %u'");
+        assert_eq!(Exec::parse(&exec.to_string())?, exec);
+
+        let recently_used = parse_reader(SAMPLE.as_bytes())?;
+        let app = &recently_used.bookmarks[0].info.as_ref().unwrap().metadata[0]
+            .applications
+            .applications[0];
+        let command = app.command()?;
+        assert_eq!(command.program(), Some("gnome-text-editor"));
+        assert_eq!(
+            command.expand("file:///home/user/My%20Notes.md")?,
+            ["gnome-text-editor", "file:///home/user/My%20Notes.md"]
+        );
+
+        let exec = Exec::new(["my editor", "--new-window", "%f"]);
+        assert_eq!(exec.to_string(), r#"''\''my editor'\'' --new-window %f'"#);
+        assert_eq!(Exec::parse(&exec.to_string())?, exec);
+        assert_eq!(
+            exec.expand("file:///home/user/My%20Notes.md")?,
+            ["my editor", "--new-window", "/home/user/My Notes.md"]
+        );
+        assert!(matches!(
+            exec.expand("sftp://example.com/notes.md"),
+            Err(Error::Exec(_))
+        ));
+
+        assert_eq!(
+            Exec::parse(r#"'editor' "%u" &"#)?.args(),
+            ["editor", "%u", "&"]
+        );
+        assert_eq!(
+            Exec::parse("gedit 'my file' %u")?.args(),
+            ["gedit", "my file", "%u"]
+        );
+        assert_eq!(Exec::parse("gedit %u")?.args(), ["gedit", "%u"]);
+        assert!(matches!(Exec::parse("'gedit %u"), Err(Error::Exec(_))));
+        assert!(matches!(
+            Exec::new(["app", "%k"]).expand("file:///a"),
+            Err(Error::Exec(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_by_href() -> Result<(), Box<dyn std::error::Error>> {
+        let mut recently_used = parse_reader(SAMPLE.as_bytes())?;
+        let mut duplicate = recently_used.bookmarks[0].clone();
+        duplicate.title = Some("Later".to_owned());
+        recently_used.bookmarks.push(duplicate);
+        recently_used.bookmarks.push(
+            Bookmark::builder("/home/user/other.txt")
+                .app("org.test", "'test %u'")
+                .build()?,
+        );
+
+        let by_href = recently_used.by_href();
+        assert_eq!(by_href.len(), 2);
+        assert_eq!(
+            by_href["file:///home/user/notes.md"].title.as_deref(),
+            Some("Later")
+        );
+        assert!(by_href.contains_key("file:///home/user/other.txt"));
+
+        let map = recently_used.into_map();
+        assert_eq!(map.len(), 2);
+        assert_eq!(
+            map["file:///home/user/notes.md"].title.as_deref(),
+            Some("Later")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_frecency() -> Result<(), Box<dyn std::error::Error>> {
+        let now = Utc::now();
+        let bookmark = |path: &str, days: i64, count: u32| {
+            Bookmark::builder(path)
+                .visited(now - chrono::Duration::days(days))
+                .application(Application::builder("org.gnome.Nautilus", "nautilus %u").count(count))
+                .build()
+        };
+
+        let recent = bookmark("/home/user/recent.txt", 1, 1)?;
+        let frequent = bookmark("/home/user/frequent.txt", 20, 5)?;
+        let stale = bookmark("/home/user/stale.txt", 365, 2)?;
+        assert_eq!(recent.score(now), 100.0);
+        assert_eq!(frequent.score(now), 250.0);
+        assert_eq!(stale.score(now), 20.0);
+
+        let recently_used = RecentlyUsed {
+            bookmarks: vec![stale, recent, frequent],
+            ..RecentlyUsed::default()
+        };
+        let ranked: Vec<_> = recently_used
+            .ranked()
+            .into_iter()
+            .map(|bookmark| bookmark.href.as_str())
+            .collect();
+        assert_eq!(
+            ranked,
+            [
+                "file:///home/user/frequent.txt",
+                "file:///home/user/recent.txt",
+                "file:///home/user/stale.txt"
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_time_periods() -> Result<(), Box<dyn std::error::Error>> {
+        let now = Utc.with_ymd_and_hms(2024, 9, 10, 9, 0, 0).unwrap();
+        let visited = |time| {
+            Bookmark::builder("/home/user/notes.md")
+                .visited(time)
+                .app("org.test", "'test %u'")
+                .build()
+        };
+
+        let bookmark = visited(Utc.with_ymd_and_hms(2024, 9, 10, 1, 0, 0).unwrap())?;
+        assert_eq!(bookmark.visited_ago(now), Some(chrono::Duration::hours(8)));
+        assert_eq!(bookmark.visited_period(&now), Some(TimePeriod::Today));
+
+        let bookmark = visited(Utc.with_ymd_and_hms(2024, 9, 9, 23, 0, 0).unwrap())?;
+        assert_eq!(bookmark.visited_period(&now), Some(TimePeriod::Yesterday));
+        let tokyo = now.with_timezone(&chrono::FixedOffset::east_opt(9 * 3600).unwrap());
+        assert_eq!(bookmark.visited_period(&tokyo), Some(TimePeriod::Today));
+
+        let bookmark = visited(Utc.with_ymd_and_hms(2024, 9, 4, 0, 0, 0).unwrap())?;
+        assert_eq!(bookmark.visited_period(&now), Some(TimePeriod::ThisWeek));
+        let bookmark = visited(Utc.with_ymd_and_hms(2024, 9, 3, 23, 0, 0).unwrap())?;
+        assert_eq!(bookmark.visited_period(&now), Some(TimePeriod::Older));
+
+        let bookmark = visited(Utc.with_ymd_and_hms(2024, 9, 11, 0, 0, 0).unwrap())?;
+        assert_eq!(
+            bookmark.visited_ago(now),
+            Some(chrono::Duration::hours(-15))
+        );
+        assert_eq!(bookmark.visited_period(&now), Some(TimePeriod::Today));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bookmark_id() -> Result<(), Box<dyn std::error::Error>> {
+        let mut recently_used = parse_reader(SAMPLE.as_bytes())?;
+        let id = recently_used.bookmarks[0].id();
+        assert_eq!(id, parse_reader(SAMPLE.as_bytes())?.bookmarks[0].id());
+        assert_eq!(id.to_string(), format!("{:016x}", id.as_u64()));
+
+        let spelled = Bookmark::builder("file:///home/user/notes.md/")
+            .app("org.test", "'test %u'")
+            .build()?;
+        assert_eq!(spelled.id(), id);
+        let other = Bookmark::builder("/home/user/other.md")
+            .app("org.test", "'test %u'")
+            .build()?;
+        assert_ne!(other.id(), id);
+
+        recently_used.get_mut(id).unwrap().title = Some("Notes".to_owned());
+        assert_eq!(
+            recently_used.get(id).unwrap().title.as_deref(),
+            Some("Notes")
+        );
+        assert!(recently_used.get(other.id()).is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_record_keeps_added() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join("recently-used.xbel");
+        fs::write(&path, SAMPLE)?;
+
+        let bookmark = Bookmark::builder("/home/user/notes.md")
+            .app("org.gnome.TextEditor", "gnome-text-editor %u")
+            .build()?;
+        record(&path, bookmark.clone())?;
+
+        let recently_used = parse_path(&path)?;
+        assert_eq!(recently_used.bookmarks.len(), 1);
+        let recorded = &recently_used.bookmarks[0];
+        assert_eq!(recorded.added, "2024-09-01T10:00:00.000000Z");
+        assert_eq!(recorded.modified, bookmark.modified);
+        assert_eq!(recorded.visited, bookmark.visited);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_visited_now() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join("notes.md");
+        let accessed = Utc.with_ymd_and_hms(2001, 1, 1, 0, 0, 0).unwrap();
+        File::create(&path)?.set_times(fs::FileTimes::new().set_accessed(accessed.into()))?;
+
+        let record = |options: &UpdateOptions| {
+            local_bookmark(&path, "org.test".into(), "test".into(), options)
+        };
+        let bookmark = record(&UpdateOptions::default())?;
+        assert!(bookmark.visited_time().unwrap() > accessed);
+
+        let bookmark = record(&UpdateOptions {
+            visited_now: false,
+            ..UpdateOptions::default()
+        })?;
+        assert_eq!(bookmark.visited_time(), Some(accessed));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_remove_recently_used() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join("recently-used.xbel");
+        fs::write(&path, SAMPLE)?;
+        record(
+            &path,
+            Bookmark::builder("sftp://example.com/a b.txt")
+                .app("org.test", "'test %u'")
+                .build()?,
+        )?;
+
+        assert!(!remove_recently_used_path(
+            &path,
+            "/home/user/other.md",
+            &WriteOptions::default()
+        )?);
+        assert!(remove_recently_used_path(
+            &path,
+            "/home/user/notes.md",
+            &WriteOptions::default()
+        )?);
+        assert!(!remove_recently_used_path(
+            &path,
+            "/home/user/notes.md",
+            &WriteOptions::default()
+        )?);
+        assert!(remove_recently_used_path(
+            &path,
+            "sftp://example.com/a%20b.txt",
+            &WriteOptions::default()
+        )?);
+        assert!(parse_path(&path)?.bookmarks.is_empty());
+
+        assert!(matches!(
+            remove_recently_used_path(&path, "notes.md", &WriteOptions::default()),
+            Err(Error::Path)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_purge_all() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join("recently-used.xbel");
+        assert_eq!(
+            purge_all_path(&path, |_| false, &WriteOptions::default())?,
+            0
+        );
+        assert!(!path.exists());
+
+        fs::write(&path, SAMPLE)?;
+        record(
+            &path,
+            Bookmark::builder("/home/user/keep.md")
+                .app("org.test", "'test %u'")
+                .build()?,
+        )?;
+        record(
+            &path,
+            Bookmark::builder("/home/user/drop.md")
+                .app("org.test", "'test %u'")
+                .build()?,
+        )?;
+
+        let removed = purge_all_path(
+            &path,
+            |bookmark| bookmark.href.ends_with("keep.md"),
+            &WriteOptions::default(),
+        )?;
+        assert_eq!(removed, 2);
+        let recently_used = parse_path(&path)?;
+        assert_eq!(recently_used.bookmarks.len(), 1);
+        assert_eq!(recently_used.bookmarks[0].href, "file:///home/user/keep.md");
+
+        assert_eq!(
+            purge_all_path(&path, |_| false, &WriteOptions::default())?,
+            1
+        );
+        let content = fs::read_to_string(&path)?;
+        assert!(content.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>"));
+        assert!(content.contains(BOOKMARK_NAMESPACE));
+        assert!(parse_path(&path)?.bookmarks.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_remove_by_application() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join("recently-used.xbel");
+        fs::write(&path, SAMPLE)?;
+        let shared = Bookmark::builder("/home/user/shared.md")
+            .app("org.gnome.TextEditor", "gnome-text-editor %u")
+            .app("org.gnome.Nautilus", "nautilus %u")
+            .build()?;
+        record(&path, shared)?;
+        record(
+            &path,
+            Bookmark::builder("/home/user/none.md")
+                .app("org.test", "'test %u'")
+                .build()?,
+        )?;
+
+        assert_eq!(
+            remove_by_application_path(&path, "org.gnome.TextEditor", &WriteOptions::default())?,
+            1
+        );
+        let recently_used = parse_path(&path)?;
+        let hrefs: Vec<_> = recently_used
+            .bookmarks
+            .iter()
+            .map(|b| b.href.as_str())
+            .collect();
+        assert_eq!(
+            hrefs,
+            ["file:///home/user/shared.md", "file:///home/user/none.md"]
+        );
+        assert!(!recently_used.bookmarks[0].has_application("org.gnome.TextEditor"));
+        assert!(recently_used.bookmarks[0].has_application("org.gnome.Nautilus"));
+
+        let modified = fs::metadata(&path)?.modified()?;
+        assert_eq!(
+            remove_by_application_path(&path, "org.gnome.TextEditor", &WriteOptions::default())?,
+            0
+        );
+        assert_eq!(fs::metadata(&path)?.modified()?, modified);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_prune_missing() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join("recently-used.xbel");
+        let existing = temp_dir.path().join("existing.txt");
+        fs::write(&existing, b"")?;
+        fs::write(
+            &path,
+            SAMPLE.replace("/home/user/notes.md", "/nonexistent/notes.md"),
+        )?;
+        record(
+            &path,
+            Bookmark::builder(&existing)
+                .app("org.test", "'test %u'")
+                .build()?,
+        )?;
+        record(
+            &path,
+            Bookmark::builder("sftp://example.com/gone.txt")
+                .app("org.test", "'test %u'")
+                .build()?,
+        )?;
+        record(
+            &path,
+            Bookmark::builder("file://server/gone.txt")
+                .app("org.test", "'test %u'")
+                .build()?,
+        )?;
+
+        assert_eq!(
+            prune_missing_path(&path, &WriteOptions::default())?,
+            ["file:///nonexistent/notes.md"]
+        );
+        let recently_used = parse_path(&path)?;
+        assert_eq!(recently_used.bookmarks.len(), 3);
+
+        assert!(prune_missing_path(&path, &WriteOptions::default())?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rename_entry() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join("recently-used.xbel");
+        fs::write(&path, SAMPLE)?;
+        let titled = Bookmark::builder("/home/user/report.txt")
+            .title("report.txt")
+            .group("Work")
+            .app("org.test", "'test %u'")
+            .build()?;
+        record(&path, titled)?;
+        record(
+            &path,
+            Bookmark::builder("/home/user/final.txt")
+                .app("org.test", "'test %u'")
+                .build()?,
+        )?;
+
+        assert!(rename_entry_path(
+            &path,
+            "/home/user/notes.md",
+            "/home/user/My Notes.md",
+            &WriteOptions::default()
+        )?);
+        assert!(rename_entry_path(
+            &path,
+            "file:///home/user/report.txt",
+            "/home/user/final.txt",
+            &WriteOptions::default()
+        )?);
+        assert!(!rename_entry_path(
+            &path,
+            "/home/user/notes.md",
+            "/tmp/notes.md",
+            &WriteOptions::default()
+        )?);
+
+        let original = parse_reader(SAMPLE.as_bytes())?;
+        let recently_used = parse_path(&path)?;
+        assert_eq!(recently_used.bookmarks.len(), 2);
+
+        let renamed = &recently_used.bookmarks[0];
+        assert_eq!(renamed.href, "file:///home/user/My%20Notes.md");
+        assert_eq!(renamed.added, original.bookmarks[0].added);
+        assert_eq!(renamed.visited, original.bookmarks[0].visited);
+        assert!(renamed.has_application("org.gnome.TextEditor"));
+
+        let moved = &recently_used.bookmarks[1];
+        assert_eq!(moved.href, "file:///home/user/final.txt");
+        assert_eq!(moved.title.as_deref(), Some("final.txt"));
+        assert!(moved.info.as_ref().unwrap().metadata[0].has_group("Work"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_record_all() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join("recently-used.xbel");
+        fs::write(&path, SAMPLE)?;
+
+        let items = [
+            RecentItem::new(temp_dir.path().join("a.rs"), "org.test", "test %u"),
+            RecentItem::new(temp_dir.path().join("b.rs"), "org.test", "test %u"),
+            RecentItem::new(temp_dir.path().join("a.rs"), "org.test", "test %u"),
+        ];
+        for item in &items {
+            fs::write(&item.path, b"")?;
+        }
+        let options = UpdateOptions::default();
+        let bookmarks = items
+            .iter()
+            .map(|item| item.to_bookmark(&options))
+            .collect::<Result<Vec<_>, _>>()?;
+        record_all(
+            &path,
+            bookmarks,
+            Placement::default(),
+            &WriteOptions::default(),
+        )?;
+
+        let recently_used = parse_path(&path)?;
+        assert_eq!(recently_used.bookmarks.len(), 3);
+        let app = &recently_used.bookmarks[1].info.as_ref().unwrap().metadata[0]
+            .applications
+            .applications[0];
+        assert_eq!(app.count, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_record_placement() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join("recently-used.xbel");
+        let day = |day| Utc.with_ymd_and_hms(2024, 9, day, 12, 0, 0).unwrap();
+        let bookmark = |name: &str, visited| {
+            Bookmark::builder(format!("/home/user/{name}"))
+                .visited(visited)
+                .app("org.test", "'test %u'")
+                .build()
+        };
+        let names = |path: &Path| -> Result<Vec<String>, Error> {
+            Ok(parse_path(path)?
+                .bookmarks
+                .iter()
+                .map(Bookmark::display_name)
+                .collect())
+        };
+
+        let write_options = WriteOptions::default();
+        let initial = [
+            bookmark("a", day(3))?,
+            bookmark("b", day(1))?,
+            bookmark("c", day(2))?,
+        ];
+        record_all(&path, initial, Placement::KeepPosition, &write_options)?;
+        record_all(
+            &path,
+            [bookmark("a", day(4))?],
+            Placement::KeepPosition,
+            &write_options,
+        )?;
+        assert_eq!(names(&path)?, ["a", "b", "c"]);
+
+        record_all(
+            &path,
+            [bookmark("a", day(5))?],
+            Placement::Append,
+            &write_options,
+        )?;
+        assert_eq!(names(&path)?, ["b", "c", "a"]);
+
+        record_all(
+            &path,
+            [bookmark("b", day(6))?],
+            Placement::SortByVisited,
+            &write_options,
+        )?;
+        assert_eq!(names(&path)?, ["c", "a", "b"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_exclude_rules() {
+        let rules = ExcludeRules::new()
+            .glob("~/.cache/**")
+            .glob("/home/*/secret?.txt")
+            .glob("/srv/**/build/*.o")
+            .uri_prefix("trash://");
+
+        let home = dirs::home_dir().unwrap();
+        assert!(rules.is_excluded(home.join(".cache/thumbnails/large/a.png")));
+        assert!(!rules.is_excluded(home.join(".cachet")));
+        assert!(rules.is_excluded("/home/user/secret1.txt"));
+        assert!(rules.is_excluded("file:///home/user/secret%C3%A9.txt"));
+        assert!(!rules.is_excluded("/home/user/nested/secret1.txt"));
+        assert!(!rules.is_excluded("/home/user/secret10.txt"));
+        assert!(rules.is_excluded("/srv/build/main.o"));
+        assert!(rules.is_excluded("/srv/project/crate/build/main.o"));
+        assert!(!rules.is_excluded("/srv/project/build/obj/main.o"));
+        assert!(rules.is_excluded("trash:///notes.md"));
+        assert!(!rules.is_excluded("sftp://server/notes.md"));
+        assert!(!ExcludeRules::new().is_excluded("/tmp/notes.md"));
+    }
+
+    #[cfg(feature = "gsettings")]
+    #[test]
+    fn test_privacy_settings() {
+        let listing = |max_age: &str| {
+            format!(
+                "org.gnome.desktop.privacy remember-app-usage true\n\
+                 org.gnome.desktop.privacy recent-files-max-age {max_age}\n\
+                 org.gnome.desktop.privacy remember-recent-files true\n"
+            )
+        };
+
+        let settings = gsettings::parse(&listing("30"));
+        assert!(settings.remember_recent_files);
+        assert_eq!(
+            settings.recent_files_max_age,
+            Some(Duration::from_secs(30 * 24 * 60 * 60))
+        );
+        assert_eq!(
+            gsettings::parse(&listing("int32 7")).recent_files_max_age,
+            Some(Duration::from_secs(7 * 24 * 60 * 60))
+        );
+
+        // Zero days turns recording off, rather than dropping every file.
+        let settings = gsettings::parse(&listing("0"));
+        assert!(!settings.remember_recent_files);
+
+        // A negative number of days keeps files forever.
+        for max_age in ["-1", "-30"] {
+            let settings = gsettings::parse(&listing(max_age));
+            assert!(settings.remember_recent_files);
+            assert_eq!(settings.recent_files_max_age, None);
+        }
+
+        let off = "org.gnome.desktop.privacy remember-recent-files false\n";
+        assert!(!gsettings::parse(off).remember_recent_files);
+        assert_eq!(gsettings::parse(""), PrivacySettings::default());
+        assert_eq!(
+            gsettings::parse(&listing("'thirty'")),
+            PrivacySettings::default()
+        );
+    }
+
+    #[cfg(feature = "gsettings")]
+    #[test]
+    fn test_privacy_settings_update() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join("recently-used.xbel");
+        fs::write(&path, SAMPLE)?;
+        let bookmark = || {
+            Bookmark::builder("/home/user/draft.md")
+                .app("org.test", "'test %u'")
+                .build()
+        };
+        let options = UpdateOptions::default();
+
+        let off = PrivacySettings {
+            remember_recent_files: false,
+            ..PrivacySettings::default()
+        };
+        let write_options = privacy_write_options(&off);
+        record_update_path(&path, [bookmark()?], &options, write_options)?;
+        assert_eq!(fs::read_to_string(&path)?, SAMPLE);
+
+        let month = PrivacySettings {
+            recent_files_max_age: Some(Duration::from_secs(30 * 24 * 60 * 60)),
+            ..PrivacySettings::default()
+        };
+        let write_options = privacy_write_options(&month);
+        record_update_path(&path, [bookmark()?], &options, write_options)?;
+        let recently_used = parse_path(&path)?;
+        assert_eq!(recently_used.bookmarks.len(), 1);
+        assert_eq!(
+            recently_used.bookmarks[0].href,
+            "file:///home/user/draft.md"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_preview_update() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join("recently-used.xbel");
+        fs::write(&path, SAMPLE)?;
+        let file = temp_dir.path().join("draft.md");
+        fs::write(&file, b"# Draft")?;
+
+        let clock = Utc.with_ymd_and_hms(2024, 9, 10, 9, 0, 0).unwrap();
+        let options = UpdateOptions {
+            clock: Some(clock),
+            ..UpdateOptions::default()
+        };
+        let preview = preview_update_path(
+            &path,
+            &file,
+            "org.test".to_owned(),
+            "'test %u'".to_owned(),
+            &options,
+        )?;
+        assert_eq!(preview.recently_used.bookmarks.len(), 2);
+        let previewed = &preview.recently_used.bookmarks[1];
+        assert_eq!(previewed.href, Url::from_file_path(&file).unwrap().as_str());
+        assert_eq!(previewed.visited_time(), Some(clock));
+        let metadata = previewed.info.as_ref().unwrap().freedesktop().unwrap();
+        assert_eq!(
+            metadata.mime_type.as_ref().unwrap().mime_type,
+            "text/markdown"
+        );
+        assert_eq!(metadata.applications.applications[0].name, "org.test");
+        assert_eq!(metadata.applications.applications[0].count, 1);
+        assert_eq!(
+            preview.recently_used.bookmarks[0],
+            parse_reader(SAMPLE.as_bytes())?.bookmarks[0]
+        );
+        assert_eq!(fs::read_to_string(&path)?, SAMPLE);
+
+        // The preview is exactly what the update writes
+        let bookmark = local_bookmark(
+            &file,
+            "org.test".to_owned(),
+            "'test %u'".to_owned(),
+            &options,
+        )?;
+        let write_options = update_write_options().unwrap_or_default();
+        record_all(&path, [bookmark], options.placement, &write_options)?;
+        assert_eq!(fs::read_to_string(&path)?, preview.document);
+        fs::write(&path, SAMPLE)?;
+
+        let excluded = UpdateOptions {
+            exclude: ExcludeRules::new().glob("**/*.md"),
+            ..UpdateOptions::default()
+        };
+        let preview = preview_update_path(
+            &path,
+            &file,
+            "org.test".to_owned(),
+            "'test %u'".to_owned(),
+            &excluded,
+        )?;
+        assert_eq!(preview.recently_used.bookmarks.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_dedup() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join("recently-used.xbel");
+        let day = |day| Utc.with_ymd_and_hms(2024, 9, day, 12, 0, 0).unwrap();
+
+        let encoded = Bookmark::builder("file:///home/user/My%20Notes.txt")
+            .added(day(2))
+            .modified(day(5))
+            .visited(day(3))
+            .app("org.gnome.TextEditor", "'gnome-text-editor %u'")
+            .group("Work")
+            .build()?;
+        let mut decoded = Bookmark::builder("file:///home/user/My Notes.txt")
+            .added(day(1))
+            .modified(day(4))
+            .visited(day(6))
+            .app("org.gnome.TextEditor", "'gnome-text-editor %u'")
+            .app("org.gnome.Nautilus", "'nautilus %u'")
+            .build()?;
+        decoded
+            .application_mut("org.gnome.TextEditor")
+            .unwrap()
+            .count = 2;
+
+        let recently_used = RecentlyUsed {
+            bookmarks: vec![
+                encoded.clone(),
+                Bookmark::builder("/home/user/other.txt")
+                    .app("org.test", "'test %u'")
+                    .build()?,
+                decoded,
+            ],
+            ..RecentlyUsed::default()
+        };
+        let mut deduped = recently_used.clone();
+        assert_eq!(deduped.dedup(), 1);
+        let merged = &deduped.bookmarks[0];
+        assert_eq!(merged.href, encoded.href);
+        assert_eq!(merged.added_time(), Some(day(1)));
+        assert_eq!(merged.modified_time(), Some(day(5)));
+        assert_eq!(merged.visited_time(), Some(day(6)));
+
+        recently_used.save_to(&path)?;
+        let bookmark = Bookmark::builder("/home/user/My Notes.txt")
+            .app("org.test", "'test %u'")
+            .build()?;
+        record(&path, bookmark)?;
+        let recently_used = parse_path(&path)?;
+        assert_eq!(recently_used.bookmarks.len(), 2);
+
+        let merged = &recently_used.bookmarks[0];
+        assert_eq!(merged.href, encoded.href);
+        let metadata = merged.info.as_ref().unwrap().freedesktop().unwrap();
+        assert!(metadata.has_group("Work"));
+        let counts: Vec<_> = metadata
+            .applications
+            .applications
+            .iter()
+            .map(|app| (app.name.as_str(), app.count))
+            .collect();
+        assert_eq!(
+            counts,
+            [
+                ("org.gnome.TextEditor", 3),
+                ("org.gnome.Nautilus", 1),
+                ("org.test", 1)
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_update_keeps_distinct_non_utf8_paths() -> Result<(), Box<dyn std::error::Error>> {
+        use std::os::unix::ffi::OsStrExt;
+
+        let temp_dir = tempdir()?;
+        let dir = temp_dir.path().canonicalize()?;
+        let path = dir.join("recently-used.xbel");
+        let ff = dir.join(OsStr::from_bytes(b"\xFF.txt"));
+        let fe = dir.join(OsStr::from_bytes(b"\xFE.txt"));
+        for file in [&ff, &fe] {
+            fs::write(file, b"text")?;
+            let bookmark = Bookmark::builder(file)
+                .app("org.test", "'test %u'")
+                .build()?;
+            record(&path, bookmark)?;
+        }
+        let bookmark = Bookmark::builder(&ff)
+            .app("org.test", "'test %u'")
+            .build()?;
+        record(&path, bookmark)?;
+
+        let counts = |recently_used: &RecentlyUsed| -> Vec<(String, u32)> {
+            recently_used
+                .bookmarks
+                .iter()
+                .map(|bookmark| {
+                    let metadata = bookmark.info.as_ref().unwrap().freedesktop().unwrap();
+                    (
+                        bookmark.href.clone(),
+                        metadata.applications.applications[0].count,
+                    )
+                })
+                .collect()
+        };
+        let recently_used = parse_path(&path)?;
+        assert_eq!(recently_used.bookmarks.len(), 2);
+        assert!(recently_used.bookmarks[0].href.ends_with("/%FF.txt"));
+        assert!(recently_used.bookmarks[1].href.ends_with("/%FE.txt"));
+        assert_eq!(counts(&recently_used)[0].1, 2);
+        assert_eq!(counts(&recently_used)[1].1, 1);
+
+        let preview = preview_update_path(
+            &path,
+            &fe,
+            "org.test".to_owned(),
+            "'test %u'".to_owned(),
+            &UpdateOptions::default(),
+        )?;
+        let counts = counts(&preview.recently_used);
+        assert_eq!(counts.len(), 2);
+        assert!(counts[0].0.ends_with("/%FF.txt"));
+        assert!(counts[1].0.ends_with("/%FE.txt"));
+        assert_eq!((counts[0].1, counts[1].1), (2, 2));
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_symlinks() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let real = temp_dir.path().canonicalize()?.join("real.txt");
+        let link = temp_dir.path().join("link.txt");
+        fs::write(&real, b"text")?;
+        std::os::unix::fs::symlink(&real, &link)?;
+
+        let given = local_bookmark(
+            &link,
+            "org.test".into(),
+            "test".into(),
+            &UpdateOptions::default(),
+        )?;
+        assert_eq!(given.href, path_to_href(&link).unwrap());
+
+        let options = UpdateOptions {
+            resolve_symlinks: true,
+            ..UpdateOptions::default()
+        };
+        let resolved = local_bookmark(&link, "org.test".into(), "test".into(), &options)?;
+        assert_eq!(resolved.href, path_to_href(&real).unwrap());
+
+        let options = UpdateOptions {
+            exclude: ExcludeRules::new().glob("**/real.txt"),
+            ..options
+        };
+        assert!(options.excludes(&link));
+        assert!(!UpdateOptions::default().excludes(&link));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_relative_path() -> Result<(), Box<dyn std::error::Error>> {
+        let manifest = std::env::current_dir()?.join("Cargo.toml");
+        let expected = path_to_href(&manifest);
+        assert!(expected.is_some());
+        assert_eq!(absolute_path(Path::new("./src/../Cargo.toml"))?, manifest);
+
+        let bookmark = local_bookmark(
+            Path::new("./Cargo.toml"),
+            "org.test".into(),
+            "test".into(),
+            &UpdateOptions::default(),
+        )?;
+        assert_eq!(Some(bookmark.href), expected);
+
+        let options = UpdateOptions {
+            exclude: ExcludeRules::new().glob("**/Cargo.toml"),
+            ..UpdateOptions::default()
+        };
+        assert!(options.excludes(Path::new("Cargo.toml")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_without_info() -> Result<(), Box<dyn std::error::Error>> {
+        let mut bare = Bookmark::builder("/home/user/notes.md")
+            .app("org.test", "'test %u'")
+            .build()?;
+        bare.info = None;
+        let mut other_owner = Bookmark::builder("/home/user/todo.md")
+            .app("org.test", "'test %u'")
+            .build()?;
+        other_owner.info.as_mut().unwrap().metadata[0].owner = "http://example.org".into();
+        let mut recently_used = RecentlyUsed {
+            bookmarks: vec![bare, other_owner],
+            ..RecentlyUsed::default()
+        };
+
+        for href in ["/home/user/notes.md", "/home/user/todo.md"] {
+            let bookmark = Bookmark::builder(href)
+                .app("org.test", "'test %u'")
+                .group("Work")
+                .build()?;
+            merge(&mut recently_used, bookmark, Placement::default());
+        }
+
+        assert_eq!(recently_used.bookmarks.len(), 2);
+        for bookmark in &recently_used.bookmarks {
+            let metadata = bookmark.info.as_ref().unwrap().freedesktop().unwrap();
+            assert!(metadata.has_group("Work"));
+            assert_eq!(metadata.applications.applications[0].name, "org.test");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_transaction() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join("recently-used.xbel");
+        fs::write(&path, SAMPLE)?;
+        let store = RecentlyUsedStore::with_path(&path);
+
+        let removed = store.transaction(|txn| {
+            txn.add(
+                Bookmark::builder("/home/user/report.odt")
+                    .app("org.test", "'test %u'")
+                    .build()?,
+            );
+            assert!(txn.set_groups("/home/user/report.odt", ["Work", "Work"])?);
+            assert!(!txn.set_groups("/home/user/missing.odt", ["Work"])?);
+            txn.remove("/home/user/notes.md")
+        })?;
+        assert!(removed);
+
+        let recently_used = parse_path(&path)?;
+        assert_eq!(recently_used.bookmarks.len(), 1);
+        let metadata = recently_used.bookmarks[0].info.as_ref().unwrap();
+        assert_eq!(metadata.freedesktop().unwrap().groups, ["Work"]);
+
+        let before = fs::read_to_string(&path)?;
+        let result = store.transaction(|txn| {
+            txn.list_mut().bookmarks.clear();
+            txn.remove("relative.odt")
+        });
+        assert!(matches!(result, Err(Error::Path)));
+        assert_eq!(fs::read_to_string(&path)?, before);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_options() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join("recently-used.xbel");
+        let file = temp_dir.path().join("Makefile");
+        fs::write(&path, SAMPLE)?;
+        fs::write(&file, b"all:")?;
+
+        let clock = Utc.with_ymd_and_hms(2024, 9, 10, 9, 0, 0).unwrap();
+        let options = UpdateOptions {
+            owner: Some("http://example.org".to_owned()),
+            mime_override: Some("text/x-makefile".to_owned()),
+            groups: vec!["Build".to_owned()],
+            private: true,
+            clock: Some(clock),
+            ..UpdateOptions::default()
+        };
+        let bookmark = local_bookmark(&file, "org.test".into(), "test".into(), &options)?;
+        assert_eq!(bookmark.visited_time(), Some(clock));
+        let metadata = &bookmark.info.as_ref().unwrap().metadata[0];
+        assert_eq!(metadata.owner, "http://example.org");
+        assert_eq!(
+            metadata.mime_type.as_ref().unwrap().mime_type,
+            "text/x-makefile"
+        );
+        assert_eq!(metadata.groups, ["Build"]);
+        assert!(metadata.private);
+        assert_eq!(
+            metadata.applications.applications[0].modified_time(),
+            bookmark.modified_time()
+        );
+
+        let options = UpdateOptions::default();
+        let directory =
+            local_bookmark(temp_dir.path(), "org.test".into(), "test".into(), &options)?;
+        let metadata = &directory.info.as_ref().unwrap().metadata[0];
+        assert_eq!(
+            metadata.mime_type.as_ref().unwrap().mime_type,
+            "inode/directory"
+        );
+
+        let options = UpdateOptions {
+            is_dir: true,
+            ..UpdateOptions::default()
+        };
+        let share = Bookmark::builder_from_url(&Url::parse("smb://server/share/")?);
+        let metadata = &options
+            .apply(share)
+            .app("org.test", "'test %u'")
+            .build()?
+            .info
+            .unwrap()
+            .metadata[0];
+        assert_eq!(
+            metadata.mime_type.as_ref().unwrap().mime_type,
+            "inode/directory"
+        );
+
+        let notes = Bookmark::builder("/home/user/notes.md")
+            .app("org.gnome.TextEditor", "gnome-text-editor %u")
+            .group("Work")
+            .private(true)
+            .build()?;
+        record(&path, notes)?;
+        let recently_used = parse_path(&path)?;
+        let metadata = &recently_used.bookmarks[0].info.as_ref().unwrap().metadata[0];
+        assert_eq!(metadata.groups, ["Work"]);
+        assert!(metadata.private);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "sniff")]
+    #[test]
+    fn test_sniff() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let files: &[(&str, &[u8], &str)] = &[
+            ("Makefile", b"all:\n", "text/x-makefile"),
+            (
+                "build",
+                b"#!/usr/bin/env -S python3\nprint()\n",
+                "text/x-python3",
+            ),
+            ("run", b"#!/bin/sh\nexit\n", "application/x-shellscript"),
+            ("README", "caf\u{e9}\n".as_bytes(), "text/plain"),
+            ("image", b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR", "image/png"),
+            ("blob", b"\0\x01\x02", "application/octet-stream"),
+            ("notes.md", b"\0", "text/markdown"),
+        ];
+
+        for (name, contents, expected) in files {
+            let path = temp_dir.path().join(name);
+            fs::write(&path, contents)?;
+            let options = UpdateOptions::default();
+            let bookmark = local_bookmark(&path, "org.test".into(), "test".into(), &options)?;
+            let metadata = &bookmark.info.as_ref().unwrap().metadata[0];
+            assert_eq!(
+                metadata.mime_type.as_ref().unwrap().mime_type,
+                *expected,
+                "{name}"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_mime_detector() -> Result<(), Box<dyn std::error::Error>> {
+        #[derive(Debug)]
+        struct Known;
+
+        impl MimeDetector for Known {
+            fn detect(&self, path: &Path) -> Option<String> {
+                match path.file_name()?.to_str()? {
+                    "report" => Some("application/vnd.oasis.opendocument.text".to_owned()),
+                    "broken" => Some("not a type".to_owned()),
+                    _ => None,
+                }
+            }
+        }
+
+        let temp_dir = tempdir()?;
+        let options = UpdateOptions {
+            mime_detector: Arc::new(Known),
+            ..UpdateOptions::default()
+        };
+        let mime_type = |name: &str| -> Result<String, Box<dyn std::error::Error>> {
+            let path = temp_dir.path().join(name);
+            fs::write(&path, b"\0")?;
+            let bookmark = local_bookmark(&path, "org.test".into(), "test".into(), &options)?;
+            Ok(bookmark.info.unwrap().metadata[0]
+                .mime_type
+                .clone()
+                .unwrap()
+                .mime_type)
+        };
+
+        assert_eq!(
+            mime_type("report")?,
+            "application/vnd.oasis.opendocument.text"
+        );
+        assert_eq!(mime_type("notes.md")?, "application/octet-stream");
+        assert_eq!(mime_type("broken")?, "application/octet-stream");
+        assert_eq!(
+            DefaultMimeDetector.detect(Path::new("notes.md")).as_deref(),
+            Some("text/markdown")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_touch_visited() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join("recently-used.xbel");
+        fs::write(&path, SAMPLE)?;
+
+        touch_visited_path(
+            &path,
+            "/home/user/notes.md",
+            "org.gnome.TextEditor",
+            &WriteOptions::default(),
+        )?;
+        touch_visited_path(
+            &path,
+            "/home/user/notes.md",
+            "org.gnome.Nautilus",
+            &WriteOptions::default(),
+        )?;
+        let recently_used = parse_path(&path)?;
+        let bookmark = &recently_used.bookmarks[0];
+        assert!(
+            bookmark.visited_time().unwrap() > Utc.with_ymd_and_hms(2024, 9, 4, 0, 0, 0).unwrap()
+        );
+        assert_eq!(bookmark.added, "2024-09-01T10:00:00.000000Z");
+        assert_eq!(
+            bookmark.modified.as_deref(),
+            Some("2024-09-02T11:00:00.000000Z")
+        );
+        let apps = &bookmark.info.as_ref().unwrap().metadata[0]
+            .applications
+            .applications;
+        assert_eq!(apps.len(), 1);
+        assert_eq!(apps[0].count, 4);
+
+        assert!(matches!(
+            touch_visited_path(
+                &path,
+                "/home/user/other.md",
+                "org.gnome.TextEditor",
+                &WriteOptions::default()
+            ),
+            Err(Error::NotFound)
+        ));
+        assert_eq!(parse_path(&path)?.bookmarks.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_record_access() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join("recently-used.xbel");
+        fs::write(&path, SAMPLE)?;
+
+        record_access_path(
+            &path,
+            "file:///home/user/notes.md",
+            "org.gnome.TextEditor",
+            &WriteOptions::default(),
+        )?;
+        let recently_used = parse_path(&path)?;
+        let original = parse_reader(SAMPLE.as_bytes())?;
+        let (bookmark, before) = (&recently_used.bookmarks[0], &original.bookmarks[0]);
+        assert_eq!(bookmark.modified, before.modified);
+        assert_eq!(bookmark.visited, before.visited);
+        let app = &bookmark.info.as_ref().unwrap().metadata[0]
+            .applications
+            .applications[0];
+        assert_eq!(app.count, 4);
+        assert!(app.modified_time() > Utc.with_ymd_and_hms(2024, 9, 4, 0, 0, 0).single());
+
+        assert!(matches!(
+            record_access_path(
+                &path,
+                "/home/user/notes.md",
+                "org.gnome.Nautilus",
+                &WriteOptions::default()
+            ),
+            Err(Error::NotFound)
+        ));
+        assert!(matches!(
+            record_access_path(
+                &path,
+                "/home/user/other.md",
+                "org.gnome.TextEditor",
+                &WriteOptions::default()
+            ),
+            Err(Error::NotFound)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_record_creates_list() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join(".local/share/recently-used.xbel");
+        record(
+            &path,
+            Bookmark::builder("/home/user/notes.md")
+                .app("org.test", "'test %u'")
+                .build()?,
+        )?;
+
+        let content = fs::read_to_string(&path)?;
+        assert!(content.contains(BOOKMARK_NAMESPACE));
+        assert!(content.contains(MIME_NAMESPACE));
+        assert_eq!(parse_path(&path)?.bookmarks.len(), 1);
+
+        fs::write(&path, b"")?;
+        record(
+            &path,
+            Bookmark::builder("/home/user/notes.md")
+                .app("org.test", "'test %u'")
+                .build()?,
+        )?;
+        assert_eq!(parse_path(&path)?.bookmarks.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_recenty_used() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let temp_file_path = temp_dir.path().join("test_file.txt");
+        let recently_used_path = dir().ok_or(Error::DoesNotExist)?;
+
+        fs::write(&temp_file_path, b"Test content")?;
+
+        if !recently_used_path.exists() {
+            RecentlyUsed::default().save_to(&recently_used_path)?;
+        }
+
+        update_recently_used(
+            &temp_file_path,
+            String::from("org.test"),
+            String::from("test"),
+            &UpdateOptions::default(),
+        )?;
+
+        // check new file name is in recents
+        let content = fs::read_to_string(recently_used_path)?;
+        assert!(content.contains("test_file.txt"));
+
+        let deserialized = parse_file()?;
+
+        assert!(!deserialized.bookmarks.is_empty());
+
+        let bookmark = deserialized
+            .bookmarks
+            .iter()
+            .find(|el| el.href.contains("test_file"));
+
+        assert!(bookmark.is_some());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_path() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join("recently-used.xbel");
+        fs::write(&path, SAMPLE)?;
+
+        let recently_used = parse_path(&path)?;
+        assert_eq!(recently_used.bookmarks.len(), 1);
+
+        assert!(matches!(
+            parse_path(temp_dir.path().join("missing.xbel")),
+            Err(Error::Read(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_lenient() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join("recently-used.xbel");
+        let broken = SAMPLE.replace(
+            "  <bookmark href",
+            "  <bookmark href=\"file:///broken&bogus;\" added=\"\" modified=\"\" visited=\"\"/>\n  <bookmark href",
+        );
+        fs::write(&path, &broken)?;
+
+        assert!(parse_path(&path).is_err());
+
+        let (recently_used, warnings) = parse_path_lenient(&path)?;
+        assert_eq!(recently_used.bookmarks.len(), 1);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(&broken[warnings[0].offset as usize..][..9], "<bookmark");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_or_default() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join("recently-used.xbel");
+
+        assert!(parse_path_or_default(&path)?.bookmarks.is_empty());
+
+        fs::write(&path, b"")?;
+        let recently_used = parse_path_or_default(&path)?;
+        assert!(recently_used.bookmarks.is_empty());
+        assert_eq!(recently_used.xmlns_bookmark, BOOKMARK_NAMESPACE);
+
+        let options = ParseOptions {
+            missing_as_empty: true,
+            ..ParseOptions::default()
+        };
+        assert!(parse_path_with(&path, &options)?.bookmarks.is_empty());
+        assert!(parse_path_with(&path, &ParseOptions::default()).is_err());
+        fs::remove_file(&path)?;
+        assert!(parse_path_with(&path, &options)?.bookmarks.is_empty());
+        assert!(matches!(
+            parse_path_with(&path, &ParseOptions::default()),
+            Err(Error::Read(_))
+        ));
+        fs::write(&path, SAMPLE)?;
+        assert_eq!(parse_path_with(&path, &options)?.bookmarks.len(), 1);
+
+        // The list in the default location belongs to the user running the tests, and is
+        // only known to be empty if it doesn't exist.
+        if !dir().is_some_and(|path| path.exists()) {
+            assert!(parse_file_with(&options)?.bookmarks.is_empty());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bookmark_iter() -> Result<(), Box<dyn std::error::Error>> {
+        let mut iter = BookmarkIter::new(SAMPLE.as_bytes())?;
+        assert_eq!(iter.next().unwrap()?.href, "file:///home/user/notes.md");
+        assert!(iter.next().is_none());
+
+        let mut iter = BookmarkIter::new(&SAMPLE.as_bytes()[..SAMPLE.len() / 2])?;
+        assert!(matches!(iter.next(), Some(Err(Error::Deserialization(_)))));
+        assert!(iter.next().is_none());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_parse_borrowed() -> Result<(), Box<dyn std::error::Error>> {
+        use std::borrow::Cow;
+
+        let recently_used = parse_borrowed(SAMPLE)?;
+        let bookmark = &recently_used.bookmarks[0];
+        assert!(matches!(bookmark.href, Cow::Borrowed(_)));
+
+        let app = &bookmark.info.as_ref().unwrap().metadata[0]
+            .applications
+            .applications[0];
+        assert_eq!(app.exec, "'gnome-text-editor %u'");
+
+        let owned = recently_used.into_owned();
+        assert_eq!(owned.bookmarks[0].href, "file:///home/user/notes.md");
+
+        // Errors from serde are reported with the same variants as those of the reader.
+        let truncated = &SAMPLE[..SAMPLE.find("<bookmark ").unwrap()];
+        assert!(matches!(
+            parse_borrowed(truncated),
+            Err(Error::Deserialization(
+                DeError::InvalidXml(_) | DeError::UnexpectedEof
+            ))
+        ));
+        let missing = SAMPLE.replace("href=\"file:///home/user/notes.md\"", "");
+        assert!(matches!(
+            parse_borrowed(&missing),
+            Err(Error::Deserialization(DeError::Custom(_)))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_namespace_prefixes() -> Result<(), Box<dyn std::error::Error>> {
+        let renamed = SAMPLE
+            .replace("xmlns:bookmark=", "xmlns:bm=")
+            .replace("bookmark:", "bm:")
+            .replace("xmlns:mime=", "xmlns=")
+            .replace("mime:", "")
+            .replace(
+                "<bm:applications>",
+                "<applications xmlns=\"urn:other\"/><bm:applications>",
+            );
+
+        let recently_used = parse_reader(renamed.as_bytes())?;
+        let info = recently_used.bookmarks[0].info.as_ref().unwrap();
+        let metadata = info.freedesktop().unwrap();
+        assert_eq!(
+            metadata.mime_type.as_ref().unwrap().mime_type,
+            "text/markdown"
+        );
+        assert_eq!(metadata.applications.applications.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_escaped_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+        let mut recently_used = parse_reader(SAMPLE.as_bytes())?;
+        let href = "file:///home/user/Tom & Jerry's <\"cartoons\">/café\tnotes.md";
+        let exec = "'editor' \"%u\" &";
+        recently_used.bookmarks[0].href = href.to_owned();
+        recently_used.bookmarks[0]
+            .info
+            .as_mut()
+            .unwrap()
+            .freedesktop_mut()
+            .unwrap()
+            .applications
+            .applications[0]
+            .exec = exec.to_owned();
+        let title = "</title><xbel> & ]]>";
+        recently_used.title = Some(title.to_owned());
+
+        let written = custom_write(&recently_used)?;
+        assert!(written.contains("Tom &amp; Jerry&apos;s &lt;&quot;cartoons&quot;&gt;"));
+        assert!(!written.contains(title));
+
+        let parsed = parse_reader(written.as_bytes())?;
+        assert_eq!(parsed.title.as_deref(), Some(title));
+        let bookmark = &parsed.bookmarks[0];
+        assert_eq!(bookmark.href, href);
+        assert_eq!(
+            bookmark
+                .info
+                .as_ref()
+                .unwrap()
+                .freedesktop()
+                .unwrap()
+                .applications
+                .applications[0]
+                .exec,
+            exec
+        );
+
+        let referenced = SAMPLE.replace("notes.md", "n&#111;tes&#x2e;md");
+        let parsed = parse_reader(referenced.as_bytes())?;
+        assert_eq!(parsed.bookmarks[0].href, "file:///home/user/notes.md");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_prolog_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+        let doctype = r#"<!DOCTYPE xbel PUBLIC "+//IDN python.org//DTD XML Bookmark Exchange Language 1.0//EN//XML" "http://www.python.org/topics/xml/dtds/xbel-1.0.dtd">"#;
+        let input = SAMPLE.replacen("?>\n", &format!("?>\n{}\n", doctype), 1);
+
+        let recently_used = parse_reader(input.as_bytes())?;
+        assert_eq!(
+            recently_used.prolog.declaration.as_deref(),
+            Some(r#"<?xml version="1.0" encoding="UTF-8"?>"#)
+        );
+        assert_eq!(recently_used.prolog.doctype.as_deref(), Some(doctype));
+
+        let written = custom_write(&recently_used)?;
+        assert_eq!(
+            parse_reader(written.as_bytes())?.prolog,
+            recently_used.prolog
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_encodings() -> Result<(), Box<dyn std::error::Error>> {
+        let with_bom = [&[0xEF, 0xBB, 0xBF], SAMPLE.as_bytes()].concat();
+        assert_eq!(parse_reader(with_bom.as_slice())?.bookmarks.len(), 1);
+
+        let utf16: Vec<u8> = "\u{feff}"
+            .chars()
+            .chain(SAMPLE.replace("UTF-8", "UTF-16").chars())
+            .flat_map(|ch| {
+                let mut units = [0; 2];
+                ch.encode_utf16(&mut units)
+                    .iter()
+                    .flat_map(|unit| unit.to_le_bytes())
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        let recently_used = parse_reader(utf16.as_slice())?;
+        assert_eq!(recently_used.bookmarks.len(), 1);
+        assert_eq!(
+            recently_used.prolog.declaration.as_deref(),
+            Some(r#"<?xml version="1.0" encoding="UTF-8"?>"#)
+        );
+
+        let latin1: Vec<u8> = SAMPLE
+            .replace("UTF-8", "ISO-8859-1")
+            .replace("notes.md", "caf\u{e9}.md")
+            .chars()
+            .map(|ch| ch as u8)
+            .collect();
+        let recently_used = parse_reader(latin1.as_slice())?;
+        assert_eq!(
+            recently_used.bookmarks[0].href,
+            "file:///home/user/caf\u{e9}.md"
+        );
+
+        let unsupported = SAMPLE.replace("UTF-8", "Shift_JIS");
+        assert!(matches!(
+            parse_reader(unsupported.as_bytes()),
+            Err(Error::UnsupportedEncoding(label)) if label == "Shift_JIS"
+        ));
+
+        Ok(())
+    }
+
+    #[cfg(feature = "spans")]
+    #[test]
+    fn test_bookmark_spans() -> Result<(), Box<dyn std::error::Error>> {
+        let recently_used = parse_reader(SAMPLE.as_bytes())?;
+        let span = recently_used.bookmarks[0].span.unwrap();
+
+        assert_eq!(span.line, 6);
+        assert!(SAMPLE[span.offset as usize..].starts_with("<bookmark "));
+        assert!(SAMPLE[..span.end as usize].ends_with("</bookmark>"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_strict() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join("recently-used.xbel");
+        fs::write(&path, SAMPLE)?;
+        assert_eq!(parse_path_strict(&path)?.bookmarks.len(), 1);
+
+        let invalid = SAMPLE.replace("<xbel version=\"1.0\"", "<xbel").replace(
+            "added=\"2024-09-01T10:00:00.000000Z\"",
+            "added=\"yesterday\"",
+        );
+        fs::write(&path, invalid)?;
+
+        match parse_path_strict(&path) {
+            Err(Error::Violations(violations)) => {
+                assert_eq!(violations.len(), 2);
+                assert_eq!(violations[0], Violation::MissingVersion);
+                assert!(matches!(
+                    violations[1],
+                    Violation::InvalidTimestamp {
+                        attribute: "added",
+                        ..
+                    }
+                ));
+            }
+            other => panic!("expected violations, got {:?}", other),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_recent() -> Result<(), Box<dyn std::error::Error>> {
+        let bookmarks = (1..=5)
+            .map(|day| {
+                format!(
+                    r#"<bookmark href="file:///{day}" added="" modified="" visited="2024-09-0{day}T00:00:00Z"/>"#
+                )
+            })
+            .collect::<Vec<_>>();
+        let document = format!(
+            r#"<xbel version="1.0">{}{}{}{}{}</xbel>"#,
+            bookmarks[2], bookmarks[4], bookmarks[0], bookmarks[3], bookmarks[1]
+        );
+
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join("recently-used.xbel");
+        fs::write(&path, document)?;
+
+        let recent = parse_path_recent(&path, 3)?;
+        let hrefs: Vec<_> = recent.bookmarks.iter().map(|b| b.href.as_str()).collect();
+        assert_eq!(hrefs, ["file:///5", "file:///4", "file:///3"]);
+
+        let all = parse_path_recent(&path, usize::MAX)?;
+        assert_eq!(all.bookmarks.len(), 5);
+        assert_eq!(all.bookmarks[0].href, "file:///5");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_xbel_document() -> Result<(), Box<dyn std::error::Error>> {
+        let document = r#"<?xml version="1.0"?>
+<xbel version="1.0">
+  <title>Browser &amp; friends</title>
+  <folder id="f1" folded="no">
+    <title>Rust</title>
+    <bookmark href="https://www.rust-lang.org/" id="b1">
+      <title>Rust</title>
+      <desc>The Rust language</desc>
+    </bookmark>
+    <separator/>
+    <folder>
+      <bookmark href="https://docs.rs/"/>
+    </folder>
+  </folder>
+  <alias ref="b1"/>
+  <bookmark href="https://system76.com/" added="2024-01-01T00:00:00Z"/>
+</xbel>"#;
+
+        let document = xbel::parse_reader(document.as_bytes())?;
+        assert_eq!(document.title.as_deref(), Some("Browser & friends"));
+        assert_eq!(document.items.len(), 3);
+
+        let xbel::Item::Folder(folder) = &document.items[0] else {
+            panic!("expected a folder");
+        };
+        assert!(!folder.folded);
+        assert_eq!(folder.items.len(), 3);
+
+        let hrefs: Vec<_> = document.bookmarks().map(|b| b.href.as_str()).collect();
+        assert_eq!(
+            hrefs,
+            [
+                "https://www.rust-lang.org/",
+                "https://docs.rs/",
+                "https://system76.com/"
+            ]
+        );
+
+        let written = xbel::write(&document)?;
+        let reparsed = xbel::parse_reader(written.as_bytes())?;
+        assert_eq!(xbel::write(&reparsed)?, written);
+        assert!(written.contains("<folder id=\"f1\" folded=\"no\">"));
+        assert!(written.contains("<separator/>"));
+        assert!(written.contains("<alias ref=\"b1\"/>"));
+        assert!(!written.contains("xmlns:bookmark"));
+
+        let recently_used = RecentlyUsed::from(document);
+        assert_eq!(recently_used.bookmarks.len(), 3);
+        assert_eq!(recently_used.bookmarks[2].added, "2024-01-01T00:00:00Z");
+
+        let written = xbel::write(&xbel::Document::from(parse_reader(SAMPLE.as_bytes())?))?;
+        assert!(written.contains("<bookmark:applications>"));
+        assert_eq!(parse_reader(written.as_bytes())?.bookmarks.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_xbel_document_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+        let document = r#"<?xml version="1.0"?>
+<xbel version="1.0" xmlns:bookmark="http://www.freedesktop.org/standards/desktop-bookmarks" xmlns:mime="http://www.freedesktop.org/standards/shared-mime-info" lang="en">
+  <info>
+    <metadata owner="http://example.com/browser">
+      <bookmark:groups>
+        <bookmark:group>Imported</bookmark:group>
+      </bookmark:groups>
+    </metadata>
+  </info>
+  <folder tag="work">
+    <bookmark href="https://example.com/" modified="2024-02-01T00:00:00Z" rating="5">
+      <note>Read later</note>
+    </bookmark>
+    <note>Projects</note>
+  </folder>
+  <bookmark href="https://example.org/"/>
+  <note>Exported</note>
+</xbel>"#;
+
+        let parsed = xbel::parse_reader(document.as_bytes())?;
+        assert!(parsed.info.is_some());
+        assert_eq!(
+            parsed.unknown.attributes,
+            [("lang".to_owned(), "en".to_owned())]
+        );
+        assert_eq!(parsed.unknown.elements.len(), 1);
+        let xbel::Item::Folder(folder) = &parsed.items[0] else {
+            panic!("expected a folder");
+        };
+        assert_eq!(folder.unknown.elements[0].text, "Projects");
+
+        let written = xbel::write(&parsed)?;
+        let reparsed = xbel::parse_reader(written.as_bytes())?;
+        assert_eq!(
+            reparsed.info.as_ref().unwrap().metadata[0].groups,
+            ["Imported"]
+        );
+        assert_eq!(reparsed.unknown, parsed.unknown);
+        assert_eq!(xbel::write(&reparsed)?, written);
+        assert!(written.contains("<note>Read later</note>"));
+        assert!(written.contains("rating=\"5\""));
+
+        // Bookmarks without an added time are taken as added when they were last used, or now.
+        let before = Utc::now();
+        let recently_used = RecentlyUsed::from(reparsed);
+        assert!(recently_used.info.is_some());
+        assert_eq!(recently_used.unknown, parsed.unknown);
+        assert_eq!(recently_used.bookmarks[0].added, "2024-02-01T00:00:00Z");
+        assert_eq!(recently_used.bookmarks[0].unknown.attributes.len(), 1);
+        assert!(recently_used.bookmarks[1].added_time() >= Some(before));
+
+        let malformed = r#"<xbel version="1.0"><bookmark href="https://example.org/"><info/></bookmark></xbel>"#;
+        assert!(matches!(
+            xbel::parse_reader(malformed.as_bytes()),
+            Err(Error::Deserialization(_))
+        ));
 
-    fn create_empty_recently_used_file(path: &PathBuf) -> Result<(), Error> {
-        let empty_file = RecentlyUsed {
-            bookmarks: vec![],
-            xmlns_mime: String::new(),
-            xmlns_bookmark: String::new(),
-        };
-        let serialized =
-            quick_xml::se::to_string(&empty_file).map_err(|why| Error::Serialization(Some(why)))?;
-        let mut file = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .open(path)
-            .map_err(|_| Error::Update)?;
-        file.write_all(serialized.as_bytes())
-            .map_err(|_| Error::Update)?;
         Ok(())
     }
 }
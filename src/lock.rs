@@ -0,0 +1,87 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::Error;
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+#[cfg(unix)]
+use std::{io, os::fd::AsRawFd, thread, time::Instant};
+
+/// How long [`update_recently_used`](crate::update_recently_used) waits for other processes
+/// to finish updating the file.
+pub const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// An advisory lock on a recently-used.xbel file, held until it is dropped.
+///
+/// Taken around a read-modify-write of the file, so that concurrent updates by other
+/// processes using this crate are serialized rather than overwriting each other. The lock is
+/// taken on a `.lock` file next to it, which stays valid when the file itself is replaced.
+/// Applications which don't take the lock are not kept out.
+///
+/// On platforms other than Unix, no lock is taken.
+#[derive(Debug)]
+pub struct FileLock {
+    #[cfg_attr(not(unix), allow(dead_code))]
+    file: File,
+}
+
+impl FileLock {
+    /// Locks the file at the given location, waiting up to `timeout` for another process to
+    /// release it.
+    pub fn acquire(path: impl AsRef<Path>, timeout: Duration) -> Result<Self, Error> {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(lock_path(path.as_ref()))
+            .map_err(Error::Lock)?;
+
+        #[cfg(unix)]
+        {
+            let deadline = Instant::now() + timeout;
+            loop {
+                // SAFETY: the descriptor is valid for as long as `file` is open.
+                let result =
+                    unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) };
+                if result == 0 {
+                    break;
+                }
+
+                let why = io::Error::last_os_error();
+                match why.kind() {
+                    io::ErrorKind::WouldBlock => (),
+                    io::ErrorKind::Interrupted => continue,
+                    _ => return Err(Error::Lock(why)),
+                }
+
+                let now = Instant::now();
+                if now >= deadline {
+                    return Err(Error::LockTimeout);
+                }
+                thread::sleep((deadline - now).min(Duration::from_millis(10)));
+            }
+        }
+
+        #[cfg(not(unix))]
+        let _ = timeout;
+
+        Ok(Self { file })
+    }
+}
+
+#[cfg(unix)]
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        // SAFETY: the descriptor is valid until `file` is dropped after this.
+        unsafe {
+            libc::flock(self.file.as_raw_fd(), libc::LOCK_UN);
+        }
+    }
+}
+
+fn lock_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".lock");
+    PathBuf::from(name)
+}
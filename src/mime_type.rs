@@ -0,0 +1,68 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{Error, MimeType};
+use mime::Mime;
+use std::fmt::Debug;
+use std::path::Path;
+
+/// Types which shared-mime-info lists as aliases, paired with their canonical names.
+const ALIASES: &[(&str, &str)] = &[
+    ("application/x-gzip", "application/gzip"),
+    ("application/x-pdf", "application/pdf"),
+    ("application/x-zip-compressed", "application/zip"),
+    ("audio/mp3", "audio/mpeg"),
+    ("audio/x-mp3", "audio/mpeg"),
+    ("image/jpg", "image/jpeg"),
+    ("image/pjpeg", "image/jpeg"),
+    ("text/x-csv", "text/csv"),
+    ("text/x-markdown", "text/markdown"),
+];
+
+impl MimeType {
+    /// Validates a MIME type, normalizing its case and any alias to the canonical name, such
+    /// as `text/x-markdown` to `text/markdown`.
+    pub fn new(mime_type: &str) -> Result<Self, Error> {
+        let mime: Mime = mime_type.parse().map_err(Error::Mime)?;
+        let essence = mime.essence_str();
+        let canonical = ALIASES
+            .iter()
+            .find(|(alias, _)| *alias == essence)
+            .map_or(essence, |(_, canonical)| canonical);
+
+        Ok(Self {
+            mime_type: format!("{canonical}{}", &mime.as_ref()[essence.len()..]),
+        })
+    }
+
+    /// The MIME type parsed with the [`mime`] crate, failing if it is malformed.
+    pub fn mime(&self) -> Result<Mime, Error> {
+        self.mime_type.parse().map_err(Error::Mime)
+    }
+}
+
+/// Determines the MIME types of files as they are recorded by
+/// [`update_recently_used`](crate::update_recently_used).
+///
+/// Applications which already know the types of their documents, or resolve them through
+/// GIO, can implement this to record the same types they show.
+pub trait MimeDetector: Debug + Send + Sync {
+    /// The MIME type of the local file at `path`, such as `text/markdown`, or `None` to
+    /// record it as `application/octet-stream`. A type which [`MimeType::new`] rejects is
+    /// recorded as `application/octet-stream` as well.
+    fn detect(&self, path: &Path) -> Option<String>;
+}
+
+/// Guesses MIME types from file extensions, and with the `sniff` feature, from the names and
+/// contents of files without a known extension.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultMimeDetector;
+
+impl MimeDetector for DefaultMimeDetector {
+    fn detect(&self, path: &Path) -> Option<String> {
+        let guessed = crate::mime_from_path(path);
+        #[cfg(feature = "sniff")]
+        let guessed = guessed.or_else(|| crate::sniff::detect(path));
+        guessed
+    }
+}
@@ -0,0 +1,92 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use std::fs::File;
+use std::io::{self, BufRead, Read};
+use std::os::fd::AsRawFd;
+use std::ptr;
+
+/// A read-only memory mapping of a file, read through as a buffered reader.
+///
+/// The mapping is private, but the contents of the file may still change while it is being
+/// read if another process writes to it in place. The recently-used.xbel file is replaced
+/// atomically by well-behaved writers, which leaves an existing mapping untouched.
+pub(crate) struct MappedFile {
+    ptr: *mut libc::c_void,
+    len: usize,
+    pos: usize,
+}
+
+impl MappedFile {
+    pub fn map(file: &File) -> io::Result<Self> {
+        let len = usize::try_from(file.metadata()?.len())
+            .map_err(|why| io::Error::new(io::ErrorKind::InvalidData, why))?;
+
+        // Mapping zero bytes is an error, and there is nothing to map anyway.
+        if len == 0 {
+            return Ok(Self {
+                ptr: ptr::null_mut(),
+                len,
+                pos: 0,
+            });
+        }
+
+        // SAFETY: the file descriptor is valid for the duration of the call, and the result
+        // is checked for failure before it is used.
+        let ptr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                len,
+                libc::PROT_READ,
+                libc::MAP_PRIVATE,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Self { ptr, len, pos: 0 })
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        if self.len == 0 {
+            return &[];
+        }
+
+        // SAFETY: the mapping is `len` bytes long, readable, and lives as long as `self`.
+        unsafe { std::slice::from_raw_parts(self.ptr as *const u8, self.len) }
+    }
+}
+
+impl Drop for MappedFile {
+    fn drop(&mut self) {
+        if self.len != 0 {
+            // SAFETY: the mapping was created by `map` with this address and length.
+            unsafe {
+                libc::munmap(self.ptr, self.len);
+            }
+        }
+    }
+}
+
+impl Read for MappedFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = (&self.as_slice()[self.pos..]).read(buf)?;
+        self.pos += read;
+        Ok(read)
+    }
+}
+
+impl BufRead for MappedFile {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        let pos = self.pos;
+        Ok(&self.as_slice()[pos..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.len);
+    }
+}
@@ -0,0 +1,91 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::FREEDESKTOP_OWNER;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+/// The owner of a metadata block, which is almost always freedesktop.org.
+///
+/// Owners are compared by their URIs, so `Other("http://freedesktop.org")` equals
+/// `Freedesktop`.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "serde", serde(from = "String", into = "String"))]
+pub enum MetadataOwner {
+    /// `http://freedesktop.org`, which owns the block holding the MIME type and applications.
+    Freedesktop,
+    /// Any other owner, by its URI.
+    Other(String),
+}
+
+impl MetadataOwner {
+    /// The URI of the owner, as written in the `owner` attribute.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Freedesktop => FREEDESKTOP_OWNER,
+            Self::Other(owner) => owner,
+        }
+    }
+}
+
+impl From<String> for MetadataOwner {
+    fn from(owner: String) -> Self {
+        match owner.as_str() {
+            FREEDESKTOP_OWNER => Self::Freedesktop,
+            _ => Self::Other(owner),
+        }
+    }
+}
+
+impl From<&str> for MetadataOwner {
+    fn from(owner: &str) -> Self {
+        match owner {
+            FREEDESKTOP_OWNER => Self::Freedesktop,
+            _ => Self::Other(owner.to_owned()),
+        }
+    }
+}
+
+impl From<MetadataOwner> for String {
+    fn from(owner: MetadataOwner) -> Self {
+        match owner {
+            MetadataOwner::Freedesktop => FREEDESKTOP_OWNER.to_owned(),
+            MetadataOwner::Other(owner) => owner,
+        }
+    }
+}
+
+impl PartialEq for MetadataOwner {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for MetadataOwner {}
+
+impl Hash for MetadataOwner {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state);
+    }
+}
+
+impl PartialEq<str> for MetadataOwner {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for MetadataOwner {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl fmt::Display for MetadataOwner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
@@ -0,0 +1,54 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::custom_reader::{Element, XbelReader};
+use crate::{Bookmark, Error, RecentlyUsed};
+use chrono::{DateTime, Utc};
+use std::io::BufRead;
+
+type Visited = Option<DateTime<Utc>>;
+
+fn visited(element: &Element) -> Visited {
+    crate::time::parse(&element.attribute("visited").ok()??)
+}
+
+/// Reads the `n` most recently visited bookmarks of a document, newest first.
+///
+/// Only the start tags of bookmarks are inspected until one is known to be recent enough to
+/// be kept. Bookmarks without a valid visited timestamp are considered the oldest, and among
+/// equally old bookmarks those further down the document are preferred.
+pub(crate) fn read<R: BufRead>(reader: R, n: usize) -> Result<RecentlyUsed, Error> {
+    let mut reader = XbelReader::new(reader)?;
+    let mut recently_used = reader.read_root().map_err(Error::Deserialization)?;
+
+    // Sorted from newest to oldest.
+    let mut newest: Vec<(Visited, Bookmark)> = Vec::new();
+
+    if n > 0 {
+        loop {
+            let mut key = None;
+            let read = reader.next_bookmark_where(|element| {
+                key = visited(element);
+                newest.len() < n || newest.last().is_some_and(|(oldest, _)| key >= *oldest)
+            });
+
+            let Some(read) = read.map_err(Error::Deserialization)? else {
+                break;
+            };
+
+            let bookmark = read.result.map_err(Error::Deserialization)?;
+            if newest.len() == n {
+                newest.pop();
+            }
+
+            let index = newest.partition_point(|(visited, _)| *visited > key);
+            newest.insert(index, (key, bookmark));
+        }
+    }
+
+    recently_used.bookmarks = newest.into_iter().map(|(_, bookmark)| bookmark).collect();
+    reader
+        .finish_root(&mut recently_used)
+        .map_err(Error::Deserialization)?;
+    Ok(recently_used)
+}
@@ -0,0 +1,140 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::custom_reader::XbelReader;
+use crate::DeError;
+use crate::{
+    encoding, Bookmark, Error, ParseWarning, RecentlyUsed, BOOKMARK_NAMESPACE, MIME_NAMESPACE,
+};
+use std::io::{BufRead, Read};
+
+/// Reads every complete bookmark from a document which may be truncated or corrupted.
+///
+/// Bookmarks are read normally up to the first error in the document's markup. From there,
+/// each remaining `<bookmark` start tag is tried on its own, so that bookmarks following a
+/// damaged region are recovered too.
+///
+/// Never fails: everything which had to be skipped is reported as a warning instead. A
+/// document whose encoding cannot be decoded is read as if it were UTF-8.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+pub(crate) fn salvage(bytes: &[u8]) -> (RecentlyUsed, Vec<ParseWarning>) {
+    let mut warnings = Vec::new();
+    let mut utf8 = Vec::new();
+
+    let decoded = encoding::detect(bytes)
+        .and_then(|mut input| input.read_to_end(&mut utf8).map_err(Error::Read));
+    let text = match decoded {
+        Ok(_) => &utf8[..],
+        Err(error) => {
+            warnings.push(ParseWarning {
+                offset: 0,
+                error: DeError::Custom(error.to_string()),
+            });
+            bytes
+        }
+    };
+
+    let mut reader = XbelReader::from_utf8(text);
+    let (mut recently_used, mut resume) = match reader.read_root() {
+        Ok(recently_used) => (recently_used, None),
+        Err(error) => {
+            let offset = reader.error_position();
+            warnings.push(ParseWarning { offset, error });
+            (RecentlyUsed::default(), Some(0))
+        }
+    };
+
+    // The end of the last bookmark read, which an error may be reported before.
+    let mut end = 0;
+
+    while resume.is_none() {
+        match reader.next_bookmark() {
+            Ok(Some(read)) => {
+                end = reader.position();
+                match read.result {
+                    Ok(bookmark) => recently_used.bookmarks.push(bookmark),
+                    Err(error) => warnings.push(ParseWarning {
+                        offset: read.offset,
+                        error,
+                    }),
+                }
+            }
+            Ok(None) => break,
+            Err(error) => {
+                let offset = reader.error_position();
+                warnings.push(ParseWarning { offset, error });
+                resume = Some(offset.max(end) as usize);
+            }
+        }
+    }
+
+    if let Err(error) = reader.finish_root(&mut recently_used) {
+        let offset = reader.root_offset;
+        warnings.push(ParseWarning { offset, error });
+    }
+
+    if let Some(offset) = resume {
+        scan(
+            &text[offset.min(text.len())..],
+            &mut recently_used.bookmarks,
+        );
+    }
+
+    crate::trace::skipped(&warnings);
+    (recently_used, warnings)
+}
+
+/// Parses each bookmark found in `rest` on its own, skipping those which are incomplete.
+fn scan(mut rest: &[u8], bookmarks: &mut Vec<Bookmark>) {
+    let root = fragment_root();
+
+    while let Some(start) = find_bookmark(rest) {
+        rest = &rest[start..];
+
+        match read_first_bookmark(root.as_bytes().chain(rest)) {
+            Some((bookmark, end)) => {
+                bookmarks.push(bookmark);
+                rest = &rest[end - root.len()..];
+            }
+            None => rest = &rest[1..],
+        }
+    }
+}
+
+/// Reads the bookmark at the start of a document, returning it with the offset of its end.
+fn read_first_bookmark(document: impl BufRead) -> Option<(Bookmark, usize)> {
+    let mut reader = XbelReader::from_utf8(document);
+    reader.read_root().ok()?;
+
+    #[allow(unused_mut)]
+    let mut bookmark = reader.next_bookmark().ok()??.result.ok()?;
+    // Offsets within the synthesized document don't describe the original file.
+    #[cfg(feature = "spans")]
+    {
+        bookmark.span = None;
+    }
+
+    Some((bookmark, reader.position() as usize))
+}
+
+/// A root element to prefix a fragment of a document with, so that the bookmarks in it can be
+/// read on their own.
+pub(crate) fn fragment_root() -> String {
+    format!(
+        r#"<xbel xmlns:bookmark="{}" xmlns:mime="{}">"#,
+        BOOKMARK_NAMESPACE, MIME_NAMESPACE
+    )
+}
+
+/// Finds the next `<bookmark` start tag.
+pub(crate) fn find_bookmark(bytes: &[u8]) -> Option<usize> {
+    const TAG: &[u8] = b"<bookmark";
+
+    bytes.windows(TAG.len() + 1).position(|window| {
+        window.starts_with(TAG)
+            && matches!(
+                window[TAG.len()],
+                b'>' | b'/' | b' ' | b'\t' | b'\r' | b'\n'
+            )
+    })
+}
@@ -0,0 +1,60 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{Bookmark, RecentlyUsed, WriteOptions};
+use chrono::Utc;
+use std::borrow::Cow;
+use std::cmp::Reverse;
+
+/// Drops the bookmarks which the options say shouldn't be kept, borrowing the document if
+/// there are none.
+pub(crate) fn apply<'a>(
+    recently_used: &'a RecentlyUsed,
+    options: &WriteOptions,
+) -> Cow<'a, RecentlyUsed> {
+    let cutoff = options
+        .retention
+        .and_then(|age| chrono::Duration::from_std(age).ok())
+        .and_then(|age| Utc::now().checked_sub_signed(age));
+    let expired = |bookmark: &Bookmark| {
+        cutoff.is_some_and(|cutoff| {
+            bookmark
+                .visited_time()
+                .is_some_and(|visited| visited < cutoff)
+        })
+    };
+
+    let bookmarks = &recently_used.bookmarks;
+    let over_limit = options.max_entries.is_some_and(|max| bookmarks.len() > max);
+    if !over_limit && !bookmarks.iter().any(expired) {
+        return Cow::Borrowed(recently_used);
+    }
+
+    let mut recently_used = recently_used.clone();
+    recently_used
+        .bookmarks
+        .retain(|bookmark| !expired(bookmark));
+    if let Some(max) = options.max_entries {
+        keep_newest(&mut recently_used.bookmarks, max);
+    }
+
+    Cow::Owned(recently_used)
+}
+
+/// Keeps the `n` most recently visited bookmarks, in their original order.
+///
+/// As in [`parse_recent`](crate::parse_recent), bookmarks without a valid visited timestamp
+/// are considered the oldest, and among equally old bookmarks those further down the list are
+/// preferred.
+fn keep_newest(bookmarks: &mut Vec<Bookmark>, n: usize) {
+    let mut newest: Vec<usize> = (0..bookmarks.len()).collect();
+    newest.sort_by_cached_key(|&index| Reverse((bookmarks[index].visited_time(), index)));
+
+    let mut keep = vec![false; bookmarks.len()];
+    for &index in newest.iter().take(n) {
+        keep[index] = true;
+    }
+
+    let mut keep = keep.into_iter();
+    bookmarks.retain(|_| keep.next().unwrap_or(false));
+}
@@ -0,0 +1,303 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{Durability, Error};
+use std::collections::hash_map::RandomState;
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::hash::BuildHasher;
+use std::io::{self, BufWriter, Write};
+#[cfg(unix)]
+use std::os::unix::{
+    self,
+    fs::{MetadataExt, OpenOptionsExt},
+};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Replaces the file at `path` with the output of `write`, such that readers see either the
+/// old or the new file in full, and never one that is half-written.
+///
+/// The contents are written to a temporary file in the same directory, which is then renamed
+/// over the original. How much of that is flushed to disk depends on `durability`. Where the
+/// filesystem supports it, the temporary file is only given a name once it has been written,
+/// so that nothing is left behind if the process is killed while writing it.
+pub(crate) fn write_atomic(
+    path: &Path,
+    durability: Durability,
+    write: impl FnOnce(&mut BufWriter<File>) -> Result<(), Error>,
+) -> Result<(), Error> {
+    let mut temp = None;
+    let result = write_temp(path, durability, write, &mut temp);
+
+    if result.is_err() {
+        // Only a file this call created is removed, never one another writer is using.
+        if let Some(temp) = temp {
+            let _ = fs::remove_file(temp);
+        }
+    } else if durability == Durability::Full {
+        sync_parent(path).map_err(Error::Write)?;
+    }
+
+    result
+}
+
+/// Writes the temporary file and renames it over `path`, setting `temp` to its name as soon
+/// as it has one.
+fn write_temp(
+    path: &Path,
+    durability: Durability,
+    write: impl FnOnce(&mut BufWriter<File>) -> Result<(), Error>,
+    temp: &mut Option<PathBuf>,
+) -> Result<(), Error> {
+    let anonymous = create_anonymous(path).map_err(Error::Write)?;
+    let anonymous_file = anonymous.is_some();
+    let file = match anonymous {
+        Some(file) => file,
+        None => {
+            let (name, file) = create_temp(path).map_err(Error::Write)?;
+            *temp = Some(name);
+            file
+        }
+    };
+
+    let mut file = BufWriter::new(file);
+    write(&mut file)?;
+    let file = file
+        .into_inner()
+        .map_err(|why| Error::Write(why.into_error()))?;
+    if durability != Durability::None {
+        file.sync_all().map_err(Error::Write)?;
+    }
+
+    if anonymous_file {
+        *temp = Some(link(&file, path).map_err(Error::Write)?);
+    }
+    match temp {
+        Some(name) => fs::rename(name, path).map_err(Error::Write),
+        None => unreachable!("the temporary file is named once it has been written"),
+    }
+}
+
+/// Creates the temporary file for replacing `original`, with the same permissions and
+/// ownership as it, returning its name along with it.
+fn create_temp(original: &Path) -> io::Result<(PathBuf, File)> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    let (temp, file) = with_unique_name(original, |temp| options.open(temp))?;
+
+    if let Err(why) = copy_permissions(&file, original) {
+        let _ = fs::remove_file(&temp);
+        return Err(why);
+    }
+    Ok((temp, file))
+}
+
+/// Creates an unnamed temporary file for replacing `original` in the same directory, with
+/// the same permissions and ownership as it, or `None` if the filesystem doesn't support
+/// unnamed files.
+#[cfg(target_os = "linux")]
+fn create_anonymous(original: &Path) -> io::Result<Option<File>> {
+    // The file is given a name through its entry in /proc.
+    if !Path::new("/proc/self/fd").is_dir() {
+        return Ok(None);
+    }
+
+    let file = OpenOptions::new()
+        .write(true)
+        .custom_flags(libc::O_TMPFILE)
+        .mode(0o600)
+        .open(parent(original));
+
+    let file = match file {
+        Ok(file) => file,
+        Err(why) if matches!(why.raw_os_error(), Some(libc::EOPNOTSUPP | libc::EISDIR)) => {
+            return Ok(None)
+        }
+        Err(why) => return Err(why),
+    };
+
+    copy_permissions(&file, original)?;
+    Ok(Some(file))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn create_anonymous(_original: &Path) -> io::Result<Option<File>> {
+    Ok(None)
+}
+
+/// Gives a file created by [`create_anonymous`] a unique temporary name next to `original`,
+/// returning the name.
+#[cfg(target_os = "linux")]
+fn link(file: &File, original: &Path) -> io::Result<PathBuf> {
+    use std::ffi::CString;
+    use std::os::fd::AsRawFd;
+    use std::os::unix::ffi::OsStrExt;
+
+    let source = CString::new(format!("/proc/self/fd/{}", file.as_raw_fd()))?;
+    let (temp, ()) = with_unique_name(original, |temp| {
+        let target = CString::new(temp.as_os_str().as_bytes())?;
+        // SAFETY: both paths are valid, NUL-terminated strings.
+        let result = unsafe {
+            libc::linkat(
+                libc::AT_FDCWD,
+                source.as_ptr(),
+                libc::AT_FDCWD,
+                target.as_ptr(),
+                libc::AT_SYMLINK_FOLLOW,
+            )
+        };
+
+        if result == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    })?;
+    Ok(temp)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn link(_file: &File, _original: &Path) -> io::Result<PathBuf> {
+    unreachable!("unnamed files are only created on Linux")
+}
+
+/// Gives a new file the permissions and ownership of the file it replaces. A file which
+/// doesn't exist yet is left readable only by its owner, since the history of recently used
+/// files is private.
+fn copy_permissions(file: &File, original: &Path) -> io::Result<()> {
+    match fs::metadata(original) {
+        Ok(metadata) => {
+            // Only root may give a file away, so another user's file ends up owned by us.
+            #[cfg(unix)]
+            let _ = unix::fs::fchown(file, Some(metadata.uid()), Some(metadata.gid()));
+            file.set_permissions(metadata.permissions())
+        }
+        Err(why) if why.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(why) => Err(why),
+    }
+}
+
+/// The directory containing `path`.
+fn parent(path: &Path) -> &Path {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
+
+/// Flushes the directory containing `path`, so that a file renamed into it persists.
+#[cfg(unix)]
+fn sync_parent(path: &Path) -> io::Result<()> {
+    File::open(parent(path))?.sync_all()
+}
+
+/// Directories can't be opened to be flushed on other platforms.
+#[cfg(not(unix))]
+fn sync_parent(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// Copies the file at `path` to the first of `count` backups, after moving each older backup
+/// along to the next, and removing the oldest.
+///
+/// Does nothing if there is no file yet.
+pub(crate) fn rotate_backups(path: &Path, count: usize) -> io::Result<()> {
+    if count == 0 || !path.exists() {
+        return Ok(());
+    }
+
+    for generation in (1..count).rev() {
+        match fs::rename(
+            backup_path(path, generation - 1),
+            backup_path(path, generation),
+        ) {
+            Err(why) if why.kind() != io::ErrorKind::NotFound => return Err(why),
+            _ => (),
+        }
+    }
+
+    fs::copy(path, backup_path(path, 0)).map(|_| ())
+}
+
+/// The location of a backup kept alongside a file. The latest is `recently-used.xbel.bak`,
+/// followed by `recently-used.xbel.bak.1`, and so on.
+pub(crate) fn backup_path(path: &Path, generation: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".bak");
+    if generation > 0 {
+        name.push(format!(".{generation}"));
+    }
+    PathBuf::from(name)
+}
+
+/// How many names are tried for a temporary file before giving up.
+const TEMP_ATTEMPTS: usize = 16;
+
+/// Calls `create` with temporary names next to `original` until one of them doesn't exist
+/// yet, returning that name and what `create` returned for it.
+///
+/// `create` must fail with [`io::ErrorKind::AlreadyExists`] rather than replace an existing
+/// file, so that concurrent writers never touch each other's temporary files.
+fn with_unique_name<T>(
+    original: &Path,
+    mut create: impl FnMut(&Path) -> io::Result<T>,
+) -> io::Result<(PathBuf, T)> {
+    for _ in 0..TEMP_ATTEMPTS {
+        let temp = temp_path(original)?;
+        match create(&temp) {
+            Ok(created) => return Ok((temp, created)),
+            Err(why) if why.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(why) => return Err(why),
+        }
+    }
+
+    Err(io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        "could not find an unused name for a temporary file",
+    ))
+}
+
+/// A hidden file next to `path`, named after it, the current process, a counter, and a
+/// random number, so that writers in this and other processes pick different names.
+fn temp_path(path: &Path) -> io::Result<PathBuf> {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path does not name a file"))?;
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let random = RandomState::new().hash_one((count, process::id())) as u32;
+
+    let mut name = OsString::from(".");
+    name.push(file_name);
+    name.push(format!(".{}.{count}.{random:08x}.tmp", process::id()));
+    Ok(path.with_file_name(name))
+}
+
+/// Counts the bytes written through it.
+pub(crate) struct Counted<W> {
+    inner: W,
+    pub bytes: u64,
+}
+
+impl<W> Counted<W> {
+    pub fn new(inner: W) -> Self {
+        Self { inner, bytes: 0 }
+    }
+}
+
+impl<W: Write> Write for Counted<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.bytes += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
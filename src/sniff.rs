@@ -0,0 +1,86 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// How much of a file is read to detect its type.
+const SNIFF_LEN: u64 = 8192;
+
+/// Files which are named by convention rather than by extension.
+const NAMES: &[(&str, &str)] = &[
+    ("GNUmakefile", "text/x-makefile"),
+    ("Makefile", "text/x-makefile"),
+    ("makefile", "text/x-makefile"),
+    ("meson.build", "text/x-meson"),
+];
+
+/// Interpreters of scripts, and the types shared-mime-info gives scripts run by them.
+const INTERPRETERS: &[(&str, &str)] = &[
+    ("bash", "application/x-shellscript"),
+    ("dash", "application/x-shellscript"),
+    ("node", "application/javascript"),
+    ("perl", "application/x-perl"),
+    ("python", "text/x-python"),
+    ("python3", "text/x-python3"),
+    ("ruby", "application/x-ruby"),
+    ("sh", "application/x-shellscript"),
+    ("zsh", "application/x-shellscript"),
+];
+
+/// Detects the MIME type of a file whose extension didn't give it away, from its name or its
+/// first few kilobytes: the interpreter of a script, magic numbers of binary formats, or
+/// else whether it is plain text.
+pub(crate) fn detect(path: &Path) -> Option<String> {
+    let name = path.file_name()?.to_str();
+    if let Some((_, mime_type)) = NAMES.iter().find(|(known, _)| Some(*known) == name) {
+        return Some((*mime_type).to_owned());
+    }
+
+    let mut head = Vec::new();
+    File::open(path)
+        .and_then(|file| file.take(SNIFF_LEN).read_to_end(&mut head))
+        .ok()?;
+
+    if let Some(mime_type) = script(&head) {
+        return Some(mime_type.to_owned());
+    }
+    if let Some(kind) = infer::get(&head) {
+        return Some(kind.mime_type().to_owned());
+    }
+    is_text(&head).then(|| "text/plain".to_owned())
+}
+
+/// The type of a script from its `#!` line, such as `#!/usr/bin/env python3`.
+fn script(head: &[u8]) -> Option<&'static str> {
+    let line = head.strip_prefix(b"#!")?.split(|&b| b == b'\n').next()?;
+    let line = std::str::from_utf8(line).ok()?;
+    let mut words = line.split_whitespace();
+    let mut program = words.next()?.rsplit('/').next()?;
+    if program == "env" {
+        program = words.find(|word| !word.starts_with('-'))?;
+    }
+
+    INTERPRETERS
+        .iter()
+        .find(|(interpreter, _)| *interpreter == program)
+        .map(|(_, mime_type)| *mime_type)
+}
+
+/// Whether a file starting with `head` looks like text: UTF-8 without control characters
+/// other than whitespace. The last few bytes may be a character cut off by the read.
+fn is_text(head: &[u8]) -> bool {
+    let text = match std::str::from_utf8(head) {
+        Ok(text) => text,
+        Err(error) if error.error_len().is_none() => {
+            std::str::from_utf8(&head[..error.valid_up_to()]).unwrap_or_default()
+        }
+        Err(_) => return false,
+    };
+
+    !text.is_empty()
+        && !text
+            .chars()
+            .any(|c| c.is_control() && !matches!(c, '\n' | '\r' | '\t' | '\x0c'))
+}
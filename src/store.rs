@@ -0,0 +1,163 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{href, Bookmark, Error, FileLock, Placement, RecentlyUsed, WriteOptions};
+use std::ffi::OsStr;
+use std::path::PathBuf;
+
+/// A recently-used.xbel file which is edited through transactions.
+///
+/// Each transaction holds the file's lock while it runs, so that edits made by other
+/// processes in the meantime aren't lost, and saves its changes with a single atomic write.
+///
+/// ```no_run
+/// use recently_used_xbel::{Bookmark, Error, RecentlyUsedStore};
+///
+/// fn main() -> Result<(), Error> {
+///     let store = RecentlyUsedStore::new()?;
+///
+///     store.transaction(|txn| {
+///         txn.add(
+///             Bookmark::builder("/home/user/report.odt")
+///                 .app("org.gnome.Nautilus", "'nautilus %u'")
+///                 .build()?,
+///         );
+///         txn.remove("/home/user/draft.odt")?;
+///         txn.set_groups("/home/user/report.odt", ["Work"])?;
+///         Ok::<_, Error>(())
+///     })
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct RecentlyUsedStore {
+    path: PathBuf,
+    options: WriteOptions,
+}
+
+impl RecentlyUsedStore {
+    /// The recently-used.xbel file in its default location.
+    pub fn new() -> Result<Self, Error> {
+        crate::dir().map(Self::with_path).ok_or(Error::DoesNotExist)
+    }
+
+    /// A recently-used.xbel file at the given location.
+    pub fn with_path(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            options: WriteOptions::default(),
+        }
+    }
+
+    /// Sets the options the file is written with when a transaction commits.
+    pub fn write_options(mut self, options: WriteOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Runs `edit` on the list, then writes the list back if it succeeded.
+    ///
+    /// If `edit` fails, its changes are discarded and the file is left as it was. A missing
+    /// or empty file is edited as an empty list, and created along with its directory.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(path = %self.path.display()))
+    )]
+    pub fn transaction<T, E>(
+        &self,
+        edit: impl FnOnce(&mut Transaction) -> Result<T, E>,
+    ) -> Result<T, E>
+    where
+        E: From<Error>,
+    {
+        crate::create_parent(&self.path)?;
+        let _lock = FileLock::acquire(&self.path, self.options.lock_timeout)?;
+        let mut transaction = Transaction {
+            recently_used: crate::parse_path_or_default(&self.path)?,
+            changed: false,
+        };
+
+        let result = edit(&mut transaction)?;
+        if transaction.changed {
+            crate::write_path(&self.path, &transaction.recently_used, &self.options)?;
+        }
+        Ok(result)
+    }
+}
+
+/// The edits made to a list by [`RecentlyUsedStore::transaction`].
+///
+/// Locations are given as URLs or absolute paths, and matched to bookmarks by their
+/// normalized hrefs.
+#[derive(Debug)]
+pub struct Transaction {
+    recently_used: RecentlyUsed,
+    changed: bool,
+}
+
+impl Transaction {
+    /// The list as edited so far.
+    pub fn list(&self) -> &RecentlyUsed {
+        &self.recently_used
+    }
+
+    /// The list, to edit it directly.
+    pub fn list_mut(&mut self) -> &mut RecentlyUsed {
+        self.changed = true;
+        &mut self.recently_used
+    }
+
+    /// Adds a bookmark as [`update_recently_used`](crate::update_recently_used) does, or if
+    /// its file is already listed, updates that bookmark with it.
+    pub fn add(&mut self, bookmark: Bookmark) {
+        crate::merge(&mut self.recently_used, bookmark, Placement::default());
+        self.changed = true;
+    }
+
+    /// Removes the bookmark of a file, returning whether it was listed.
+    pub fn remove(&mut self, location: impl AsRef<OsStr>) -> Result<bool, Error> {
+        let href = normalized_href(location.as_ref())?;
+        let bookmarks = &mut self.recently_used.bookmarks;
+        let len = bookmarks.len();
+        bookmarks.retain(|bookmark| bookmark.normalized_href() != href);
+
+        let removed = bookmarks.len() != len;
+        self.changed |= removed;
+        Ok(removed)
+    }
+
+    /// Replaces the groups of the bookmark of a file, returning whether it was listed with
+    /// metadata owned by freedesktop.org to hold them.
+    pub fn set_groups<I, S>(
+        &mut self,
+        location: impl AsRef<OsStr>,
+        groups: I,
+    ) -> Result<bool, Error>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let href = normalized_href(location.as_ref())?;
+        let metadata = self
+            .recently_used
+            .bookmarks
+            .iter_mut()
+            .find(|bookmark| bookmark.normalized_href() == href)
+            .and_then(|bookmark| bookmark.info.as_mut()?.freedesktop_mut());
+
+        let Some(metadata) = metadata else {
+            return Ok(false);
+        };
+
+        metadata.groups.clear();
+        for group in groups {
+            metadata.add_group(group);
+        }
+        self.changed = true;
+        Ok(true)
+    }
+}
+
+fn normalized_href(location: &OsStr) -> Result<String, Error> {
+    let href = crate::location_to_href(location).ok_or(Error::Path)?;
+    Ok(href::normalize(&href))
+}
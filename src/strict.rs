@@ -0,0 +1,98 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::custom_reader::XbelReader;
+use crate::validate;
+use crate::{Bookmark, Error, Info, RecentlyUsed, XBEL_VERSION};
+use chrono::DateTime;
+use std::io::BufRead;
+use url::Url;
+
+/// A way in which a document does not conform to the XBEL 1.0 specification.
+///
+/// Violations found in a bookmark carry the byte offset where that bookmark starts.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum Violation {
+    #[error("the xbel element has no version attribute")]
+    MissingVersion,
+    #[error("xbel version `{version}` is not supported")]
+    UnsupportedVersion { version: String },
+    #[error("bookmark at byte {offset}: `{href}` is not a valid URI")]
+    InvalidHref { offset: u64, href: String },
+    #[error("bookmark at byte {offset}: {attribute} timestamp `{value}` is not RFC 3339")]
+    InvalidTimestamp {
+        offset: u64,
+        attribute: &'static str,
+        value: String,
+    },
+    #[error("bookmark at byte {offset}: no applications are registered")]
+    NoApplications { offset: u64 },
+}
+
+/// Reads a complete document, collecting every violation of the specification.
+///
+/// Per the specification, only the `added` timestamp is required on a bookmark.
+pub(crate) fn read<R: BufRead>(reader: R) -> Result<RecentlyUsed, Error> {
+    let mut reader = XbelReader::new(reader)?;
+    let mut recently_used = reader.read_root().map_err(Error::Deserialization)?;
+    let mut violations = Vec::new();
+
+    match &reader.version {
+        None => violations.push(Violation::MissingVersion),
+        Some(version) if version != XBEL_VERSION => {
+            violations.push(Violation::UnsupportedVersion {
+                version: version.clone(),
+            })
+        }
+        Some(_) => (),
+    }
+
+    while let Some(read) = reader.next_bookmark().map_err(Error::Deserialization)? {
+        let bookmark = read.result.map_err(Error::Deserialization)?;
+        check_bookmark(read.offset, &bookmark, &mut violations);
+        recently_used.bookmarks.push(bookmark);
+    }
+
+    reader
+        .finish_root(&mut recently_used)
+        .map_err(Error::Deserialization)?;
+
+    if violations.is_empty() {
+        Ok(recently_used)
+    } else {
+        Err(Error::Violations(violations))
+    }
+}
+
+fn check_bookmark(offset: u64, bookmark: &Bookmark, violations: &mut Vec<Violation>) {
+    if Url::parse(&bookmark.href).is_err() {
+        violations.push(Violation::InvalidHref {
+            offset,
+            href: bookmark.href.clone(),
+        });
+    }
+
+    for (attribute, value) in validate::timestamps(bookmark) {
+        let Some(value) = value else {
+            continue;
+        };
+
+        if DateTime::parse_from_rfc3339(value).is_err() {
+            violations.push(Violation::InvalidTimestamp {
+                offset,
+                attribute,
+                value: value.clone(),
+            });
+        }
+    }
+
+    let has_applications = bookmark
+        .info
+        .as_ref()
+        .and_then(Info::freedesktop)
+        .is_some_and(|metadata| !metadata.applications.applications.is_empty());
+
+    if !has_applications {
+        violations.push(Violation::NoApplications { offset });
+    }
+}
@@ -0,0 +1,58 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::custom_reader::XbelReader;
+use crate::{encoding, repair, Error, RecentlyUsed};
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// Enough of the start of a document to contain its byte order mark and XML declaration.
+const HEAD_LEN: u64 = 256;
+
+/// Reads the bookmarks which start within the last `bytes_from_end` bytes of a file.
+///
+/// Documents which aren't encoded in UTF-8 cannot be read from an arbitrary offset, and are
+/// read in full instead, as are files no longer than `bytes_from_end`.
+pub(crate) fn read(mut file: File, bytes_from_end: u64) -> Result<RecentlyUsed, Error> {
+    let len = file.metadata().map_err(Error::Metadata)?.len();
+    let start = len.saturating_sub(bytes_from_end);
+
+    let mut head = Vec::new();
+    (&mut file)
+        .take(HEAD_LEN)
+        .read_to_end(&mut head)
+        .map_err(Error::Read)?;
+
+    if start < HEAD_LEN || !encoding::is_utf8(&head)? {
+        file.rewind().map_err(Error::Read)?;
+        return crate::parse_reader(io::BufReader::new(file));
+    }
+
+    let mut tail = Vec::new();
+    file.seek(SeekFrom::Start(start)).map_err(Error::Read)?;
+    file.read_to_end(&mut tail).map_err(Error::Read)?;
+
+    let mut recently_used = RecentlyUsed::default();
+    let Some(first) = repair::find_bookmark(&tail) else {
+        return Ok(recently_used);
+    };
+
+    // The end tag of the document's own root closes the one it is prefixed with.
+    let root = repair::fragment_root();
+    let mut reader = XbelReader::new(root.as_bytes().chain(&tail[first..]))?;
+    reader.read_root().map_err(Error::Deserialization)?;
+
+    while let Some(read) = reader.next_bookmark().map_err(Error::Deserialization)? {
+        #[allow(unused_mut)]
+        let mut bookmark = read.result.map_err(Error::Deserialization)?;
+        // Offsets within the synthesized document don't describe the original file.
+        #[cfg(feature = "spans")]
+        {
+            bookmark.span = None;
+        }
+
+        recently_used.bookmarks.push(bookmark);
+    }
+
+    Ok(recently_used)
+}
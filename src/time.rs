@@ -0,0 +1,114 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{Application, Bookmark};
+use chrono::{DateTime, Duration, SecondsFormat, TimeZone, Utc};
+
+/// A coarse period of time something happened in, for grouping recent files in menus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TimePeriod {
+    /// On the same calendar day, or in the future.
+    Today,
+    /// On the calendar day before.
+    Yesterday,
+    /// Within the calendar days of the last week.
+    ThisWeek,
+    /// More than a week ago.
+    Older,
+}
+
+impl TimePeriod {
+    /// The period `time` falls in, counting calendar days in the time zone of `now`.
+    pub fn of<Tz: TimeZone>(time: DateTime<Utc>, now: &DateTime<Tz>) -> Self {
+        let today = now.date_naive();
+        let day = time.with_timezone(&now.timezone()).date_naive();
+
+        match (today - day).num_days() {
+            ..=0 => Self::Today,
+            1 => Self::Yesterday,
+            2..=6 => Self::ThisWeek,
+            _ => Self::Older,
+        }
+    }
+}
+
+/// Parses an RFC 3339 timestamp with any offset, normalized to UTC.
+pub(crate) fn parse(time: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(time)
+        .ok()
+        .map(|time| time.to_utc())
+}
+
+/// Formats a timestamp as GTK writes them, in UTC with microseconds, such as
+/// `2024-05-01T12:30:00.000000Z`.
+pub(crate) fn format(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+impl Bookmark {
+    /// When the file was added to the list, if the timestamp is valid.
+    pub fn added_time(&self) -> Option<DateTime<Utc>> {
+        parse(&self.added)
+    }
+
+    /// When the file was last modified, if known and the timestamp is valid.
+    pub fn modified_time(&self) -> Option<DateTime<Utc>> {
+        parse(self.modified.as_deref()?)
+    }
+
+    /// When the file was last visited, if known and the timestamp is valid.
+    pub fn visited_time(&self) -> Option<DateTime<Utc>> {
+        parse(self.visited.as_deref()?)
+    }
+
+    /// When the file was last modified, falling back to when it was added for bookmarks
+    /// which don't say, as the specification only requires the latter.
+    pub fn effective_modified(&self) -> Option<DateTime<Utc>> {
+        self.modified_time().or_else(|| self.added_time())
+    }
+
+    /// When the file was last visited, falling back to when it was last modified, and then
+    /// to when it was added.
+    pub fn effective_visited(&self) -> Option<DateTime<Utc>> {
+        self.visited_time().or_else(|| self.effective_modified())
+    }
+
+    /// How long before `now` the file was last visited, as in
+    /// [`Bookmark::effective_visited`]. Negative if the visit is in the future.
+    pub fn visited_ago(&self, now: DateTime<Utc>) -> Option<Duration> {
+        Some(now - self.effective_visited()?)
+    }
+
+    /// The period of time the file was last visited in, as in
+    /// [`Bookmark::effective_visited`], such as [`TimePeriod::Yesterday`].
+    pub fn visited_period<Tz: TimeZone>(&self, now: &DateTime<Tz>) -> Option<TimePeriod> {
+        Some(TimePeriod::of(self.effective_visited()?, now))
+    }
+
+    /// Sets when the file was added to the list.
+    pub fn set_added(&mut self, time: DateTime<Utc>) {
+        self.added = format(time);
+    }
+
+    /// Sets when the file was last modified.
+    pub fn set_modified(&mut self, time: DateTime<Utc>) {
+        self.modified = Some(format(time));
+    }
+
+    /// Sets when the file was last visited.
+    pub fn set_visited(&mut self, time: DateTime<Utc>) {
+        self.visited = Some(format(time));
+    }
+}
+
+impl Application {
+    /// When the application last modified the bookmark, if the timestamp is valid.
+    pub fn modified_time(&self) -> Option<DateTime<Utc>> {
+        parse(&self.modified)
+    }
+
+    /// Sets when the application last modified the bookmark.
+    pub fn set_modified(&mut self, time: DateTime<Utc>) {
+        self.modified = format(time);
+    }
+}
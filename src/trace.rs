@@ -0,0 +1,19 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::ParseWarning;
+
+/// Reports the content a lenient parse skipped, so that damaged files show up in the logs of
+/// applications which don't look at the warnings themselves.
+pub(crate) fn skipped(warnings: &[ParseWarning]) {
+    #[cfg(feature = "tracing")]
+    for warning in warnings {
+        tracing::warn!(
+            offset = warning.offset,
+            error = %warning.error,
+            "skipped malformed content"
+        );
+    }
+    #[cfg(not(feature = "tracing"))]
+    let _ = warnings;
+}
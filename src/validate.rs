@@ -0,0 +1,134 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{Bookmark, RecentlyUsed};
+use chrono::{DateTime, Utc};
+use std::collections::hash_map::{Entry, HashMap};
+use url::Url;
+
+/// A problem found in a document by [`RecentlyUsed::validate`].
+///
+/// Issues found in a bookmark carry its index in [`RecentlyUsed::bookmarks`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum Issue {
+    #[error("bookmark {index}: `{href}` is not a valid URI")]
+    InvalidHref { index: usize, href: String },
+    #[error("bookmark {index}: {attribute} timestamp `{value}` is not RFC 3339")]
+    InvalidTimestamp {
+        index: usize,
+        attribute: &'static str,
+        value: String,
+    },
+    #[error("bookmark {index}: {attribute} timestamp `{value}` is in the future")]
+    FutureTimestamp {
+        index: usize,
+        attribute: &'static str,
+        value: String,
+    },
+    #[error("bookmark {index}: `{href}` was already listed by bookmark {first}")]
+    DuplicateHref {
+        index: usize,
+        first: usize,
+        href: String,
+    },
+    #[error("bookmark {index}: application `{name}` has a count of zero")]
+    ZeroCount { index: usize, name: String },
+    #[error("bookmark {index}: `{mime_type}` is not a valid MIME type")]
+    InvalidMimeType { index: usize, mime_type: String },
+}
+
+/// The timestamp attributes of a bookmark, paired with their names.
+pub(crate) fn timestamps(bookmark: &Bookmark) -> [(&'static str, Option<&String>); 3] {
+    [
+        ("added", Some(&bookmark.added)),
+        ("modified", bookmark.modified.as_ref()),
+        ("visited", bookmark.visited.as_ref()),
+    ]
+}
+
+impl RecentlyUsed {
+    /// Audits the bookmarks for invalid URIs, unparsable or future timestamps, duplicate
+    /// hrefs, applications with a count of zero, and malformed MIME types.
+    ///
+    /// Unlike [`parse_strict`](crate::parse_strict), this checks a document which is already
+    /// in memory, such as one about to be written back out.
+    pub fn validate(&self) -> Vec<Issue> {
+        let now = Utc::now();
+        let mut issues = Vec::new();
+        let mut seen = HashMap::new();
+
+        for (index, bookmark) in self.bookmarks.iter().enumerate() {
+            if Url::parse(&bookmark.href).is_err() {
+                issues.push(Issue::InvalidHref {
+                    index,
+                    href: bookmark.href.clone(),
+                });
+            }
+
+            // Hrefs are compared as bookmarks are, so differently escaped spellings of the
+            // same location are duplicates too.
+            match seen.entry(bookmark.normalized_href()) {
+                Entry::Occupied(entry) => issues.push(Issue::DuplicateHref {
+                    index,
+                    first: *entry.get(),
+                    href: bookmark.href.clone(),
+                }),
+                Entry::Vacant(entry) => {
+                    entry.insert(index);
+                }
+            }
+
+            for (attribute, value) in timestamps(bookmark) {
+                let Some(value) = value else {
+                    continue;
+                };
+
+                match DateTime::parse_from_rfc3339(value) {
+                    Ok(time) if time > now => issues.push(Issue::FutureTimestamp {
+                        index,
+                        attribute,
+                        value: value.clone(),
+                    }),
+                    Ok(_) => (),
+                    Err(_) => issues.push(Issue::InvalidTimestamp {
+                        index,
+                        attribute,
+                        value: value.clone(),
+                    }),
+                }
+            }
+
+            let applications = bookmark
+                .info
+                .iter()
+                .flat_map(|info| &info.metadata)
+                .flat_map(|metadata| &metadata.applications.applications);
+
+            for application in applications {
+                if application.count == 0 {
+                    issues.push(Issue::ZeroCount {
+                        index,
+                        name: application.name.clone(),
+                    });
+                }
+            }
+
+            let mime_types = bookmark
+                .info
+                .iter()
+                .flat_map(|info| &info.metadata)
+                .filter_map(|metadata| metadata.mime_type.as_ref());
+
+            for mime_type in mime_types {
+                if mime_type.mime().is_err() {
+                    issues.push(Issue::InvalidMimeType {
+                        index,
+                        mime_type: mime_type.mime_type.clone(),
+                    });
+                }
+            }
+        }
+
+        issues
+    }
+}
@@ -0,0 +1,89 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::custom_reader::XbelReader;
+use crate::{Application, Bookmark, Error, Info};
+use std::io::BufRead;
+use std::ops::ControlFlow;
+
+/// Receives the contents of a document as it is read by [`visit`].
+///
+/// Every method has a default implementation which does nothing, so only the callbacks of
+/// interest need to be implemented. Returning [`ControlFlow::Break`] from any of them stops
+/// reading the document.
+pub trait XbelVisitor {
+    /// Called for each bookmark, before its applications are visited.
+    fn visit_bookmark(&mut self, bookmark: &Bookmark) -> ControlFlow<()> {
+        let _ = bookmark;
+        ControlFlow::Continue(())
+    }
+
+    /// Called for each application registered on a bookmark.
+    fn visit_application(
+        &mut self,
+        bookmark: &Bookmark,
+        application: &Application,
+    ) -> ControlFlow<()> {
+        let _ = (bookmark, application);
+        ControlFlow::Continue(())
+    }
+}
+
+/// Reads a recently-used.xbel document, passing each bookmark and application to `visitor`.
+///
+/// Only one bookmark is held in memory at a time, which allows computing statistics or
+/// filtering entries without building the entire list.
+///
+/// ```
+/// use recently_used_xbel::{visit, Application, Bookmark, XbelVisitor};
+/// use std::ops::ControlFlow;
+///
+/// #[derive(Default)]
+/// struct Opens(u32);
+///
+/// impl XbelVisitor for Opens {
+///     fn visit_application(&mut self, _: &Bookmark, app: &Application) -> ControlFlow<()> {
+///         self.0 += app.count;
+///         ControlFlow::Continue(())
+///     }
+/// }
+///
+/// let document = r#"<xbel>
+///   <bookmark href="file:///a" added="" modified="" visited="">
+///     <info><metadata owner="http://freedesktop.org"><bookmark:applications>
+///       <bookmark:application name="a" exec="a %u" modified="" count="2"/>
+///       <bookmark:application name="b" exec="b %u" modified="" count="3"/>
+///     </bookmark:applications></metadata></info>
+///   </bookmark>
+/// </xbel>"#;
+///
+/// let mut opens = Opens::default();
+/// visit(document.as_bytes(), &mut opens).unwrap();
+/// assert_eq!(opens.0, 5);
+/// ```
+pub fn visit<R: BufRead>(reader: R, visitor: &mut impl XbelVisitor) -> Result<(), Error> {
+    let mut reader = XbelReader::new(reader)?;
+    reader.read_root().map_err(Error::Deserialization)?;
+
+    while let Some(read) = reader.next_bookmark().map_err(Error::Deserialization)? {
+        let bookmark = read.result.map_err(Error::Deserialization)?;
+
+        if visitor.visit_bookmark(&bookmark).is_break() {
+            break;
+        }
+
+        let applications = bookmark
+            .info
+            .iter()
+            .filter_map(Info::freedesktop)
+            .flat_map(|metadata| &metadata.applications.applications);
+
+        for application in applications {
+            if visitor.visit_application(&bookmark, application).is_break() {
+                return Ok(());
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,452 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! Generic XBEL 1.0 documents.
+//!
+//! The recently-used.xbel file is a flat list of bookmarks, but XBEL itself also describes
+//! bookmark collections with nested folders, titles, and separators, such as those exported
+//! by web browsers. [`Document`] models the full format, converts to and from
+//! [`RecentlyUsed`] by flattening its bookmarks, and is written back with its structure
+//! intact by [`write`].
+
+use crate::custom_reader::{self, Element, XbelReader, ROOT_ATTRIBUTES};
+use crate::custom_writer::{self, attr, unknown_attributes, write_text_element};
+use crate::xml::{self, Writer};
+use crate::DeError;
+use crate::{
+    Error, Info, Prolog, RecentlyUsed, Unknown, WriteOptions, BOOKMARK_NAMESPACE, MIME_NAMESPACE,
+    XBEL_VERSION,
+};
+use chrono::Utc;
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+/// An XBEL document.
+#[derive(Debug, Clone, Default)]
+pub struct Document {
+    /// The version of XBEL the document conforms to.
+    pub version: Option<String>,
+    /// The title of the collection.
+    pub title: Option<String>,
+    /// A description of the collection.
+    pub desc: Option<String>,
+    /// The freedesktop.org metadata of the collection, if it has any.
+    pub info: Option<Info>,
+    /// The bookmarks, folders, aliases, and separators at the top level of the collection.
+    pub items: Vec<Item>,
+    /// The XML declaration and DOCTYPE which preceded the root element.
+    pub prolog: Prolog,
+    /// Attributes and children of the root element which aren't modeled.
+    pub unknown: Unknown,
+}
+
+/// An entry of a [`Document`] or [`Folder`].
+#[derive(Debug, Clone)]
+pub enum Item {
+    Bookmark(Bookmark),
+    Folder(Folder),
+    Alias(Alias),
+    Separator,
+}
+
+/// A folder grouping other items.
+#[derive(Debug, Clone)]
+pub struct Folder {
+    /// A document-unique identifier, which aliases may refer to.
+    pub id: Option<String>,
+    /// Whether the folder is shown collapsed. Defaults to `true`.
+    pub folded: bool,
+    /// The title of the folder.
+    pub title: Option<String>,
+    /// A description of the folder.
+    pub desc: Option<String>,
+    /// The items contained in the folder.
+    pub items: Vec<Item>,
+    /// Attributes and children of the folder which aren't modeled.
+    pub unknown: Unknown,
+}
+
+/// A bookmark of an XBEL document.
+///
+/// Unlike in recently-used.xbel, every attribute other than the href is optional.
+#[derive(Debug, Clone, Default)]
+pub struct Bookmark {
+    /// The location of the bookmarked resource.
+    pub href: String,
+    /// A document-unique identifier, which aliases may refer to.
+    pub id: Option<String>,
+    /// When the bookmark was added.
+    pub added: Option<String>,
+    /// When the resource was last modified.
+    pub modified: Option<String>,
+    /// When the resource was last visited.
+    pub visited: Option<String>,
+    /// The title of the bookmark.
+    pub title: Option<String>,
+    /// A description of the bookmark.
+    pub desc: Option<String>,
+    /// The freedesktop.org metadata of the bookmark, if it has any.
+    pub info: Option<Info>,
+    /// Attributes and children of the bookmark which aren't modeled.
+    pub unknown: Unknown,
+}
+
+/// A reference to a bookmark or folder elsewhere in the document.
+#[derive(Debug, Clone)]
+pub struct Alias {
+    /// The id of the referenced item.
+    pub reference: String,
+}
+
+impl Document {
+    /// Iterates over every bookmark of the document, including those nested in folders.
+    pub fn bookmarks(&self) -> Bookmarks<'_> {
+        Bookmarks {
+            stack: vec![self.items.iter()],
+        }
+    }
+}
+
+/// Iterates over every bookmark of a [`Document`], depth-first.
+pub struct Bookmarks<'a> {
+    stack: Vec<std::slice::Iter<'a, Item>>,
+}
+
+impl<'a> Iterator for Bookmarks<'a> {
+    type Item = &'a Bookmark;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.stack.last_mut()?.next() {
+                Some(Item::Bookmark(bookmark)) => return Some(bookmark),
+                Some(Item::Folder(folder)) => self.stack.push(folder.items.iter()),
+                Some(_) => (),
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+    }
+}
+
+/// Parses an XBEL document from any buffered reader.
+pub fn parse_reader<R: BufRead>(reader: R) -> Result<Document, Error> {
+    let mut reader = XbelReader::new(reader)?;
+    let (prolog, root) = reader.read_document().map_err(Error::Deserialization)?;
+    document(prolog, &root).map_err(Error::Deserialization)
+}
+
+/// Parses an XBEL file at the given location.
+pub fn parse_path(path: impl AsRef<Path>) -> Result<Document, Error> {
+    parse_reader(crate::open(path.as_ref())?)
+}
+
+fn document(prolog: Prolog, root: &Element) -> Result<Document, DeError> {
+    Ok(Document {
+        version: root.attribute("version")?,
+        title: optional_text(root, "title")?,
+        desc: optional_text(root, "desc")?,
+        info: optional_info(root)?,
+        items: items(root)?,
+        prolog,
+        unknown: root.unknown(ROOT_ATTRIBUTES, |el| {
+            is_item(el)
+                || ["title", "desc", "info"]
+                    .iter()
+                    .any(|name| el.is(None, name))
+        })?,
+    })
+}
+
+fn is_item(element: &Element) -> bool {
+    ["bookmark", "folder", "alias", "separator"]
+        .iter()
+        .any(|name| element.is(None, name))
+}
+
+fn items(parent: &Element) -> Result<Vec<Item>, DeError> {
+    let mut items = Vec::new();
+
+    for element in &parent.children {
+        let item = if element.is(None, "bookmark") {
+            Item::Bookmark(bookmark(element)?)
+        } else if element.is(None, "folder") {
+            Item::Folder(Folder {
+                id: element.attribute("id")?,
+                folded: element.attribute("folded")?.as_deref() != Some("no"),
+                title: optional_text(element, "title")?,
+                desc: optional_text(element, "desc")?,
+                items: self::items(element)?,
+                unknown: element.unknown(&["id", "folded"], |el| {
+                    is_item(el) || el.is(None, "title") || el.is(None, "desc")
+                })?,
+            })
+        } else if element.is(None, "alias") {
+            Item::Alias(Alias {
+                reference: element.required_attribute("ref")?,
+            })
+        } else if element.is(None, "separator") {
+            Item::Separator
+        } else {
+            continue;
+        };
+
+        items.push(item);
+    }
+
+    Ok(items)
+}
+
+fn bookmark(element: &Element) -> Result<Bookmark, DeError> {
+    Ok(Bookmark {
+        href: element.required_attribute("href")?,
+        id: element.attribute("id")?,
+        added: element.attribute("added")?,
+        modified: element.attribute("modified")?,
+        visited: element.attribute("visited")?,
+        title: optional_text(element, "title")?,
+        desc: optional_text(element, "desc")?,
+        info: optional_info(element)?,
+        unknown: element.unknown(&["href", "id", "added", "modified", "visited"], |el| {
+            ["title", "desc", "info"]
+                .iter()
+                .any(|name| el.is(None, name))
+        })?,
+    })
+}
+
+fn optional_text(parent: &Element, name: &str) -> Result<Option<String>, DeError> {
+    parent.child(None, name).map(Element::text).transpose()
+}
+
+fn optional_info(parent: &Element) -> Result<Option<Info>, DeError> {
+    parent
+        .child(None, "info")
+        .map(custom_reader::info)
+        .transpose()
+}
+
+/// Serializes an XBEL document to a string.
+pub fn write(document: &Document) -> Result<String, Error> {
+    let mut bytes = Vec::new();
+    write_to(document, &mut bytes)?;
+    String::from_utf8(bytes).map_err(|_| Error::Serialization(None))
+}
+
+/// Serializes an XBEL document directly into a writer.
+///
+/// Folders, aliases, and separators are written where they were, and the freedesktop.org
+/// namespaces are only declared if a bookmark has metadata.
+pub fn write_to<W: Write>(document: &Document, writer: W) -> Result<(), Error> {
+    let mut writer = Writer::new_with_indent(writer, b' ', 2);
+    write_document(&mut writer, document).map_err(custom_writer::write_error)
+}
+
+fn write_document<W: Write>(writer: &mut Writer<W>, document: &Document) -> Result<(), xml::Error> {
+    let prolog = &document.prolog;
+    let declaration = prolog
+        .declaration
+        .as_deref()
+        .unwrap_or(custom_writer::DEFAULT_DECLARATION);
+    for markup in [Some(declaration), prolog.doctype.as_deref()]
+        .into_iter()
+        .flatten()
+    {
+        let inner = writer.get_mut();
+        inner.write_all(markup.as_bytes())?;
+        inner.write_all(b"\n")?;
+    }
+
+    let namespaces = (document.info.is_some()
+        || document.bookmarks().any(|bookmark| bookmark.info.is_some()))
+    .then_some([
+        attr("xmlns:bookmark", BOOKMARK_NAMESPACE),
+        attr("xmlns:mime", MIME_NAMESPACE),
+    ]);
+
+    writer
+        .create_element("xbel")
+        .with_attributes(
+            document
+                .version
+                .as_deref()
+                .map(|version| attr("version", version))
+                .into_iter()
+                .chain(namespaces.into_iter().flatten())
+                .chain(unknown_attributes(&document.unknown)),
+        )
+        .write_inner_content::<_, xml::Error>(|writer| {
+            write_titles(writer, &document.title, &document.desc)?;
+            write_info(writer, &document.info)?;
+            write_items(writer, &document.items)?;
+            write_unknown(writer, &document.unknown)
+        })?;
+
+    writer.get_mut().write_all(b"\n")?;
+    Ok(())
+}
+
+fn write_titles<W: Write>(
+    writer: &mut Writer<W>,
+    title: &Option<String>,
+    desc: &Option<String>,
+) -> Result<(), xml::Error> {
+    if let Some(title) = title {
+        write_text_element(writer, "title", title)?;
+    }
+    if let Some(desc) = desc {
+        write_text_element(writer, "desc", desc)?;
+    }
+    Ok(())
+}
+
+fn write_info<W: Write>(writer: &mut Writer<W>, info: &Option<Info>) -> Result<(), xml::Error> {
+    match info {
+        Some(info) => custom_writer::write_info(writer, info, &WriteOptions::default()),
+        None => Ok(()),
+    }
+}
+
+fn write_unknown<W: Write>(writer: &mut Writer<W>, unknown: &Unknown) -> Result<(), xml::Error> {
+    custom_writer::write_unknown_elements(writer, unknown, &WriteOptions::default())
+}
+
+fn write_items<W: Write>(writer: &mut Writer<W>, items: &[Item]) -> Result<(), xml::Error> {
+    for item in items {
+        match item {
+            Item::Bookmark(bookmark) => write_bookmark(writer, bookmark)?,
+            Item::Folder(folder) => {
+                writer
+                    .create_element("folder")
+                    .with_attributes(
+                        folder
+                            .id
+                            .as_deref()
+                            .map(|id| attr("id", id))
+                            .into_iter()
+                            .chain((!folder.folded).then(|| attr("folded", "no")))
+                            .chain(unknown_attributes(&folder.unknown)),
+                    )
+                    .write_inner_content::<_, xml::Error>(|writer| {
+                        write_titles(writer, &folder.title, &folder.desc)?;
+                        write_items(writer, &folder.items)?;
+                        write_unknown(writer, &folder.unknown)
+                    })?;
+            }
+            Item::Alias(alias) => {
+                writer
+                    .create_element("alias")
+                    .with_attribute(attr("ref", &alias.reference))
+                    .write_empty()?;
+            }
+            Item::Separator => {
+                writer.create_element("separator").write_empty()?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn write_bookmark<W: Write>(writer: &mut Writer<W>, bookmark: &Bookmark) -> Result<(), xml::Error> {
+    let attributes = [
+        ("id", &bookmark.id),
+        ("added", &bookmark.added),
+        ("modified", &bookmark.modified),
+        ("visited", &bookmark.visited),
+    ];
+    let element = writer.create_element("bookmark").with_attributes(
+        [attr("href", &bookmark.href)].into_iter().chain(
+            attributes
+                .iter()
+                .filter_map(|(key, value)| Some(attr(key, value.as_deref()?)))
+                .chain(unknown_attributes(&bookmark.unknown)),
+        ),
+    );
+
+    if bookmark.title.is_none()
+        && bookmark.desc.is_none()
+        && bookmark.info.is_none()
+        && bookmark.unknown.elements.is_empty()
+    {
+        element.write_empty()?;
+        return Ok(());
+    }
+
+    element.write_inner_content::<_, xml::Error>(|writer| {
+        write_titles(writer, &bookmark.title, &bookmark.desc)?;
+        write_info(writer, &bookmark.info)?;
+        write_unknown(writer, &bookmark.unknown)
+    })?;
+    Ok(())
+}
+
+impl From<Document> for RecentlyUsed {
+    fn from(document: Document) -> Self {
+        RecentlyUsed {
+            version: document
+                .version
+                .clone()
+                .unwrap_or_else(|| XBEL_VERSION.to_owned()),
+            xmlns_bookmark: BOOKMARK_NAMESPACE.to_owned(),
+            xmlns_mime: MIME_NAMESPACE.to_owned(),
+            title: document.title.clone(),
+            desc: document.desc.clone(),
+            info: document.info.clone(),
+            bookmarks: document
+                .bookmarks()
+                .map(|bookmark| crate::Bookmark {
+                    href: bookmark.href.clone(),
+                    // Every bookmark of recently-used.xbel needs to have been added, at the
+                    // latest when it was last used.
+                    added: bookmark
+                        .added
+                        .as_ref()
+                        .or(bookmark.modified.as_ref())
+                        .or(bookmark.visited.as_ref())
+                        .cloned()
+                        .unwrap_or_else(|| crate::time::format(Utc::now())),
+                    modified: bookmark.modified.clone(),
+                    visited: bookmark.visited.clone(),
+                    title: bookmark.title.clone(),
+                    desc: bookmark.desc.clone(),
+                    info: bookmark.info.clone(),
+                    #[cfg(feature = "spans")]
+                    span: None,
+                    unknown: bookmark.unknown.clone(),
+                })
+                .collect(),
+            prolog: document.prolog,
+            unknown: document.unknown,
+        }
+    }
+}
+
+impl From<RecentlyUsed> for Document {
+    fn from(recently_used: RecentlyUsed) -> Self {
+        Document {
+            version: Some(recently_used.version),
+            title: recently_used.title,
+            desc: recently_used.desc,
+            info: recently_used.info,
+            items: recently_used
+                .bookmarks
+                .into_iter()
+                .map(|bookmark| {
+                    Item::Bookmark(Bookmark {
+                        href: bookmark.href,
+                        added: Some(bookmark.added),
+                        modified: bookmark.modified,
+                        visited: bookmark.visited,
+                        title: bookmark.title,
+                        desc: bookmark.desc,
+                        info: bookmark.info,
+                        unknown: bookmark.unknown,
+                        ..Bookmark::default()
+                    })
+                })
+                .collect(),
+            prolog: recently_used.prolog,
+            unknown: recently_used.unknown,
+        }
+    }
+}
@@ -0,0 +1,20 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: MPL-2.0
+
+//! The XML parser and writer that documents are read and written with: quick-xml, or the
+//! crate's own lean one when the `quick-xml` feature is disabled.
+
+#[cfg(not(feature = "quick-xml"))]
+pub(crate) use crate::lean_xml::{
+    escape, unescape, AttrError, Attribute, BytesStart, BytesText, Error, EscapeError, Event,
+    NsReader, QName, ResolveResult, Writer,
+};
+#[cfg(feature = "quick-xml")]
+pub(crate) use quick_xml::{
+    escape::{escape, unescape, EscapeError},
+    events::attributes::{AttrError, Attribute},
+    events::{BytesStart, BytesText, Event},
+    name::{QName, ResolveResult},
+    writer::Writer,
+    Error, NsReader,
+};